@@ -0,0 +1,21 @@
+use minus::error::MinusError;
+use std::time::Duration;
+use tokio::{join, time::sleep};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), MinusError> {
+    let output = minus::Pager::with_capacity(10);
+
+    let increment = async {
+        for i in 0..=100_u32 {
+            output.push_str_async(format!("{}\n", i)).await?;
+            sleep(Duration::from_millis(100)).await;
+        }
+        Result::<_, MinusError>::Ok(())
+    };
+
+    let (res1, res2) = join!(output.run_async(), increment);
+    res1?;
+    res2?;
+    Ok(())
+}
@@ -9,24 +9,12 @@
 
 use std::env::args;
 use std::fs::File;
-use std::io::{BufReader, Read};
-use std::thread;
 
 fn read_file(name: String, pager: minus::Pager) -> Result<(), Box<dyn std::error::Error>> {
     let file = File::open(name)?;
-    let changes = || {
-        let mut buff = String::new();
-        let mut buf_reader = BufReader::new(file);
-        buf_reader.read_to_string(&mut buff)?;
-        pager.push_str(&buff)?;
-        Result::<(), Box<dyn std::error::Error>>::Ok(())
-    };
-
-    let pager = pager.clone();
-    let res1 = thread::spawn(|| minus::dynamic_paging(pager));
-    let res2 = changes();
-    res1.join().unwrap()?;
-    res2?;
+    // Read the file in the background while the pager is running
+    pager.push_reader(file)?;
+    minus::dynamic_paging(pager)?;
     Ok(())
 }
 
@@ -0,0 +1,169 @@
+//! Provides the [`Terminal`] and [`EventSource`] traits
+//!
+//! By default minus draws on the standard output and reads user input from the terminal
+//! attached to it using crossterm. Applications that own their output stream, like a pseudo
+//! terminal that they have allocated or an in-memory buffer used for snapshot tests, can
+//! implement these traits and hand them to [`Pager::run_with`](crate::Pager::run_with).
+//!
+//! minus writes ANSI escape sequences to the [`Terminal`], hence whatever sits on the other end
+//! of it must be able to interpret them.
+
+use crate::error::{CleanupError, SetupError};
+use crate::minus_core::utils::term;
+use crossterm::{event::Event, tty::IsTty};
+use std::{io, time::Duration};
+
+/// A destination where minus draws the pager
+///
+/// Apart from being a [`Write`](io::Write) type, it tells minus about its size and how to
+/// prepare and restore it. Only [`size`](Terminal::size) is required, the rest have defaults
+/// suitable for outputs that need no preparation, like in-memory buffers.
+pub trait Terminal: io::Write + Send {
+    /// Returns the size of the terminal as `(columns, rows)`
+    ///
+    /// # Errors
+    /// Returns an error if the size could not be determined
+    fn size(&self) -> io::Result<(u16, u16)>;
+
+    /// Returns whether this is an interactive terminal
+    ///
+    /// In static mode, minus simply writes all the data and quits if this returns `false`.
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    /// Prepare the terminal for paging
+    ///
+    /// This is called right before the pager starts drawing.
+    ///
+    /// # Errors
+    /// Returns a [`SetupError`] if the terminal could not be prepared
+    fn setup(&mut self) -> Result<(), SetupError> {
+        Ok(())
+    }
+
+    /// Restore the terminal to the state it was before [`setup`](Terminal::setup)
+    ///
+    /// This is called when the pager quits.
+    ///
+    /// # Errors
+    /// Returns a [`CleanupError`] if the terminal could not be restored
+    fn cleanup(&mut self) -> Result<(), CleanupError> {
+        Ok(())
+    }
+
+    /// Prepare the terminal for paging inline in `rows` rows below the cursor
+    ///
+    /// This is called instead of [`setup`](Terminal::setup) when the pager is
+    /// [drawn inline](crate::Pager::set_inline). It must make room for `rows` rows below the
+    /// cursor, scrolling the text above it up if needed, and return the row where the pager
+    /// starts. The default calls [`setup`](Terminal::setup) and starts at the first row.
+    ///
+    /// # Errors
+    /// Returns a [`SetupError`] if the terminal could not be prepared
+    fn setup_inline(&mut self, rows: u16) -> Result<u16, SetupError> {
+        let _ = rows;
+        self.setup().map(|()| 0)
+    }
+
+    /// Restore the terminal to the state it was before [`setup_inline`](Terminal::setup_inline)
+    ///
+    /// The text drawn by the pager must be left on the screen. The default calls
+    /// [`cleanup`](Terminal::cleanup).
+    ///
+    /// # Errors
+    /// Returns a [`CleanupError`] if the terminal could not be restored
+    fn cleanup_inline(&mut self) -> Result<(), CleanupError> {
+        self.cleanup()
+    }
+
+    /// Function that restores the terminal if the pager panics
+    ///
+    /// It runs inside the panic hook hence it cannot access `self`.
+    fn panic_cleanup(&self) -> Option<fn()> {
+        None
+    }
+}
+
+impl Terminal for io::Stdout {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        if IsTty::is_tty(self) {
+            crossterm::terminal::size()
+        } else {
+            // For other cases beyond control
+            Ok((1, 1))
+        }
+    }
+
+    fn is_interactive(&self) -> bool {
+        IsTty::is_tty(self)
+    }
+
+    fn setup(&mut self) -> Result<(), SetupError> {
+        term::setup(self)
+    }
+
+    fn cleanup(&mut self) -> Result<(), CleanupError> {
+        term::cleanup(self, &crate::ExitStrategy::PagerQuit, true)
+    }
+
+    fn setup_inline(&mut self, rows: u16) -> Result<u16, SetupError> {
+        term::setup_inline(self, rows)
+    }
+
+    fn cleanup_inline(&mut self) -> Result<(), CleanupError> {
+        term::cleanup_inline(self)
+    }
+
+    fn panic_cleanup(&self) -> Option<fn()> {
+        Some(|| {
+            // While silently ignoring error is considered a bad practice, we are forced to do it here
+            // as we cannot use the ? and panicking here will cause UB.
+            drop(term::cleanup(
+                io::stdout(),
+                &crate::ExitStrategy::PagerQuit,
+                true,
+            ));
+        })
+    }
+}
+
+/// A source of terminal events like key presses, mouse actions and resizes
+///
+/// minus polls this from a separate thread while the pager is running. The search prompt also
+/// reads from it directly while it is open. While the pager is suspended, for example to run an
+/// editor, it is not polled at all so that the input goes to the program that was started.
+pub trait EventSource: Send {
+    /// Wait for at most `timeout` for an event to become available
+    ///
+    /// Returns `true` if an event is available to be [`read`](EventSource::read).
+    ///
+    /// # Errors
+    /// Returns an error if the source could not be polled
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool>;
+
+    /// Read the next event
+    ///
+    /// This is only called after [`poll`](EventSource::poll) has returned `true`.
+    ///
+    /// # Errors
+    /// Returns an error if the event could not be read
+    fn read(&mut self) -> io::Result<Event>;
+}
+
+/// Reads events from the terminal using crossterm
+///
+/// This is the [`EventSource`] used by [`dynamic_paging`](crate::dynamic_paging) and
+/// [`page_all`](crate::page_all).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CrosstermEvents;
+
+impl EventSource for CrosstermEvents {
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        crossterm::event::poll(timeout)
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        crossterm::event::read()
+    }
+}
@@ -4,14 +4,20 @@
 //! the [`ev_handler`](super::ev_handler).
 
 use std::fmt::Debug;
+use std::path::PathBuf;
 
 use crate::{
+    hints::HintAction,
     input::{InputClassifier, InputEvent},
+    minus_core::utils::syntax::SyntaxHighlighting,
+    prompt::PromptTheme,
+    save::SaveOptions,
+    screen::{gutter::Gutter, storage::TextStore},
     ExitStrategy, LineNumbers,
 };
 
 #[cfg(feature = "search")]
-use crate::search::SearchOpts;
+use crate::search::{CaseSensitivity, Filter, Highlight, SearchOpts};
 
 /// Different events that can be encountered while the pager is running
 #[non_exhaustive]
@@ -22,25 +28,67 @@ pub enum Command {
     // Data related
     AppendData(String),
     SetData(String),
+    ReaderStarted,
+    ReaderFinished,
+    EndOfInput,
+    AnimateSpinner,
+    SetStorage(Box<dyn TextStore>),
+    SetMaxFormattedRows(Option<usize>),
+    SetMaxLines(Option<usize>),
+    SaveToFile(PathBuf, SaveOptions),
+    SetSaveOptions(SaveOptions),
+    AllowSaving(bool),
+
+    // Buffer related
+    AddBuffer(String, String),
+    AppendBufferData(String, String),
+    RemoveBuffer(String),
+    SwitchBuffer(String),
 
     // Prompt related
     SendMessage(String),
     ShowPrompt(bool),
     SetPrompt(String),
+    SetPromptFormat(Option<String>),
+    SetPromptTheme(PromptTheme),
 
     // Screen output configurations
     LineWrapping(bool),
     SetLineNumbers(LineNumbers),
+    SetGutter(Gutter),
     FollowOutput(bool),
+    SplitView(bool),
+    SetHighlighter(Option<SyntaxHighlighting>),
+    SetHintAction(Option<HintAction>),
+    #[cfg(feature = "search")]
+    SetFilter(Option<Filter>),
+    #[cfg(feature = "search")]
+    AddHighlight(Highlight),
+    #[cfg(feature = "search")]
+    RemoveHighlight(String),
+    #[cfg(feature = "search")]
+    ClearHighlights,
 
     // Configuration options
     SetExitStrategy(ExitStrategy),
     SetInputClassifier(Box<dyn InputClassifier + Send + Sync + 'static>),
     AddExitCallback(Box<dyn FnMut() + Send + Sync + 'static>),
-    #[cfg(feature = "static_output")]
+    AllowExternalCommands(bool),
+    SetInline(Option<usize>),
     SetRunNoOverflow(bool),
     #[cfg(feature = "search")]
     IncrementalSearchCondition(Box<dyn Fn(&SearchOpts) -> bool + Send + Sync + 'static>),
+    #[cfg(feature = "search")]
+    SetSearchCase(CaseSensitivity),
+    #[cfg(feature = "search")]
+    SetLiteralSearch(bool),
+    #[cfg(feature = "search")]
+    AddSearchHistory(Vec<String>),
+    #[cfg(feature = "search")]
+    SetSearchHistoryFile(PathBuf),
+
+    // Terminal related
+    SuspendWith(Box<dyn FnOnce() + Send + 'static>),
 
     // Internal commands
     FormatRedrawPrompt,
@@ -53,17 +101,57 @@ impl PartialEq for Command {
             (Self::SetData(d1), Self::SetData(d2))
             | (Self::AppendData(d1), Self::AppendData(d2))
             | (Self::SetPrompt(d1), Self::SetPrompt(d2))
-            | (Self::SendMessage(d1), Self::SendMessage(d2)) => d1 == d2,
+            | (Self::SendMessage(d1), Self::SendMessage(d2))
+            | (Self::RemoveBuffer(d1), Self::RemoveBuffer(d2))
+            | (Self::SwitchBuffer(d1), Self::SwitchBuffer(d2)) => d1 == d2,
+            (Self::AddBuffer(n1, d1), Self::AddBuffer(n2, d2))
+            | (Self::AppendBufferData(n1, d1), Self::AppendBufferData(n2, d2)) => {
+                n1 == n2 && d1 == d2
+            }
+            (Self::SetPromptFormat(d1), Self::SetPromptFormat(d2)) => d1 == d2,
+            (Self::SetPromptTheme(d1), Self::SetPromptTheme(d2)) => d1 == d2,
             (Self::LineWrapping(d1), Self::LineWrapping(d2)) => d1 == d2,
             (Self::SetLineNumbers(d1), Self::SetLineNumbers(d2)) => d1 == d2,
-            (Self::ShowPrompt(d1), Self::ShowPrompt(d2)) => d1 == d2,
+            (Self::ShowPrompt(d1), Self::ShowPrompt(d2))
+            | (Self::SplitView(d1), Self::SplitView(d2)) => d1 == d2,
+            (Self::SetMaxFormattedRows(d1), Self::SetMaxFormattedRows(d2))
+            | (Self::SetMaxLines(d1), Self::SetMaxLines(d2)) => d1 == d2,
             (Self::SetExitStrategy(d1), Self::SetExitStrategy(d2)) => d1 == d2,
-            #[cfg(feature = "static_output")]
+            (Self::SaveToFile(p1, o1), Self::SaveToFile(p2, o2)) => p1 == p2 && o1 == o2,
+            (Self::SetSaveOptions(d1), Self::SetSaveOptions(d2)) => d1 == d2,
+            (Self::AllowSaving(d1), Self::AllowSaving(d2)) => d1 == d2,
+            (Self::AllowExternalCommands(d1), Self::AllowExternalCommands(d2)) => d1 == d2,
+            (Self::SetInline(d1), Self::SetInline(d2)) => d1 == d2,
             (Self::SetRunNoOverflow(d1), Self::SetRunNoOverflow(d2)) => d1 == d2,
             (Self::SetInputClassifier(_), Self::SetInputClassifier(_))
-            | (Self::AddExitCallback(_), Self::AddExitCallback(_)) => true,
+            | (Self::AddExitCallback(_), Self::AddExitCallback(_))
+            | (Self::SuspendWith(_), Self::SuspendWith(_))
+            | (Self::SetStorage(_), Self::SetStorage(_))
+            | (Self::SetGutter(_), Self::SetGutter(_)) => true,
+            (Self::SetHighlighter(h1), Self::SetHighlighter(h2)) => h1.is_some() == h2.is_some(),
+            (Self::SetHintAction(a1), Self::SetHintAction(a2)) => a1.is_some() == a2.is_some(),
             #[cfg(feature = "search")]
             (Self::IncrementalSearchCondition(_), Self::IncrementalSearchCondition(_)) => true,
+            #[cfg(feature = "search")]
+            (Self::SetFilter(d1), Self::SetFilter(d2)) => d1 == d2,
+            #[cfg(feature = "search")]
+            (Self::AddHighlight(d1), Self::AddHighlight(d2)) => d1 == d2,
+            #[cfg(feature = "search")]
+            (Self::RemoveHighlight(d1), Self::RemoveHighlight(d2)) => d1 == d2,
+            #[cfg(feature = "search")]
+            (Self::ClearHighlights, Self::ClearHighlights) => true,
+            (Self::ReaderStarted, Self::ReaderStarted)
+            | (Self::ReaderFinished, Self::ReaderFinished)
+            | (Self::EndOfInput, Self::EndOfInput)
+            | (Self::AnimateSpinner, Self::AnimateSpinner) => true,
+            #[cfg(feature = "search")]
+            (Self::SetSearchCase(d1), Self::SetSearchCase(d2)) => d1 == d2,
+            #[cfg(feature = "search")]
+            (Self::SetLiteralSearch(d1), Self::SetLiteralSearch(d2)) => d1 == d2,
+            #[cfg(feature = "search")]
+            (Self::AddSearchHistory(d1), Self::AddSearchHistory(d2)) => d1 == d2,
+            #[cfg(feature = "search")]
+            (Self::SetSearchHistoryFile(d1), Self::SetSearchHistoryFile(d2)) => d1 == d2,
             _ => false,
         }
     }
@@ -76,20 +164,66 @@ impl Debug for Command {
             Self::AppendData(text) => write!(f, "AppendData({:?})", text),
             Self::SetPrompt(text) => write!(f, "SetPrompt({:?})", text),
             Self::SendMessage(text) => write!(f, "SendMessage({:?})", text),
+            Self::SetPromptFormat(format) => write!(f, "SetPromptFormat({format:?})"),
+            Self::SetPromptTheme(theme) => write!(f, "SetPromptTheme({theme:?})"),
             Self::SetLineNumbers(ln) => write!(f, "SetLineNumbers({:?})", ln),
+            Self::SetGutter(gutter) => write!(f, "SetGutter({gutter:?})"),
             Self::LineWrapping(lw) => write!(f, "LineWrapping({:?})", lw),
             Self::SetExitStrategy(es) => write!(f, "SetExitStrategy({:?})", es),
             Self::SetInputClassifier(_) => write!(f, "SetInputClassifier"),
             Self::ShowPrompt(show) => write!(f, "ShowPrompt({show:?})"),
+            Self::ReaderStarted => write!(f, "ReaderStarted"),
+            Self::ReaderFinished => write!(f, "ReaderFinished"),
+            Self::EndOfInput => write!(f, "EndOfInput"),
+            Self::AnimateSpinner => write!(f, "AnimateSpinner"),
+            Self::SetStorage(_) => write!(f, "SetStorage"),
+            Self::SetMaxFormattedRows(max) => write!(f, "SetMaxFormattedRows({max:?})"),
+            Self::SetMaxLines(max) => write!(f, "SetMaxLines({max:?})"),
+            Self::SaveToFile(path, options) => write!(f, "SaveToFile({path:?}, {options:?})"),
+            Self::SetSaveOptions(options) => write!(f, "SetSaveOptions({options:?})"),
+            Self::AllowSaving(allowed) => write!(f, "AllowSaving({allowed:?})"),
+            Self::AddBuffer(name, text) => write!(f, "AddBuffer({name:?}, {text:?})"),
+            Self::AppendBufferData(name, text) => {
+                write!(f, "AppendBufferData({name:?}, {text:?})")
+            }
+            Self::RemoveBuffer(name) => write!(f, "RemoveBuffer({name:?})"),
+            Self::SwitchBuffer(name) => write!(f, "SwitchBuffer({name:?})"),
             Self::FormatRedrawPrompt => write!(f, "FormatRedrawPrompt"),
             Self::FormatRedrawDisplay => write!(f, "FormatRedrawDisplay"),
             #[cfg(feature = "search")]
             Self::IncrementalSearchCondition(_) => write!(f, "IncrementalSearchCondition"),
             Self::AddExitCallback(_) => write!(f, "AddExitCallback"),
-            #[cfg(feature = "static_output")]
+            Self::AllowExternalCommands(allowed) => write!(f, "AllowExternalCommands({allowed:?})"),
+            Self::SetInline(rows) => write!(f, "SetInline({rows:?})"),
+            Self::SuspendWith(_) => write!(f, "SuspendWith"),
             Self::SetRunNoOverflow(val) => write!(f, "SetRunNoOverflow({val:?})"),
             Self::UserInput(input) => write!(f, "UserInput({input:?})"),
             Self::FollowOutput(follow_output) => write!(f, "FollowOutput({follow_output:?})"),
+            Self::SplitView(split) => write!(f, "SplitView({split:?})"),
+            Self::SetHighlighter(syntax) => {
+                write!(
+                    f,
+                    "SetHighlighter({})",
+                    if syntax.is_some() { "Some" } else { "None" }
+                )
+            }
+            Self::SetHintAction(action) => write!(f, "SetHintAction({action:?})"),
+            #[cfg(feature = "search")]
+            Self::SetFilter(filter) => write!(f, "SetFilter({filter:?})"),
+            #[cfg(feature = "search")]
+            Self::AddHighlight(highlight) => write!(f, "AddHighlight({highlight:?})"),
+            #[cfg(feature = "search")]
+            Self::RemoveHighlight(pattern) => write!(f, "RemoveHighlight({pattern:?})"),
+            #[cfg(feature = "search")]
+            Self::ClearHighlights => write!(f, "ClearHighlights"),
+            #[cfg(feature = "search")]
+            Self::SetSearchCase(case) => write!(f, "SetSearchCase({case:?})"),
+            #[cfg(feature = "search")]
+            Self::SetLiteralSearch(literal) => write!(f, "SetLiteralSearch({literal:?})"),
+            #[cfg(feature = "search")]
+            Self::AddSearchHistory(queries) => write!(f, "AddSearchHistory({queries:?})"),
+            #[cfg(feature = "search")]
+            Self::SetSearchHistoryFile(path) => write!(f, "SetSearchHistoryFile({path:?})"),
         }
     }
 }
@@ -1,53 +1,142 @@
 //! Provides the [`handle_event`] function
 
-use std::convert::TryInto;
-use std::io::Write;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
 use std::sync::{atomic::AtomicBool, Arc};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[cfg(feature = "search")]
+use crossterm::event::{Event, KeyCode, KeyEvent};
 use parking_lot::{Condvar, Mutex};
 
-use super::utils::display::{self, AppendStyle};
+use super::commands::Command;
+use super::utils::{
+    display::{self, AppendStyle},
+    line_input,
+    selection::Selection,
+};
 use super::CommandQueue;
-use super::{commands::Command, utils::term};
 #[cfg(feature = "search")]
 use crate::search;
-use crate::{error::MinusError, input::InputEvent, PagerState};
+use crate::state::GotoTarget;
+use crate::{
+    backend::{EventSource, Terminal},
+    error::{MinusError, SetupError},
+    hints::{self, Hint, HintAction, Hints},
+    input::InputEvent,
+    save::{SaveOptions, SaveRegion},
+    PagerState,
+};
 
 /// Respond based on the type of command
 ///
 /// It will match the type of event received and based on that, it can take actions like:-
 /// - Mutating fields of [`PagerState`]
-/// - Handle cleanup and exits
+/// - Handle exits
 /// - Call search related functions
-#[cfg_attr(not(feature = "search"), allow(unused_mut))]
+///
+/// The terminal itself is cleaned up by the caller once `is_exited` is set.
 #[allow(clippy::too_many_lines)]
 pub fn handle_event(
     ev: Command,
-    mut out: &mut impl Write,
+    mut out: &mut impl Terminal,
     p: &mut PagerState,
     command_queue: &mut CommandQueue,
     is_exited: &Arc<AtomicBool>,
-    #[cfg(feature = "search")] user_input_active: &Arc<(Mutex<bool>, Condvar)>,
+    events: &Mutex<dyn EventSource + '_>,
+    user_input_active: &Arc<(Mutex<bool>, Condvar)>,
 ) -> Result<(), MinusError> {
     match ev {
         Command::SetData(text) => {
-            p.screen.orig_text = text;
+            p.set_text(&text);
+            if !p.running.lock().is_uninitialized() {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        Command::SetStorage(store) => {
+            p.screen.store = store;
+            if let Some(syntax) = &mut p.screen.syntax {
+                syntax.clear();
+            }
+            p.screen.line_count = p.screen.store.line_count();
             p.format_lines();
-            p.screen.line_count = p.screen.orig_text.lines().count();
+            p.trim_lines();
+            if !p.running.lock().is_uninitialized() {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        Command::SetMaxFormattedRows(max) => {
+            p.screen.max_formatted_rows = max;
+            if max.is_some() {
+                p.screen.trim_window(p.upper_mark, p.rows);
+                p.screen.formatted_lines.shrink_to_fit();
+            } else {
+                p.format_lines();
+            }
+        }
+        Command::SetMaxLines(max) => {
+            p.screen.max_lines = max;
+            if p.trim_lines() > 0 && !p.running.lock().is_uninitialized() {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        Command::AddBuffer(name, text) => {
+            let is_current = p.find_buffer(&name) == Some(p.current_buffer);
+            p.add_buffer(name, &text);
+            if !p.running.lock().is_uninitialized() {
+                if is_current {
+                    display::draw_full(&mut out, p)?;
+                } else {
+                    display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+                }
+            }
+        }
+        Command::AppendBufferData(name, text) => match p.find_buffer(&name) {
+            Some(idx) if idx == p.current_buffer => {
+                return handle_event(
+                    Command::AppendData(text),
+                    out,
+                    p,
+                    command_queue,
+                    is_exited,
+                    events,
+                    user_input_active,
+                );
+            }
+            Some(idx) => p.with_buffer(idx, |ps| {
+                ps.append_str(&text);
+            }),
+            None => {}
+        },
+        Command::RemoveBuffer(name) => {
+            let switched = p.remove_buffer(&name);
             if !p.running.lock().is_uninitialized() {
+                if switched {
+                    display::draw_full(&mut out, p)?;
+                } else {
+                    display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+                }
+            }
+        }
+        Command::SwitchBuffer(name) => {
+            let switched = matches!(p.find_buffer(&name), Some(idx) if p.switch_buffer(idx));
+            if switched && !p.running.lock().is_uninitialized() {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        Command::UserInput(InputEvent::SwitchBuffer(idx)) => {
+            if p.switch_buffer(idx) && !p.running.lock().is_uninitialized() {
                 display::draw_full(&mut out, p)?;
             }
         }
         Command::UserInput(InputEvent::Exit) => {
             p.exit();
             is_exited.store(true, std::sync::atomic::Ordering::SeqCst);
-            term::cleanup(&mut out, &p.exit_strategy, true)?;
         }
         Command::UserInput(InputEvent::UpdateUpperMark(mut um)) => {
             let line_count = p.screen.formatted_lines_count();
-            // Reduce one row for prompt/messages
-            let writable_rows = p.rows.saturating_sub(1);
+            // Rows of the focused pane, leaving one row for prompt/messages
+            let writable_rows = p.pane_rows();
             // Calculate the lower_mark by adding either the rows or line_count depending
             // on the minimality
             let lower_mark = p.upper_mark.saturating_add(writable_rows.min(line_count));
@@ -61,7 +150,8 @@ pub fn handle_event(
             p.upper_mark = um;
         }
         Command::UserInput(InputEvent::UpdateLeftMark(lm)) if !p.screen.line_wrapping => {
-            if lm.saturating_add(p.cols) > p.screen.get_max_line_length() && lm > p.left_mark {
+            let text_cols = p.cols.saturating_sub(p.screen.gutter_width(p.line_numbers));
+            if lm.saturating_add(text_cols) > p.screen.get_max_line_length() && lm > p.left_mark {
                 return Ok(());
             }
             p.left_mark = lm;
@@ -73,8 +163,10 @@ pub fn handle_event(
             command_queue.push_back_unchecked(Command::FormatRedrawPrompt);
         }
         Command::UserInput(InputEvent::UpdateTermArea(c, r)) => {
-            p.rows = r;
+            p.rows = p.fit_rows(r);
             p.cols = c;
+            // Keep the whole pager on the screen when it is drawn inline
+            p.top = p.top.min(r - p.rows);
             // Readjust the text wrapping for the new number of columns
             command_queue.push_back(Command::FormatRedrawDisplay);
         }
@@ -90,35 +182,60 @@ pub fn handle_event(
             // less matches in this search than last time
             p.search_state.search_mark = 0;
 
-            // Pause the main user input thread, read search query and then restart the main input thread
-            let (lock, cvar) = (&user_input_active.0, &user_input_active.1);
-            let mut active = lock.lock();
-            *active = false;
-            drop(active);
-            // let string = search::fetch_input(&mut out, p.search_mode, p.rows)?;
-            let search_result = search::fetch_input(&mut out, p)?;
-            let mut active = lock.lock();
-            *active = true;
-            drop(active);
-            cvar.notify_one();
+            let search_result = with_input_paused(user_input_active, || {
+                search::fetch_input(&mut out, p, events)
+            })?;
 
             command_queue.push_back_unchecked(Command::FormatRedrawPrompt);
+            // Incremental search draws the text as a single pane, so the panes are drawn again
+            // once the search is done
+            if p.split.is_some() {
+                command_queue.push_back_unchecked(Command::FormatRedrawDisplay);
+            }
+            // Search options toggled at the prompt stay in effect for later searches
+            let options_changed = search_result.case_sensitivity != p.search_state.case_sensitivity
+                || search_result.literal != p.search_state.literal;
+            p.search_state.case_sensitivity = search_result.case_sensitivity;
+            p.search_state.literal = search_result.literal;
+            // Remember the query so that it can be recalled at the prompt
+            if !search_result.string.is_empty() {
+                if let Err(e) = p.search_state.history.push(search_result.string.clone()) {
+                    p.history_error(&e);
+                }
+            }
+
             // If we have incremental search cache directly use it and return
             if let Some(incremental_search_result) = search_result.incremental_search_result {
                 p.search_state.search_term = search_result.compiled_regex;
+                p.search_state.search_query = search_result.string;
                 p.upper_mark = incremental_search_result.upper_mark;
                 p.search_state.search_mark = incremental_search_result.search_mark;
                 p.search_state.search_idx = incremental_search_result.search_idx;
                 p.screen.formatted_lines = incremental_search_result.formatted_lines;
+                p.screen.window_start = incremental_search_result.window_start;
+                return Ok(());
+            }
+
+            // An empty query keeps the previous search but applies the new options to it
+            if search_result.string.is_empty() {
+                if options_changed {
+                    p.compile_search_term();
+                    p.format_lines();
+                }
                 return Ok(());
             }
 
             // If we only have compiled regex cached, use that otherwise compile the original
-            // string query if its not empty
+            // string query
             p.search_state.search_term = if search_result.compiled_regex.is_some() {
                 search_result.compiled_regex
-            } else if !search_result.string.is_empty() {
-                let compiled_regex = regex::Regex::new(&search_result.string).ok();
+            } else {
+                let compiled_regex = search::compile_query(
+                    &search_result.string,
+                    search_result.case_sensitivity,
+                    search_result.literal,
+                )
+                .ok();
                 if compiled_regex.is_none() {
                     command_queue.push_back_unchecked(Command::SendMessage(
                         "Invalid regular expression. Press Enter".to_string(),
@@ -126,14 +243,189 @@ pub fn handle_event(
                     return Ok(());
                 }
                 compiled_regex
-            } else {
-                return Ok(());
             };
+            p.search_state.search_query = search_result.string;
 
             // Format the lines, this will automatically generate the PagerState.search_idx
             p.format_lines();
         }
         #[cfg(feature = "search")]
+        Command::UserInput(InputEvent::Filter) => {
+            let filter_result = with_input_paused(user_input_active, || {
+                search::fetch_pattern(&mut out, p, events, '&')
+            })?;
+
+            command_queue.push_back_unchecked(Command::FormatRedrawPrompt);
+            // The filter is left as it is if the prompt was cancelled
+            let Some(filter_result) = filter_result else {
+                return Ok(());
+            };
+            // An empty pattern removes the filter and a leading `!` inverts it
+            let pattern = filter_result.string;
+            let filter = if pattern.is_empty() {
+                None
+            } else {
+                let (inverted, pattern) = pattern
+                    .strip_prefix('!')
+                    .map_or((false, pattern.as_str()), |p| (true, p));
+                let Ok(regex) = search::compile_query(
+                    pattern,
+                    filter_result.case_sensitivity,
+                    filter_result.literal,
+                ) else {
+                    command_queue.push_back_unchecked(Command::SendMessage(
+                        "Invalid regular expression. Press Enter".to_string(),
+                    ));
+                    return Ok(());
+                };
+                Some(if inverted {
+                    search::Filter::inverted(regex)
+                } else {
+                    search::Filter::new(regex)
+                })
+            };
+            p.set_filter(filter);
+            display::draw_full(&mut out, p)?;
+        }
+        #[cfg(feature = "search")]
+        Command::UserInput(ev @ (InputEvent::AddHighlight | InputEvent::RemoveHighlight)) => {
+            let adding = matches!(ev, InputEvent::AddHighlight);
+            let prompt_char = if adding { '+' } else { '-' };
+            let pattern_result = with_input_paused(user_input_active, || {
+                search::fetch_pattern(&mut out, p, events, prompt_char)
+            })?;
+
+            command_queue.push_back_unchecked(Command::FormatRedrawPrompt);
+            let Some(pattern_result) = pattern_result else {
+                return Ok(());
+            };
+            // An empty pattern removes all the highlights
+            if pattern_result.string.is_empty() {
+                if !adding && p.remove_highlights(None) {
+                    display::draw_full(&mut out, p)?;
+                }
+                return Ok(());
+            }
+            let Ok(regex) = search::compile_query(
+                &pattern_result.string,
+                pattern_result.case_sensitivity,
+                pattern_result.literal,
+            ) else {
+                command_queue.push_back_unchecked(Command::SendMessage(
+                    "Invalid regular expression. Press Enter".to_string(),
+                ));
+                return Ok(());
+            };
+            if adding {
+                let style = search::next_highlight_style(&p.search_state.highlights);
+                p.add_highlight(search::Highlight::new(regex, style));
+            } else if !p.remove_highlights(Some(regex.as_str())) {
+                command_queue.push_back_unchecked(Command::SendMessage(
+                    "Pattern is not highlighted. Press Enter".to_string(),
+                ));
+                return Ok(());
+            }
+            display::draw_full(&mut out, p)?;
+        }
+        Command::UserInput(InputEvent::Goto) => {
+            let input = with_input_paused(user_input_active, || {
+                line_input::fetch_line(&mut out, p, events, ':')
+            })?;
+
+            command_queue.push_back_unchecked(Command::FormatRedrawPrompt);
+            let Some(input) = input else {
+                return Ok(());
+            };
+            let Some(target) = GotoTarget::parse(&input) else {
+                command_queue.push_back_unchecked(Command::SendMessage(
+                    "Expected a line number, N% or Nb. Press Enter".to_string(),
+                ));
+                return Ok(());
+            };
+            match p.row_of_target(target) {
+                Ok(row) => command_queue
+                    .push_back_unchecked(Command::UserInput(InputEvent::UpdateUpperMark(row))),
+                Err(e) => p.storage_error(&e),
+            }
+        }
+        Command::UserInput(InputEvent::Save) if p.saving_allowed => {
+            let input = with_input_paused(user_input_active, || {
+                line_input::fetch_line(&mut out, p, events, '>')
+            })?;
+
+            match input {
+                Some(name) if !name.trim().is_empty() => {
+                    p.save_to_file(Path::new(name.trim()), p.save_options, false);
+                    display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+                }
+                _ => command_queue.push_back_unchecked(Command::FormatRedrawPrompt),
+            }
+        }
+        Command::SetSaveOptions(options) => p.save_options = options,
+        Command::AllowSaving(allowed) => p.saving_allowed = allowed,
+        Command::SaveToFile(path, options) => {
+            p.save_to_file(&path, options, true);
+            if !p.running.lock().is_uninitialized() {
+                display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+            }
+        }
+        #[cfg(feature = "search")]
+        Command::AddHighlight(highlight) => {
+            p.add_highlight(highlight);
+            if !p.running.lock().is_uninitialized() {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        #[cfg(feature = "search")]
+        Command::RemoveHighlight(pattern) => {
+            if p.remove_highlights(Some(&pattern)) && !p.running.lock().is_uninitialized() {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        #[cfg(feature = "search")]
+        Command::ClearHighlights => {
+            if p.remove_highlights(None) && !p.running.lock().is_uninitialized() {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        #[cfg(feature = "search")]
+        Command::SetSearchCase(case_sensitivity) => {
+            let literal = p.search_state.literal;
+            if p.set_search_options(case_sensitivity, literal)
+                && !p.running.lock().is_uninitialized()
+            {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        #[cfg(feature = "search")]
+        Command::SetLiteralSearch(literal) => {
+            let case_sensitivity = p.search_state.case_sensitivity;
+            if p.set_search_options(case_sensitivity, literal)
+                && !p.running.lock().is_uninitialized()
+            {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        #[cfg(feature = "search")]
+        Command::AddSearchHistory(queries) => p.search_state.history.extend(queries),
+        #[cfg(feature = "search")]
+        Command::SetSearchHistoryFile(path) => {
+            if let Err(e) = p.search_state.history.set_file(path) {
+                p.history_error(&e);
+                p.format_prompt();
+                if !p.running.lock().is_uninitialized() {
+                    display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+                }
+            }
+        }
+        #[cfg(feature = "search")]
+        Command::SetFilter(filter) => {
+            p.set_filter(filter);
+            if !p.running.lock().is_uninitialized() {
+                display::draw_full(&mut out, p)?;
+            }
+        }
+        #[cfg(feature = "search")]
         Command::UserInput(InputEvent::NextMatch | InputEvent::MoveToNextMatch(1))
             if p.search_state.search_term.is_some() =>
         {
@@ -258,19 +550,26 @@ pub fn handle_event(
             let prev_fmt_lines_count = p.screen.formatted_lines_count();
             let is_running = !p.running.lock().is_uninitialized();
             let rows = p.rows;
+            let top = p.top;
+            let is_split = p.split.is_some();
             let append_style = p.append_str(text.as_str());
 
             if is_running {
-                if append_style == AppendStyle::FullRedraw {
-                    return display::draw_full(out, p);
+                // Text appended to a screen that isn't filled may show up in both panes
+                if append_style == AppendStyle::FullRedraw
+                    || (is_split && prev_fmt_lines_count < rows)
+                {
+                    display::draw_full(out, p)?;
+                } else {
+                    display::draw_append_text(
+                        out,
+                        top,
+                        rows,
+                        prev_unterminated,
+                        prev_fmt_lines_count,
+                        &append_style,
+                    )?;
                 }
-                display::draw_append_text(
-                    out,
-                    rows,
-                    prev_unterminated,
-                    prev_fmt_lines_count,
-                    &append_style,
-                )?;
 
                 if p.follow_output {
                     command_queue.push_back_unchecked(Command::UserInput(
@@ -280,6 +579,17 @@ pub fn handle_event(
             }
         }
 
+        Command::SetPromptFormat(_) | Command::SetPromptTheme(_) => {
+            match ev {
+                Command::SetPromptFormat(format) => p.prompt_format = format,
+                Command::SetPromptTheme(theme) => p.prompt_theme = theme,
+                _ => unreachable!(),
+            }
+            p.format_prompt();
+            if !p.running.lock().is_uninitialized() {
+                display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+            }
+        }
         Command::SetPrompt(ref text) | Command::SendMessage(ref text) => {
             if let Command::SetPrompt(_) = ev {
                 p.prompt = text.to_string();
@@ -288,32 +598,89 @@ pub fn handle_event(
             }
             p.format_prompt();
             if !p.running.lock().is_uninitialized() {
-                display::write_prompt(out, &p.displayed_prompt, p.rows.try_into().unwrap())?;
+                display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
             }
         }
         Command::SetLineNumbers(ln) => {
             p.line_numbers = ln;
             p.format_lines();
             if !p.running.lock().is_uninitialized() {
-                display::write_prompt(out, &p.displayed_prompt, p.rows.try_into().unwrap())?;
+                display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+            }
+        }
+        Command::SetGutter(gutter) => {
+            p.set_gutter(gutter);
+            if !p.running.lock().is_uninitialized() {
+                display::draw_full(out, p)?;
             }
         }
         Command::FormatRedrawPrompt => {
             p.format_prompt();
-            display::write_prompt(out, &p.displayed_prompt, p.rows.try_into().unwrap())?;
+            display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
         }
         Command::SetExitStrategy(es) => p.exit_strategy = es,
         Command::LineWrapping(lw) => {
             p.screen.line_wrapping = lw;
             p.format_lines();
         }
-        #[cfg(feature = "static_output")]
         Command::SetRunNoOverflow(val) => p.run_no_overflow = val,
         #[cfg(feature = "search")]
         Command::IncrementalSearchCondition(cb) => p.search_state.incremental_search_condition = cb,
         Command::SetInputClassifier(clf) => p.input_classifier = clf,
         Command::AddExitCallback(cb) => p.exit_callbacks.push(cb),
+        Command::AllowExternalCommands(allowed) => p.external_commands_allowed = allowed,
+        Command::SetInline(rows) => {
+            // The terminal is set up for either mode when the pager starts
+            if p.running.lock().is_uninitialized() {
+                let (_, term_rows) = out.size().map_err(|e| SetupError::TerminalSize(e.into()))?;
+                p.inline_rows = rows;
+                p.rows = p.fit_rows(term_rows.into());
+            }
+        }
+        Command::SuspendWith(f) => {
+            if p.running.lock().is_uninitialized() {
+                f();
+            } else {
+                suspend(out, p, events, user_input_active, |_, _| f())?;
+            }
+        }
         Command::ShowPrompt(show) => p.show_prompt = show,
+        Command::ReaderStarted | Command::ReaderFinished => {
+            if ev == Command::ReaderStarted {
+                p.loading += 1;
+            } else {
+                p.loading = p.loading.saturating_sub(1);
+            }
+            // Readers are usually given before the pager starts, in which case there is
+            // nothing to redraw yet
+            if p.running.lock().is_uninitialized() {
+                p.format_prompt();
+            } else {
+                command_queue.push_back_unchecked(Command::FormatRedrawPrompt);
+            }
+        }
+        Command::EndOfInput => {
+            p.input_complete = true;
+            // Like `less -F`, quit if there is nothing to scroll. Before the pager starts, this
+            // is checked when it starts.
+            if p.running.lock().is_uninitialized() {
+                return Ok(());
+            }
+            if p.fits_on_screen() {
+                // An inline pager already leaves the text on the screen
+                p.print_on_exit = p.inline_rows.is_none();
+                command_queue.push_back_unchecked(Command::UserInput(InputEvent::Exit));
+            } else {
+                // Show the end of the text at the prompt
+                command_queue.push_back_unchecked(Command::FormatRedrawPrompt);
+            }
+        }
+        Command::AnimateSpinner => {
+            if p.is_waiting_for_data() {
+                p.spinner = p.spinner.wrapping_add(1);
+                command_queue.push_back(Command::FormatRedrawPrompt);
+            }
+        }
         Command::FollowOutput(follow_output)
         | Command::UserInput(InputEvent::FollowOutput(follow_output)) => {
             p.follow_output = follow_output;
@@ -322,45 +689,400 @@ pub fn handle_event(
             )));
             command_queue.push_back(Command::FormatRedrawPrompt);
         }
+        Command::SplitView(split) | Command::UserInput(InputEvent::SplitView(split)) => {
+            p.set_split(split);
+            if !p.running.lock().is_uninitialized() {
+                display::draw_full(out, p)?;
+            }
+        }
+        Command::SetHighlighter(syntax) => {
+            p.set_highlighter(syntax);
+            if !p.running.lock().is_uninitialized() {
+                display::draw_full(out, p)?;
+            }
+        }
+        Command::UserInput(InputEvent::SetMark(name)) => p.set_mark(name),
+        Command::UserInput(InputEvent::JumpToMark(name)) => match p.mark(name) {
+            Some(line) => command_queue.push_back_unchecked(Command::UserInput(
+                InputEvent::UpdateUpperMark(p.row_of_line(line)),
+            )),
+            None => command_queue.push_back_unchecked(Command::SendMessage(format!(
+                "Mark '{name}' is not set. Press Enter"
+            ))),
+        },
+        Command::UserInput(InputEvent::CopyToMark(name)) => {
+            p.copy_lines_to_mark(out, name)?;
+            display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+        }
+        Command::UserInput(InputEvent::StartSelection(column, row)) => {
+            let selected = p.selection.and_then(|s| s.bounds()).is_some();
+            p.selection = p.position_at(column, row).map(Selection::new);
+            // Clear the highlight of the previous selection
+            if selected {
+                display::draw_full(out, p)?;
+            }
+        }
+        Command::UserInput(InputEvent::ExtendSelection(column, row)) => {
+            if let (Some(position), Some(selection)) =
+                (p.position_at(column, row), &mut p.selection)
+            {
+                selection.head = Some(position);
+                display::draw_full(out, p)?;
+            }
+        }
+        Command::UserInput(InputEvent::CopySelection) => {
+            if p.copy_selection(out)? {
+                display::draw_full(out, p)?;
+            }
+        }
+        Command::SetHintAction(action) => p.hint_action = action,
+        Command::UserInput(InputEvent::ShowHints(true)) => {
+            let hints = Hints::new(p.visible_hints());
+            if hints.labels.is_empty() {
+                p.hints = None;
+                p.message = Some("No links or file locations on the screen".to_string());
+                p.format_prompt();
+                display::write_prompt(out, &p.displayed_prompt, p.prompt_row())?;
+            } else {
+                display::write_hint_labels(out, &hints, p.top)?;
+                p.hints = Some(hints);
+            }
+        }
+        Command::UserInput(InputEvent::ShowHints(false)) => {
+            if p.hints.take().is_some() {
+                display::draw_full(out, p)?;
+            }
+        }
+        Command::UserInput(InputEvent::SelectHint(c)) => {
+            let Some(mut hints) = p.hints.take() else {
+                return Ok(());
+            };
+            hints.typed.push(c);
+            let selected = hints
+                .labels
+                .iter()
+                .find(|l| l.text == hints.typed)
+                .map(|l| l.hint.clone());
+            display::draw_full(out, p)?;
+            if let Some(hint) = selected {
+                open_hint(out, p, &hint, events, user_input_active)?;
+            } else if hints.matching().next().is_some() {
+                // Wait for the rest of the label
+                display::write_hint_labels(out, &hints, p.top)?;
+                p.hints = Some(hints);
+            }
+        }
+        Command::UserInput(InputEvent::Edit) if p.external_commands_allowed => {
+            edit_text(out, p, events, user_input_active)?;
+        }
+        Command::UserInput(InputEvent::Shell) if p.external_commands_allowed => {
+            let input = with_input_paused(user_input_active, || {
+                line_input::fetch_line(&mut out, p, events, '!')
+            })?;
+
+            let Some(command) = input else {
+                command_queue.push_back_unchecked(Command::FormatRedrawPrompt);
+                return Ok(());
+            };
+            let command = command.trim();
+            let result = suspend(out, p, events, user_input_active, |out, events| {
+                shell_command(command).status()?;
+                // Keep the output of the command on the screen until the user has read it
+                if !command.is_empty() {
+                    write!(out, "\nPress Enter to return to the pager")?;
+                    out.flush()?;
+                    wait_for_enter(events)?;
+                }
+                io::Result::Ok(())
+            })?;
+            if let Err(e) = result {
+                show_message(out, p, format!("Failed to run the command: {e}"))?;
+            }
+        }
+        #[cfg(unix)]
+        Command::UserInput(InputEvent::Suspend) => {
+            suspend(out, p, events, user_input_active, |_, _| {
+                // Stop the process like the shell does when Ctrl+Z is pressed outside of raw
+                // mode. This returns once the process is continued.
+                // SAFETY: raise only sends a signal to the calling thread
+                unsafe { libc::raise(libc::SIGTSTP) };
+            })?;
+        }
+        Command::UserInput(InputEvent::SwitchPane) => {
+            if p.switch_pane() {
+                display::draw_full(out, p)?;
+            }
+        }
         Command::UserInput(_) => {}
     }
     Ok(())
 }
 
+/// Open `hint` with the [`HintAction`] of the pager and show any error at the prompt
+fn open_hint(
+    out: &mut impl Terminal,
+    p: &mut PagerState,
+    hint: &Hint,
+    events: &Mutex<dyn EventSource + '_>,
+    user_input_active: &Arc<(Mutex<bool>, Condvar)>,
+) -> Result<(), MinusError> {
+    match p.hint_action.clone() {
+        Some(HintAction::Callback(f)) => f(hint),
+        Some(HintAction::Command { url, location }) => {
+            let template = match hint {
+                Hint::Url(_) => url,
+                Hint::Location { .. } => location,
+            };
+            let Some(template) = template else {
+                return Ok(());
+            };
+            let result = match hints::command_line(&template, hint) {
+                Ok(args) => suspend(out, p, events, user_input_active, |_, _| {
+                    run_to_completion(process::Command::new(&args[0]).args(&args[1..]))
+                })?,
+                Err(e) => Err(format!("Failed to run the command: {e}")),
+            };
+            if let Err(e) = result {
+                show_message(out, p, e)?;
+            }
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Open the text of the current buffer in the editor of the user
+///
+/// The text is written without colors to a temporary file, which is removed once the editor
+/// quits. The editor is started at the line displayed at the top of the screen.
+fn edit_text(
+    out: &mut impl Terminal,
+    p: &mut PagerState,
+    events: &Mutex<dyn EventSource + '_>,
+    user_input_active: &Arc<(Mutex<bool>, Condvar)>,
+) -> Result<(), MinusError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let path = std::env::temp_dir().join(format!("minus-{}-{nanos}.txt", process::id()));
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|file| {
+            let options = SaveOptions {
+                strip_ansi: true,
+                region: SaveRegion::All,
+            };
+            let mut file = io::BufWriter::new(file);
+            p.write_saved_text(&mut file, options)?;
+            file.flush()
+        });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&path);
+        return show_message(out, p, format!("Failed to write {}: {e}", path.display()));
+    }
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok();
+    let line = p.lines_to_row_map.line_of_row(p.upper_mark) + 1;
+    let result = suspend(out, p, events, user_input_active, |_, _| {
+        run_to_completion(&mut editor_command(editor.as_deref(), &path, line))
+    })?;
+    let _ = std::fs::remove_file(&path);
+    if let Err(e) = result {
+        show_message(out, p, e)?;
+    }
+    Ok(())
+}
+
+/// Command that opens `path` at `line` in `editor`, or in a default editor if it is not set
+///
+/// Like `$EDITOR`, `editor` can contain the arguments to pass to the editor after its name.
+fn editor_command(editor: Option<&str>, path: &Path, line: usize) -> process::Command {
+    let default = if cfg!(windows) { "notepad" } else { "vi" };
+    let mut words = editor
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or(default)
+        .split_whitespace();
+    // There is at least one word as the editor is not blank
+    let mut command = process::Command::new(words.next().unwrap());
+    command.args(words);
+    if !cfg!(windows) {
+        command.arg(format!("+{line}"));
+    }
+    command.arg(path);
+    command
+}
+
+/// Command that runs `command` in the shell of the user, or starts the shell if it is empty
+fn shell_command(command: &str) -> process::Command {
+    if cfg!(windows) {
+        let mut shell = process::Command::new("cmd");
+        if !command.is_empty() {
+            shell.args(["/C", command]);
+        }
+        shell
+    } else {
+        let mut shell =
+            process::Command::new(std::env::var_os("SHELL").unwrap_or_else(|| "sh".into()));
+        if !command.is_empty() {
+            shell.args(["-c", command]);
+        }
+        shell
+    }
+}
+
+/// Run `command` and wait for it to exit
+///
+/// # Errors
+/// Returns a message telling why if the command could not be started or did not succeed
+fn run_to_completion(command: &mut process::Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{program} failed with {status}")),
+        Err(e) => Err(format!("Failed to run {program}: {e}")),
+    }
+}
+
+/// Wait until the user presses `Enter`
+///
+/// This reads the events while the terminal is not in raw mode, so the keys typed before
+/// `Enter` are only received along with it.
+fn wait_for_enter(events: &mut dyn EventSource) -> io::Result<()> {
+    loop {
+        if events.poll(Duration::from_millis(100))? {
+            if let Event::Key(KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            }) = events.read()?
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Show `message` at the prompt
+fn show_message(
+    out: &mut impl Terminal,
+    p: &mut PagerState,
+    message: String,
+) -> Result<(), MinusError> {
+    p.message = Some(message);
+    p.format_prompt();
+    display::write_prompt(out, &p.displayed_prompt, p.prompt_row())
+}
+
+/// Run `f` with the event reader paused, for prompts that read the user input themselves
+///
+/// The event reader is restarted afterwards even if `f` fails, otherwise it would never notice
+/// that the pager has quit.
+fn with_input_paused<R>(
+    user_input_active: &Arc<(Mutex<bool>, Condvar)>,
+    f: impl FnOnce() -> R,
+) -> R {
+    let (lock, cvar) = (&user_input_active.0, &user_input_active.1);
+    *lock.lock() = false;
+    let result = f();
+    *lock.lock() = true;
+    cvar.notify_one();
+    result
+}
+
+/// Hand the terminal over to `f` while the pager is suspended
+///
+/// The terminal is restored to the state it was in before the pager started and the event reader
+/// is paused so that `f` can run programs that use the terminal. `f` gets the terminal and the
+/// source of events in case it has to interact with the user itself. Afterwards, the terminal is
+/// set up for the pager again and the screen is redrawn.
+fn suspend<T: Terminal, R>(
+    out: &mut T,
+    p: &mut PagerState,
+    events: &Mutex<dyn EventSource + '_>,
+    user_input_active: &Arc<(Mutex<bool>, Condvar)>,
+    f: impl FnOnce(&mut T, &mut dyn EventSource) -> R,
+) -> Result<R, MinusError> {
+    let result = with_input_paused(user_input_active, || {
+        // Holding the event source waits for the event reader to finish polling and keeps it
+        // from reading the input meant for `f`
+        let mut source = events.lock();
+        match p.cleanup_terminal(out) {
+            Ok(()) => {
+                let result = f(out, &mut *source);
+                drop(source);
+                p.setup_terminal(out)
+                    .map(|()| result)
+                    .map_err(MinusError::from)
+            }
+            Err(e) => Err(e.into()),
+        }
+    })?;
+    display::draw_full(out, p)?;
+    Ok(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::commands::Command;
     use super::handle_event;
-    use crate::{minus_core::CommandQueue, ExitStrategy, PagerState, RunMode};
-    use std::sync::{atomic::AtomicBool, Arc};
-    #[cfg(feature = "search")]
+    use crate::{backend::Terminal, minus_core::CommandQueue, ExitStrategy, PagerState, RunMode};
+    use std::{
+        io::Write,
+        sync::{atomic::AtomicBool, Arc},
+    };
     use {
-        once_cell::sync::Lazy,
+        crate::backend::CrosstermEvents,
         parking_lot::{Condvar, Mutex},
     };
 
     // Tests constants
-    #[cfg(feature = "search")]
-    static UIA: Lazy<Arc<(Mutex<bool>, Condvar)>> =
-        Lazy::new(|| Arc::new((Mutex::new(true), Condvar::new())));
+    static EVENTS: Mutex<CrosstermEvents> = parking_lot::const_mutex(CrosstermEvents);
     const TEST_STR: &str = "This is some sample text";
 
+    /// Whether the event reader is running, which is always the case in these tests
+    fn uia() -> Arc<(Mutex<bool>, Condvar)> {
+        Arc::new((Mutex::new(true), Condvar::new()))
+    }
+
+    /// Terminal that keeps whatever is drawn on it in memory
+    #[derive(Default)]
+    struct Output(Vec<u8>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Terminal for Output {
+        fn size(&self) -> std::io::Result<(u16, u16)> {
+            Ok((80, 10))
+        }
+    }
+
     // Tests for event emitting functions of Pager
     #[test]
     #[cfg(any(feature = "dynamic_output", feature = "static_output"))]
     fn set_data() {
         let mut ps = PagerState::new().unwrap();
         let ev = Command::SetData(TEST_STR.to_string());
-        let mut out = Vec::new();
+        let mut out = Output::default();
         #[cfg(feature = "dynamic_output")]
         {
-            *crate::minus_core::RUNMODE.lock() = RunMode::Dynamic;
+            *ps.running.lock() = RunMode::Dynamic;
         }
         #[cfg(feature = "static_output")]
         {
-            *crate::minus_core::RUNMODE.lock() = RunMode::Static;
+            *ps.running.lock() = RunMode::Static;
         }
-        let mut command_queue = CommandQueue::new_zero();
+        let mut command_queue = CommandQueue::new_zero(ps.running.clone());
 
         handle_event(
             ev,
@@ -368,8 +1090,8 @@ mod tests {
             &mut ps,
             &mut command_queue,
             &Arc::new(AtomicBool::new(false)),
-            #[cfg(feature = "search")]
-            &UIA,
+            &EVENTS,
+            &uia(),
         )
         .unwrap();
         assert_eq!(ps.screen.formatted_lines, vec![TEST_STR.to_string()]);
@@ -380,8 +1102,8 @@ mod tests {
         let mut ps = PagerState::new().unwrap();
         let ev1 = Command::AppendData(format!("{TEST_STR}\n"));
         let ev2 = Command::AppendData(TEST_STR.to_string());
-        let mut out = Vec::new();
-        let mut command_queue = CommandQueue::new_zero();
+        let mut out = Output::default();
+        let mut command_queue = CommandQueue::new_zero(ps.running.clone());
 
         handle_event(
             ev1,
@@ -389,8 +1111,8 @@ mod tests {
             &mut ps,
             &mut command_queue,
             &Arc::new(AtomicBool::new(false)),
-            #[cfg(feature = "search")]
-            &UIA,
+            &EVENTS,
+            &uia(),
         )
         .unwrap();
         handle_event(
@@ -399,8 +1121,8 @@ mod tests {
             &mut ps,
             &mut command_queue,
             &Arc::new(AtomicBool::new(false)),
-            #[cfg(feature = "search")]
-            &UIA,
+            &EVENTS,
+            &uia(),
         )
         .unwrap();
         assert_eq!(
@@ -414,15 +1136,15 @@ mod tests {
     fn set_prompt() {
         let mut ps = PagerState::new().unwrap();
         let ev = Command::SetPrompt(TEST_STR.to_string());
-        let mut out = Vec::new();
-        let mut command_queue = CommandQueue::new_zero();
+        let mut out = Output::default();
+        let mut command_queue = CommandQueue::new_zero(ps.running.clone());
         #[cfg(feature = "dynamic_output")]
         {
-            *crate::minus_core::RUNMODE.lock() = RunMode::Dynamic;
+            *ps.running.lock() = RunMode::Dynamic;
         }
         #[cfg(feature = "static_output")]
         {
-            *crate::minus_core::RUNMODE.lock() = RunMode::Static;
+            *ps.running.lock() = RunMode::Static;
         }
 
         handle_event(
@@ -431,8 +1153,8 @@ mod tests {
             &mut ps,
             &mut command_queue,
             &Arc::new(AtomicBool::new(false)),
-            #[cfg(feature = "search")]
-            &UIA,
+            &EVENTS,
+            &uia(),
         )
         .unwrap();
         assert_eq!(ps.prompt, TEST_STR.to_string());
@@ -444,15 +1166,15 @@ mod tests {
         let mut ps = PagerState::new().unwrap();
         #[cfg(feature = "dynamic_output")]
         {
-            *crate::minus_core::RUNMODE.lock() = RunMode::Dynamic;
+            *ps.running.lock() = RunMode::Dynamic;
         }
         #[cfg(feature = "static_output")]
         {
-            *crate::minus_core::RUNMODE.lock() = RunMode::Static;
+            *ps.running.lock() = RunMode::Static;
         }
         let ev = Command::SendMessage(TEST_STR.to_string());
-        let mut out = Vec::new();
-        let mut command_queue = CommandQueue::new_zero();
+        let mut out = Output::default();
+        let mut command_queue = CommandQueue::new_zero(ps.running.clone());
 
         handle_event(
             ev,
@@ -460,20 +1182,19 @@ mod tests {
             &mut ps,
             &mut command_queue,
             &Arc::new(AtomicBool::new(false)),
-            #[cfg(feature = "search")]
-            &UIA,
+            &EVENTS,
+            &uia(),
         )
         .unwrap();
         assert_eq!(ps.message.unwrap(), TEST_STR.to_string());
     }
 
     #[test]
-    #[cfg(feature = "static_output")]
     fn set_run_no_overflow() {
         let mut ps = PagerState::new().unwrap();
         let ev = Command::SetRunNoOverflow(false);
-        let mut out = Vec::new();
-        let mut command_queue = CommandQueue::new_zero();
+        let mut out = Output::default();
+        let mut command_queue = CommandQueue::new_zero(ps.running.clone());
 
         handle_event(
             ev,
@@ -481,8 +1202,8 @@ mod tests {
             &mut ps,
             &mut command_queue,
             &Arc::new(AtomicBool::new(false)),
-            #[cfg(feature = "search")]
-            &UIA,
+            &EVENTS,
+            &uia(),
         )
         .unwrap();
         assert!(!ps.run_no_overflow);
@@ -492,8 +1213,8 @@ mod tests {
     fn set_exit_strategy() {
         let mut ps = PagerState::new().unwrap();
         let ev = Command::SetExitStrategy(ExitStrategy::PagerQuit);
-        let mut out = Vec::new();
-        let mut command_queue = CommandQueue::new_zero();
+        let mut out = Output::default();
+        let mut command_queue = CommandQueue::new_zero(ps.running.clone());
 
         handle_event(
             ev,
@@ -501,8 +1222,8 @@ mod tests {
             &mut ps,
             &mut command_queue,
             &Arc::new(AtomicBool::new(false)),
-            #[cfg(feature = "search")]
-            &UIA,
+            &EVENTS,
+            &uia(),
         )
         .unwrap();
         assert_eq!(ps.exit_strategy, ExitStrategy::PagerQuit);
@@ -512,8 +1233,8 @@ mod tests {
     fn add_exit_callback() {
         let mut ps = PagerState::new().unwrap();
         let ev = Command::AddExitCallback(Box::new(|| println!("Hello World")));
-        let mut out = Vec::new();
-        let mut command_queue = CommandQueue::new_zero();
+        let mut out = Output::default();
+        let mut command_queue = CommandQueue::new_zero(ps.running.clone());
 
         handle_event(
             ev,
@@ -521,10 +1242,25 @@ mod tests {
             &mut ps,
             &mut command_queue,
             &Arc::new(AtomicBool::new(false)),
-            #[cfg(feature = "search")]
-            &UIA,
+            &EVENTS,
+            &uia(),
         )
         .unwrap();
         assert_eq!(ps.exit_callbacks.len(), 1);
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn editor_commands() {
+        use super::editor_command;
+        use std::path::Path;
+
+        let path = Path::new("/tmp/text");
+        let command = editor_command(Some("code -w"), path, 12);
+        assert_eq!(command.get_program(), "code");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["-w", "+12", "/tmp/text"]);
+        assert_eq!(editor_command(Some(" "), path, 1).get_program(), "vi");
+        assert_eq!(editor_command(None, path, 1).get_program(), "vi");
+    }
 }
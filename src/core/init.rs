@@ -8,39 +8,34 @@
 //! * The [`start_reactor`] function displays the displays the output and also polls
 //! the [`Receiver`] held inside the [`Pager`] for events. Whenever a event is
 //! detected, it reacts to it accordingly.
-#[cfg(feature = "static_output")]
-use crate::minus_core::utils::display;
 use crate::{
+    backend::{CrosstermEvents, EventSource, Terminal},
     error::MinusError,
-    input::InputEvent,
-    minus_core::{
-        commands::Command,
-        ev_handler::handle_event,
-        utils::{display::draw_full, term},
-        RunMode,
-    },
-    Pager, PagerState,
+    hints::Hints,
+    input::{InputEvent, MarkAction},
+    minus_core::{commands::Command, ev_handler::handle_event, utils::display::draw_full, RunMode},
+    ExitStrategy, Pager, PagerState,
 };
 
-use crossbeam_channel::{Receiver, Sender, TrySendError};
-use crossterm::event;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use flume::{Receiver, SendTimeoutError, Sender};
+#[cfg(feature = "async")]
+use futures_util::Stream;
+#[cfg(feature = "async")]
+use std::io;
 use std::{
-    io::{stdout, Stdout},
+    io::stdout,
     panic,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
-#[cfg(feature = "static_output")]
-use {super::utils::display::write_raw_lines, crossterm::tty::IsTty};
-
-#[cfg(feature = "search")]
-use parking_lot::Condvar;
-use parking_lot::Mutex;
+use parking_lot::{Condvar, Mutex};
 
-use super::{utils::display::draw_for_change, CommandQueue, RUNMODE};
+use super::{utils::display::draw_for_change, CommandQueue};
 
 /// The main entry point of minus
 ///
@@ -52,19 +47,17 @@ use super::{utils::display::draw_for_change, CommandQueue, RUNMODE};
 ///
 /// Then it checks if the minus is running in static mode and does some checks:-
 /// * If standard output is not a terminal screen, that is if it is a file or block
-/// device, minus will write all the data at once to the stdout and quit
+///   device, minus will write all the data at once to the stdout and quit
 ///
 /// * If the size of the data is less than the available number of rows in the terminal
-/// then it displays everything on the main stdout screen at once and quits. This
-/// behaviour can be turned off if [`Pager::set_run_no_overflow(true)`] is called
-/// by the main application
-// Sorry... this behaviour would have been cool to have in async mode, just think about it!!! Many
-// implementations were proposed but none were perfect
-// It is because implementing this especially with line wrapping and terminal scrolling
-// is a a nightmare because terminals are really naughty and more when you have to fight with it
-// using your library... your only weapon
-// So we just don't take any more proposals about this. It is really frustating to
-// to thoroughly test each implementation and fix out all rough edges around it
+///   then it displays everything on the main stdout screen at once and quits. This
+///   behaviour can be turned off if [`Pager::set_run_no_overflow(true)`] is called
+///   by the main application
+///
+/// In dynamic mode, the same is done once [`Pager::end_of_input`] is called. If the pager has
+/// already started by then, it quits and writes the text after switching back to the main
+/// screen.
+///
 /// Next it initializes the runtime and calls [`start_reactor`] and a [`event reader`]` which is
 /// selected based on the enabled feature set:-
 ///
@@ -75,101 +68,66 @@ use super::{utils::display::draw_for_change, CommandQueue, RUNMODE};
 ///
 /// [`event reader`]: event_reader
 #[allow(clippy::module_name_repetitions)]
-#[allow(clippy::too_many_lines)]
 pub fn init_core(pager: &Pager, rm: RunMode) -> std::result::Result<(), MinusError> {
-    #[allow(unused_mut)]
-    let mut out = stdout();
+    init_core_with(pager, rm, &mut stdout(), CrosstermEvents)
+}
+
+/// Same as [`init_core`] but draws on `out` and reads user input from `events`
+///
+/// # Errors
+///
+/// Setting/cleaning up the terminal can fail and IO to/from the terminal can
+/// fail.
+#[allow(clippy::too_many_lines)]
+pub fn init_core_with<T, E>(
+    pager: &Pager,
+    rm: RunMode,
+    out: &mut T,
+    events: E,
+) -> std::result::Result<(), MinusError>
+where
+    T: Terminal,
+    E: EventSource,
+{
     // Is the event reader running
-    #[cfg(feature = "search")]
     let input_thread_running = Arc::new((Mutex::new(true), Condvar::new()));
 
-    #[allow(unused_mut)]
-    let mut ps = crate::state::PagerState::generate_initial_state(&pager.rx, &mut out)?;
-
-    {
-        let mut runmode = super::RUNMODE.lock();
-        assert!(runmode.is_uninitialized(), "Failed to set the RUNMODE. This is caused probably because another instance of minus is already running");
-        *runmode = rm;
-        drop(runmode);
-    }
-
-    // Static mode checks
-    #[cfg(feature = "static_output")]
-    if *RUNMODE.lock() == RunMode::Static {
-        // If stdout is not a tty, write everything and quit
-        if !out.is_tty() {
-            write_raw_lines(&mut out, &[ps.screen.orig_text], None)?;
-            let mut rm = RUNMODE.lock();
-            *rm = RunMode::Uninitialized;
-            drop(rm);
-            return Ok(());
-        }
-        // If number of lines of text is less than available rows, write everything and quit
-        // unless run_no_overflow is set to true
-        if ps.screen.formatted_lines_count() <= ps.rows && !ps.run_no_overflow {
-            write_raw_lines(&mut out, &ps.screen.formatted_lines, Some("\r"))?;
-            ps.exit();
-            let mut rm = RUNMODE.lock();
-            *rm = RunMode::Uninitialized;
-            drop(rm);
-            return Ok(());
-        }
-    }
-
-    // Setup terminal, adjust line wraps and get rows
-    term::setup(&out)?;
+    let Some(ps) = prepare(pager, rm, out)? else {
+        return Ok(());
+    };
+    let running = pager.running.clone();
+    let stop = || *running.lock() = RunMode::Uninitialized;
 
     // Has the user quit
     let is_exited = Arc::new(AtomicBool::new(false));
-    let is_exited2 = is_exited.clone();
-
-    {
-        let panic_hook = panic::take_hook();
-        panic::set_hook(Box::new(move |pinfo| {
-            is_exited2.store(true, std::sync::atomic::Ordering::SeqCst);
-            // While silently ignoring error is considered a bad practice, we are forced to do it here
-            // as we cannot use the ? and panicking here will cause UB.
-            drop(term::cleanup(
-                stdout(),
-                &crate::ExitStrategy::PagerQuit,
-                true,
-            ));
-            panic_hook(pinfo);
-        }));
-    }
+    let restore_panic_hook = set_panic_hook(out, is_exited.clone());
 
     let ps_mutex = Arc::new(Mutex::new(ps));
+    // The event source is shared between the event reader, the prompts and the programs run
+    // while the pager is suspended
+    let events = Mutex::new(events);
 
     let evtx = pager.tx.clone();
     let rx = pager.rx.clone();
-    let out = stdout();
 
     let p1 = ps_mutex.clone();
+    let p2 = ps_mutex.clone();
+    let p3 = ps_mutex.clone();
 
-    #[cfg(feature = "search")]
     let input_thread_running2 = input_thread_running.clone();
 
-    std::thread::scope(|s| -> crate::Result {
-        let out = Arc::new(out);
-        let out_copy = out.clone();
+    let res = std::thread::scope(|s| -> crate::Result {
+        let events = &events;
         let is_exited3 = is_exited.clone();
         let is_exited4 = is_exited.clone();
 
         let t1 = s.spawn(move || {
-            let res = event_reader(
-                &evtx,
-                &p1,
-                #[cfg(feature = "search")]
-                &input_thread_running2,
-                &is_exited3,
-            );
+            let res = event_reader(&evtx, &p1, events, &input_thread_running2, &is_exited3);
 
             if res.is_err() {
                 is_exited3.store(true, std::sync::atomic::Ordering::SeqCst);
-                let mut rm = RUNMODE.lock();
-                *rm = RunMode::Uninitialized;
-                drop(rm);
-                term::cleanup(out.as_ref(), &crate::ExitStrategy::PagerQuit, true)?;
+                // Wake up the reactor so that it notices the exit and cleans up the terminal
+                let _ = evtx.try_send(Command::UserInput(InputEvent::Ignore));
             }
             res
         });
@@ -177,18 +135,16 @@ pub fn init_core(pager: &Pager, rm: RunMode) -> std::result::Result<(), MinusErr
             let res = start_reactor(
                 &rx,
                 &ps_mutex,
-                &out_copy,
-                #[cfg(feature = "search")]
+                out,
+                events,
                 &input_thread_running,
                 &is_exited4,
             );
 
             if res.is_err() {
                 is_exited4.store(true, std::sync::atomic::Ordering::SeqCst);
-                let mut rm = RUNMODE.lock();
-                *rm = RunMode::Uninitialized;
-                drop(rm);
-                term::cleanup(out_copy.as_ref(), &crate::ExitStrategy::PagerQuit, true)?;
+                stop();
+                p2.lock().cleanup_terminal(out)?;
             }
             res
         });
@@ -196,10 +152,295 @@ pub fn init_core(pager: &Pager, rm: RunMode) -> std::result::Result<(), MinusErr
         let r1 = t1.join().unwrap();
         let r2 = t2.join().unwrap();
 
+        // Report the errors of either thread before deciding to quit the process
         r1?;
         r2?;
+        if p3.lock().exit_strategy == ExitStrategy::ProcessQuit {
+            std::process::exit(0);
+        }
         Ok(())
-    })
+    });
+    restore_panic_hook();
+    res
+}
+
+/// Mark `pager` as running in `rm` mode, generate its initial state and setup `out`
+///
+/// Returns `None` if the pager need not be started at all, for example when in static mode,
+/// all the text fits on the screen. The [`RunMode`] of the pager is reset if this fails or
+/// returns `None`.
+///
+/// # Panics
+/// Panics if the pager is already running.
+fn prepare<T: Terminal>(
+    pager: &Pager,
+    rm: RunMode,
+    out: &mut T,
+) -> Result<Option<PagerState>, MinusError> {
+    {
+        let mut runmode = pager.running.lock();
+        assert!(
+            runmode.is_uninitialized(),
+            "Failed to set the RunMode. This is caused probably because the pager is already running"
+        );
+        *runmode = rm;
+        drop(runmode);
+    }
+    let stop = || *pager.running.lock() = RunMode::Uninitialized;
+
+    // The initial commands must be applied as if the pager has not started yet
+    let mut ps = match PagerState::generate_initial_state(&pager.rx, out) {
+        Ok(ps) => ps,
+        Err(e) => {
+            stop();
+            return Err(e);
+        }
+    };
+    ps.running = pager.running.clone();
+    ps.marks = pager.marks.clone();
+
+    // Static mode checks
+    #[cfg(feature = "static_output")]
+    if rm == RunMode::Static {
+        // All of the text is given before the pager starts
+        ps.input_complete = true;
+        // If stdout is not a tty, write everything and quit
+        if !out.is_interactive() {
+            let res = ps.screen.write_text(out);
+            stop();
+            return res.map(|()| None);
+        }
+    }
+
+    // If number of lines of text is less than available rows, write everything and quit
+    // unless run_no_overflow is set to true. In dynamic mode, this is only known once the end
+    // of the input has been signalled.
+    if ps.input_complete && ps.fits_on_screen() {
+        let res = ps.write_all_rows(out);
+        ps.exit();
+        stop();
+        return res.map(|()| None);
+    }
+
+    // Setup terminal, adjust line wraps and get rows
+    if let Err(e) = ps.setup_terminal(out) {
+        stop();
+        return Err(e.into());
+    }
+    Ok(Some(ps))
+}
+
+/// Restore `out` and mark the pager as quit if the application panics
+///
+/// Returns a function that puts back the previous panic hook. It must be called once the
+/// pager quits so that the hooks of successive pagers don't pile up.
+fn set_panic_hook<T: Terminal>(out: &T, is_exited: Arc<AtomicBool>) -> impl FnOnce() {
+    let previous = Arc::new(panic::take_hook());
+    let panic_hook = previous.clone();
+    let panic_cleanup = out.panic_cleanup();
+    panic::set_hook(Box::new(move |pinfo| {
+        is_exited.store(true, std::sync::atomic::Ordering::SeqCst);
+        if let Some(cleanup) = panic_cleanup {
+            cleanup();
+        }
+        panic_hook(pinfo);
+    }));
+
+    move || {
+        // The hook cannot be changed while panicking
+        if std::thread::panicking() {
+            return;
+        }
+        // Dropping our hook releases its reference to the previous one, unless another pager
+        // started meanwhile has put its own hook in place
+        drop(panic::take_hook());
+        match Arc::try_unwrap(previous) {
+            Ok(previous) => panic::set_hook(previous),
+            Err(previous) => panic::set_hook(Box::new(move |pinfo| previous(pinfo))),
+        }
+    }
+}
+
+/// Same as [`init_core_with`] but runs the pager on the current task instead of spawning threads
+///
+/// User input is read from `events`, a stream of terminal events like crossterm's
+/// [`EventStream`](crossterm::event::EventStream). The pager always runs in
+/// [`RunMode::Dynamic`]. There is no timer sending [`Command::AnimateSpinner`], so the spinner
+/// is not animated.
+///
+/// # Errors
+///
+/// Setting/cleaning up the terminal can fail and IO to/from the terminal can
+/// fail.
+#[cfg(feature = "async")]
+pub async fn init_core_async<T, S>(
+    pager: &Pager,
+    out: &mut T,
+    mut events: S,
+) -> std::result::Result<(), MinusError>
+where
+    T: Terminal,
+    S: Stream<Item = io::Result<Event>> + Unpin + Send,
+{
+    let Some(mut ps) = prepare(pager, RunMode::Dynamic, out)? else {
+        return Ok(());
+    };
+
+    // Has the user quit
+    let is_exited = Arc::new(AtomicBool::new(false));
+    let restore_panic_hook = set_panic_hook(out, is_exited.clone());
+
+    let res = start_async_reactor(pager, &mut ps, out, &mut events, &is_exited).await;
+    is_exited.store(true, Ordering::SeqCst);
+    restore_panic_hook();
+
+    // Cleanup the screen
+    let cleanup = ps.cleanup_on_exit(out);
+    *pager.running.lock() = RunMode::Uninitialized;
+    res?;
+    cleanup?;
+
+    if ps.exit_strategy == ExitStrategy::ProcessQuit {
+        std::process::exit(0);
+    }
+    Ok(())
+}
+
+/// Asynchronous counterpart of [`start_reactor`] and [`event_reader`]
+///
+/// It waits for either a command from the [`Pager`] or an event from `events` and reacts to
+/// whichever comes first.
+#[cfg(feature = "async")]
+async fn start_async_reactor<T, S>(
+    pager: &Pager,
+    ps: &mut PagerState,
+    out: &mut T,
+    events: &mut S,
+    is_exited: &Arc<AtomicBool>,
+) -> Result<(), MinusError>
+where
+    T: Terminal,
+    S: Stream<Item = io::Result<Event>> + Unpin + Send,
+{
+    use futures_util::{
+        future::{select, Either},
+        StreamExt,
+    };
+
+    let mut command_queue = CommandQueue::new(ps.running.clone());
+    let input_active = Arc::new((Mutex::new(true), Condvar::new()));
+
+    draw_full(out, ps)?;
+    if ps.follow_output {
+        draw_for_change(out, ps, &mut (usize::MAX - 1))?;
+    }
+
+    while !is_exited.load(Ordering::SeqCst) {
+        let command = if let Some(command) = command_queue.pop_front() {
+            command
+        } else {
+            match select(pager.rx.recv_async(), events.next()).await {
+                Either::Left((command, _)) => {
+                    // The pager itself holds a sender, hence this never fails
+                    let Ok(command) = command else { break };
+                    command
+                }
+                Either::Right((Some(ev), _)) => {
+                    let ev = ev.map_err(|e| MinusError::HandleEvent(e.into()))?;
+                    let Some(iev) = classify_event(ev, ps) else {
+                        continue;
+                    };
+                    Command::UserInput(iev)
+                }
+                // No more input can come from the user
+                Either::Right((None, _)) => break,
+            }
+        };
+
+        handle_event(
+            command,
+            out,
+            ps,
+            &mut command_queue,
+            is_exited,
+            &Mutex::new(BlockingStream {
+                stream: events,
+                next: None,
+            }),
+            &input_active,
+        )?;
+    }
+    Ok(())
+}
+
+/// Lets the prompts read events from a stream by blocking the current thread
+#[cfg(feature = "async")]
+struct BlockingStream<'a, S> {
+    stream: &'a mut S,
+    next: Option<Event>,
+}
+
+#[cfg(feature = "async")]
+impl<S> EventSource for BlockingStream<'_, S>
+where
+    S: Stream<Item = io::Result<Event>> + Unpin + Send,
+{
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        use std::{
+            pin::Pin,
+            task::{Context, Poll, Wake, Waker},
+            thread::{self, Thread},
+            time::Instant,
+        };
+
+        struct ThreadWaker(Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        if self.next.is_some() {
+            return Ok(true);
+        }
+
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        // Wait forever if the deadline is too far away to be represented
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            match Pin::new(&mut *self.stream).poll_next(&mut cx) {
+                Poll::Ready(Some(ev)) => {
+                    self.next = Some(ev?);
+                    return Ok(true);
+                }
+                Poll::Ready(None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the event stream has ended",
+                    ))
+                }
+                Poll::Pending => match deadline {
+                    Some(deadline) => {
+                        let now = Instant::now();
+                        if now >= deadline {
+                            return Ok(false);
+                        }
+                        thread::park_timeout(deadline - now);
+                    }
+                    None => thread::park(),
+                },
+            }
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        if self.next.is_none() {
+            self.poll(Duration::MAX)?;
+        }
+        Ok(self.next.take().unwrap())
+    }
 }
 
 /// Continuously displays the output and reacts to events
@@ -215,111 +456,76 @@ pub fn init_core(pager: &Pager, rm: RunMode) -> std::result::Result<(), MinusErr
 /// [`AppendData`](super::events::Event::AppendData) event occurs, it is absolutely necessory
 /// to update the screen immediately; while if all rows are filled, we can omit to redraw the
 /// screen.
-#[allow(clippy::too_many_lines)]
-fn start_reactor(
+///
+/// Once the user quits, it cleans up the terminal and quits the process if the
+/// [`ExitStrategy`] requires it.
+fn start_reactor<T>(
     rx: &Receiver<Command>,
     ps: &Arc<Mutex<PagerState>>,
-    out: &Stdout,
-    #[cfg(feature = "search")] input_thread_running: &Arc<(Mutex<bool>, Condvar)>,
+    out: &mut T,
+    events: &Mutex<dyn EventSource + '_>,
+    input_thread_running: &Arc<(Mutex<bool>, Condvar)>,
     is_exited: &Arc<AtomicBool>,
-) -> Result<(), MinusError> {
-    let mut out_lock = out.lock();
-    let mut command_queue = CommandQueue::new();
+) -> Result<(), MinusError>
+where
+    T: Terminal,
+{
+    let mut p = ps.lock();
+    let running = p.running.clone();
+    let mut command_queue = CommandQueue::new(running.clone());
 
     {
-        let mut p = ps.lock();
-
-        draw_full(&mut out_lock, &mut p)?;
+        draw_full(out, &mut p)?;
 
         if p.follow_output {
-            draw_for_change(&mut out_lock, &mut p, &mut (usize::MAX - 1))?;
+            draw_for_change(out, &mut p, &mut (usize::MAX - 1))?;
         }
     }
+    drop(p);
 
-    let run_mode = *RUNMODE.lock();
-    match run_mode {
-        #[cfg(feature = "dynamic_output")]
-        RunMode::Dynamic => loop {
-            if is_exited.load(Ordering::SeqCst) {
-                let mut rm = RUNMODE.lock();
-                *rm = RunMode::Uninitialized;
-                drop(rm);
-                break;
-            }
-
-            let next_command = if command_queue.is_empty() {
-                rx.recv()
-            } else {
-                Ok(command_queue.pop_front().unwrap())
-            };
-
-            if let Ok(command) = next_command {
-                let mut p = ps.lock();
-                handle_event(
-                    command,
-                    &mut out_lock,
-                    &mut p,
-                    &mut command_queue,
-                    is_exited,
-                    #[cfg(feature = "search")]
-                    input_thread_running,
-                )?;
-            }
-        },
-        #[cfg(feature = "static_output")]
-        RunMode::Static => {
-            {
-                let mut p = ps.lock();
-                if p.follow_output {
-                    display::draw_for_change(&mut out_lock, &mut p, &mut (usize::MAX - 1))?;
-                }
-            }
-
-            loop {
-                if is_exited.load(Ordering::SeqCst) {
-                    // Cleanup the screen
-                    //
-                    // This is not needed in dynamic paging because this is already handled by handle_event
-                    term::cleanup(&mut out_lock, &ps.lock().exit_strategy, true)?;
+    assert!(
+        !running.lock().is_uninitialized(),
+        "RunMode of the pager set to uninitialized.\
+This is most likely a bug. Please open an issue to the developers"
+    );
 
-                    let mut rm = RUNMODE.lock();
-                    *rm = RunMode::Uninitialized;
-                    drop(rm);
+    loop {
+        if is_exited.load(Ordering::SeqCst) {
+            break;
+        }
 
-                    break;
-                }
-                let next_command = if command_queue.is_empty() {
-                    rx.recv()
-                } else {
-                    Ok(command_queue.pop_front().unwrap())
-                };
-
-                if let Ok(command) = next_command {
-                    let mut p = ps.lock();
-                    handle_event(
-                        command,
-                        &mut out_lock,
-                        &mut p,
-                        &mut command_queue,
-                        is_exited,
-                        #[cfg(feature = "search")]
-                        input_thread_running,
-                    )?;
-                }
-            }
+        let next_command = if command_queue.is_empty() {
+            rx.recv()
+        } else {
+            Ok(command_queue.pop_front().unwrap())
+        };
+
+        if let Ok(command) = next_command {
+            let mut p = ps.lock();
+            handle_event(
+                command,
+                out,
+                &mut p,
+                &mut command_queue,
+                is_exited,
+                events,
+                input_thread_running,
+            )?;
         }
-        RunMode::Uninitialized => panic!(
-            "Static variable RUNMODE set to uninitialized.\
-This is most likely a bug. Please open an issue to the developers"
-        ),
     }
+
+    // Cleanup the screen
+    ps.lock().cleanup_on_exit(out)?;
+
+    *running.lock() = RunMode::Uninitialized;
     Ok(())
 }
 
 fn event_reader(
     evtx: &Sender<Command>,
     ps: &Arc<Mutex<PagerState>>,
-    #[cfg(feature = "search")] user_input_active: &Arc<(Mutex<bool>, Condvar)>,
+    events: &Mutex<impl EventSource>,
+    user_input_active: &Arc<(Mutex<bool>, Condvar)>,
     is_exited: &Arc<AtomicBool>,
 ) -> Result<(), MinusError> {
     loop {
@@ -327,7 +533,6 @@ fn event_reader(
             break;
         }
 
-        #[cfg(feature = "search")]
         {
             let (lock, cvar) = (&user_input_active.0, &user_input_active.1);
             let mut active = lock.lock();
@@ -336,29 +541,99 @@ fn event_reader(
             }
         }
 
-        if event::poll(std::time::Duration::from_millis(100))
-            .map_err(|e| MinusError::HandleEvent(e.into()))?
-        {
-            let ev = event::read().map_err(|e| MinusError::HandleEvent(e.into()))?;
-            let mut guard = ps.lock();
-            // Get the events
-            let input = guard.input_classifier.classify_input(ev, &guard);
-            if let Some(iev) = input {
-                if let InputEvent::Number(n) = iev {
-                    guard.prefix_num.push(n);
-                    guard.format_prompt();
-                } else if !guard.prefix_num.is_empty() {
-                    guard.prefix_num.clear();
-                    guard.format_prompt();
-                }
-                if let Err(TrySendError::Disconnected(_)) = evtx.try_send(Command::UserInput(iev)) {
-                    break;
+        // Don't hold the event source while classifying the input. The search prompt locks the
+        // event source while holding the PagerState
+        let ev = {
+            let mut events = events.lock();
+            if events
+                .poll(Duration::from_millis(100))
+                .map_err(|e| MinusError::HandleEvent(e.into()))?
+            {
+                Some(
+                    events
+                        .read()
+                        .map_err(|e| MinusError::HandleEvent(e.into()))?,
+                )
+            } else {
+                None
+            }
+        };
+
+        let Some(ev) = ev else {
+            // Animate the spinner while waiting for data. Skipping a frame when the channel is
+            // full does no harm
+            if ps.lock().is_waiting_for_data() {
+                let _ = evtx.try_send(Command::AnimateSpinner);
+            }
+            continue;
+        };
+        let input = classify_event(ev, &mut ps.lock());
+        if let Some(iev) = input {
+            // The channel may be bounded, so keep checking whether the pager has quit
+            // while waiting for it to have some space
+            let mut command = Command::UserInput(iev);
+            loop {
+                match evtx.send_timeout(command, Duration::from_millis(100)) {
+                    Ok(()) => break,
+                    Err(SendTimeoutError::Timeout(c)) if !is_exited.load(Ordering::SeqCst) => {
+                        command = c;
+                    }
+                    Err(_) => return Ok(()),
                 }
-            } else if !guard.prefix_num.is_empty() {
-                guard.prefix_num.clear();
-                guard.format_prompt();
             }
         }
     }
     Result::<(), MinusError>::Ok(())
 }
+
+/// Classify `ev` using the [`InputClassifier`](crate::input::InputClassifier) of `ps`
+///
+/// This also keeps track of the numbers typed as prefix to an action.
+pub fn classify_event(ev: Event, ps: &mut PagerState) -> Option<InputEvent> {
+    // The character typed after `InputEvent::ReadMark` names the mark, any other key cancels it
+    if let Some(action) = ps.pending_mark.take() {
+        if let Event::Key(KeyEvent {
+            code: KeyCode::Char(name),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            ..
+        }) = ev
+        {
+            return Some(match action {
+                MarkAction::Set => InputEvent::SetMark(name),
+                MarkAction::Jump => InputEvent::JumpToMark(name),
+                MarkAction::Copy => InputEvent::CopyToMark(name),
+            });
+        }
+    }
+    // While hints are labelled, the characters typed select them and any other key cancels it
+    if ps.hints.is_some() {
+        if let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = ev
+        {
+            return Some(match code {
+                KeyCode::Char(c)
+                    if matches!(modifiers, KeyModifiers::NONE | KeyModifiers::SHIFT) =>
+                {
+                    InputEvent::SelectHint(c)
+                }
+                _ => InputEvent::ShowHints(false),
+            });
+        }
+    }
+    let input = ps.input_classifier.classify_input(ev, ps);
+    if let Some(InputEvent::ReadMark(action)) = input {
+        ps.pending_mark = Some(action);
+    }
+    if input == Some(InputEvent::ShowHints(true)) {
+        ps.hints = Some(Hints::default());
+    }
+    if let Some(InputEvent::Number(n)) = input {
+        ps.prefix_num.push(n);
+        ps.format_prompt();
+    } else if !ps.prefix_num.is_empty() {
+        ps.prefix_num.clear();
+        ps.format_prompt();
+    }
+    input
+}
@@ -1,11 +1,11 @@
-use std::collections::VecDeque;
+use parking_lot::Mutex;
+use std::{collections::VecDeque, sync::Arc};
 
 pub mod commands;
 pub mod ev_handler;
 #[cfg(any(feature = "dynamic_output", feature = "static_output"))]
 pub mod init;
 pub mod utils;
-pub static RUNMODE: parking_lot::Mutex<RunMode> = parking_lot::const_mutex(RunMode::Uninitialized);
 
 use commands::Command;
 
@@ -16,48 +16,57 @@ use commands::Command;
 /// requires the text data to be reformatted and repainted on the screen. Hence it can push that
 /// command to this to be executed once it itself has completed executing.
 ///
-/// This also takes into account [RUNMODE] before inserting data. The means that it will ensure that
-/// [RUNMODE] is not uninitialized before pushing any data into the queue. Hence it is best used
-/// case is while declaring handlers for [Command::UserInput].
+/// This also takes into account the [RunMode] of the pager before inserting data. The means that it
+/// will ensure that the pager is running before pushing any data into the queue. Hence it is best
+/// used case is while declaring handlers for [Command::UserInput].
 ///
 /// This is a FIFO type hence the command that enters first gets executed first.
-pub struct CommandQueue(VecDeque<Command>);
+pub struct CommandQueue {
+    queue: VecDeque<Command>,
+    running: Arc<Mutex<RunMode>>,
+}
 
 impl CommandQueue {
-    /// Create a new CommandQueue with default size of 10.
-    pub fn new() -> Self {
-        Self(VecDeque::with_capacity(10))
+    /// Create a new CommandQueue with default size of 10 for a pager running in `running` mode.
+    pub fn new(running: Arc<Mutex<RunMode>>) -> Self {
+        Self {
+            queue: VecDeque::with_capacity(10),
+            running,
+        }
     }
     /// Create a new CommandQueue with zero memory allocation.
     ///
     /// This is useful when we have to pass this type to [handle_event](ev_handler::handle_event)
     /// but it is sure that this won't be used.
-    pub fn new_zero() -> Self {
-        Self(VecDeque::with_capacity(0))
+    pub fn new_zero(running: Arc<Mutex<RunMode>>) -> Self {
+        Self {
+            queue: VecDeque::with_capacity(0),
+            running,
+        }
     }
     /// Returns true if the queue is empty.
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.queue.is_empty()
     }
-    /// Store `value` only if [RUNMODE] is not unintialized.
+    /// Store `value` only if the pager is running.
     ///
     /// # Panics
-    /// This function will panic if it is called in an environment where [RUNMODE] is
-    /// uninitialized.
+    /// This function will panic if it is called in an environment where the [RunMode] of the
+    /// pager is uninitialized.
     pub fn push_back(&mut self, value: Command) {
-        assert!(!RUNMODE.lock().is_uninitialized(), "CommandQueue::push_back() caled when  RUNMODE is not set. This is most likely a bug. Please report the issue on minus's issue tracker on Github.");
-        self.0.push_back(value);
+        assert!(!self.running.lock().is_uninitialized(), "CommandQueue::push_back() caled when the pager is not running. This is most likely a bug. Please report the issue on minus's issue tracker on Github.");
+        self.queue.push_back(value);
     }
-    /// Store `value` without checking [RUNMODE].
+    /// Store `value` without checking the [RunMode].
     ///
     /// This is only meant to be used as an optimization over [push_back](CommandQueue::push_back)
-    /// when it is absolutely sure that [RUNMODE] isn't uninitialized. Hence calling this in an
-    /// enviroment where [RUNMODE] is uninitialized can lead to unexpect slowdowns.
+    /// when it is absolutely sure that the pager is running. Hence calling this in an
+    /// enviroment where the [RunMode] is uninitialized can lead to unexpect slowdowns.
     pub fn push_back_unchecked(&mut self, value: Command) {
-        self.0.push_back(value);
+        self.queue.push_back(value);
     }
     pub fn pop_front(&mut self) -> Option<Command> {
-        self.0.pop_front()
+        self.queue.pop_front()
     }
 }
 
@@ -3,14 +3,18 @@
 use crossterm::{
     cursor::MoveTo,
     execute, queue,
+    style::Stylize,
     terminal::{Clear, ClearType},
 };
 
-use std::{cmp::Ordering, convert::TryInto, io::Write};
+use std::{borrow::Cow, cmp::Ordering, convert::TryInto, io::Write};
 
-use super::term;
-use crate::screen::Row;
-use crate::{error::MinusError, minus_core, LineNumbers, PagerState};
+use super::{selection, term, LinesRowMap};
+use crate::screen::{
+    gutter::{split_gutter, Gutter},
+    Row,
+};
+use crate::{error::MinusError, hints::Hints, minus_core, LineNumbers, PagerState};
 
 /// How should the incoming text be drawn on the screen
 #[derive(Debug, PartialEq, Eq)]
@@ -38,11 +42,11 @@ pub fn draw_for_change(
 ) -> Result<(), MinusError> {
     let line_count = ps.screen.formatted_lines_count();
 
-    // Reduce one row for prompt/messages
+    // Rows of the focused pane, which is one less than the total rows for prompt/messages
     //
     // NOTE This should be the value of rows that should be used throughout this function.
     // Don't use PagerState::rows, it might lead to wrong output
-    let writable_rows = ps.rows.saturating_sub(1);
+    let writable_rows = ps.pane_rows();
 
     // Calculate the lower_bound for current and new upper marks
     // by adding either the rows or line_count depending on the minimality
@@ -55,6 +59,21 @@ pub fn draw_for_change(
         *new_upper_mark = line_count.saturating_sub(writable_rows);
     }
 
+    // Scrolling the terminal would move both panes of a split view and keep the relative line
+    // numbers of the rows that stay on the screen. The rows that scroll into view may also be
+    // part of the selection. When drawn inline, it would also move the text above the pager.
+    if ps.split.is_some()
+        || ps.inline_rows.is_some()
+        || ps.selection.is_some()
+        || (ps.screen.gutter.relative && ps.line_numbers.is_on())
+    {
+        if *new_upper_mark != ps.upper_mark {
+            ps.upper_mark = *new_upper_mark;
+            draw_full(out, ps)?;
+        }
+        return Ok(());
+    }
+
     let delta = new_upper_mark.abs_diff(ps.upper_mark);
     // Sometimes the value of delta is too large that we can rather use the value of the writable rows to
     // achieve the same effect with better performance. This means that we have draw to less lines to the terminal
@@ -70,6 +89,12 @@ pub fn draw_for_change(
     // need this value whatever the value of delta be.
     let normalized_delta = delta.min(writable_rows);
 
+    // Format the rows of the new page if they aren't kept in memory
+    ps.load_rows(
+        *new_upper_mark,
+        new_upper_mark.saturating_add(writable_rows),
+    );
+
     let lines = match (*new_upper_mark).cmp(&ps.upper_mark) {
         Ordering::Greater => {
             // Scroll down `normalized_delta` lines, and put the cursor one line above, where the old prompt would present.
@@ -120,23 +145,53 @@ pub fn draw_for_change(
         ps.cols,
         ps.screen.line_wrapping,
         ps.left_mark,
-        ps.line_numbers.is_on(),
-        ps.screen.line_count(),
+        ps.screen.gutter_width(ps.line_numbers),
     )?;
 
     ps.upper_mark = *new_upper_mark;
 
     if ps.show_prompt {
-        super::display::write_prompt(out, &ps.displayed_prompt, ps.rows.try_into().unwrap())?;
+        // The prompt may show the position in the text
+        if ps.prompt_tracks_position() {
+            ps.format_prompt();
+        }
+        super::display::write_prompt(out, &ps.displayed_prompt, ps.prompt_row())?;
     }
     out.flush()?;
 
     Ok(())
 }
 
-/// Write given text at the prompt site
-pub fn write_prompt(out: &mut impl Write, text: &str, rows: u16) -> Result<(), MinusError> {
-    write!(out, "{mv}\r{prompt}", mv = MoveTo(0, rows), prompt = text)?;
+/// Write given text at the prompt site on the row `row`
+pub fn write_prompt(out: &mut impl Write, text: &str, row: u16) -> Result<(), MinusError> {
+    write!(out, "{mv}\r{prompt}", mv = MoveTo(0, row), prompt = text)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Draw the rest of the labels of `hints` that match the part typed so far over the text
+///
+/// The rows of the labels are counted from `top`, the row where the pager starts.
+pub fn write_hint_labels(
+    out: &mut impl Write,
+    hints: &Hints,
+    top: usize,
+) -> Result<(), MinusError> {
+    for label in hints.matching() {
+        term::move_cursor(
+            out,
+            label
+                .column
+                .try_into()
+                .map_err(|_| MinusError::Conversion)?,
+            (top + label.row)
+                .try_into()
+                .map_err(|_| MinusError::Conversion)?,
+            false,
+        )?;
+        let rest = &label.text[hints.typed.len()..];
+        write!(out, "{}", rest.black().on_yellow().bold())?;
+    }
     out.flush()?;
     Ok(())
 }
@@ -153,15 +208,16 @@ pub fn write_prompt(out: &mut impl Write, text: &str, rows: u16) -> Result<(), M
 ///   - If there is one, it will display it at the prompt site
 ///   - If there isn't one, it will display the prompt in place of it
 pub fn draw_full(out: &mut impl Write, ps: &mut PagerState) -> Result<(), MinusError> {
-    super::term::move_cursor(out, 0, 0, false)?;
-    queue!(out, Clear(ClearType::All))?;
+    let top = ps.top.try_into().map_err(|_| MinusError::Conversion)?;
+    term::clear_from_row(out, top, false)?;
 
     write_from_pagerstate(out, ps)?;
 
-    let pager_rows: u16 = ps.rows.try_into().map_err(|_| MinusError::Conversion)?;
-
     if ps.show_prompt {
-        write_prompt(out, &ps.displayed_prompt, pager_rows)?;
+        if ps.prompt_tracks_position() {
+            ps.format_prompt();
+        }
+        write_prompt(out, &ps.displayed_prompt, ps.prompt_row())?;
     }
 
     out.flush().map_err(MinusError::Draw)
@@ -169,6 +225,7 @@ pub fn draw_full(out: &mut impl Write, ps: &mut PagerState) -> Result<(), MinusE
 
 pub fn draw_append_text(
     out: &mut impl Write,
+    top: usize,
     rows: usize,
     prev_unterminated: usize,
     prev_fmt_lines_count: usize,
@@ -183,8 +240,7 @@ pub fn draw_append_text(
         term::move_cursor(
             out,
             0,
-            prev_fmt_lines_count
-                .saturating_sub(prev_unterminated)
+            (top + prev_fmt_lines_count.saturating_sub(prev_unterminated))
                 .try_into()
                 .unwrap(),
             false,
@@ -231,20 +287,28 @@ pub fn draw_append_text(
 /// This function ensures that upper mark never exceeds a value such that adding upper mark and available rows exceeds
 /// the number of lines of text data. This rule is disobeyed in only one special case which is if number of lines of
 /// text is less than available rows. In this situation, upper mark is always 0.
+///
+/// `lines` may contain only a part of all the `line_count` rows of text, starting at the row `lines_start`.
+/// The text is drawn from the row `top` of the terminal downwards. The line numbers are offset by
+/// the `line_offset` lines dropped from the start of the text.
 #[allow(clippy::too_many_arguments)]
 pub fn write_text_checked(
     out: &mut impl Write,
+    top: u16,
     lines: &[String],
+    lines_start: usize,
+    line_count: usize,
     mut upper_mark: usize,
     rows: usize,
     cols: usize,
     line_wrapping: bool,
     left_mark: usize,
     line_numbers: LineNumbers,
+    gutter: &Gutter,
+    lines_to_row_map: &LinesRowMap,
     total_line_count: usize,
+    line_offset: usize,
 ) -> Result<(), MinusError> {
-    let line_count = lines.len();
-
     // Reduce one row for prompt/messages
     let writable_rows = rows.saturating_sub(1);
 
@@ -260,23 +324,36 @@ pub fn write_text_checked(
     }
 
     // Add \r to ensure cursor is placed at the beginning of each row
-    let display_lines: &[String] = &lines[upper_mark..lower_mark];
+    let lines_end = lines_start + lines.len();
+    let display_lines: &[String] = &lines[upper_mark.clamp(lines_start, lines_end) - lines_start
+        ..lower_mark.clamp(lines_start, lines_end) - lines_start];
+
+    let digits = minus_core::utils::digits(line_offset + total_line_count);
+    let display_lines = gutter.fill_relative(
+        display_lines,
+        upper_mark,
+        line_numbers.is_on(),
+        lines_to_row_map,
+        digits,
+        line_offset,
+    );
 
-    term::move_cursor(out, 0, 0, false)?;
-    term::clear_entire_screen(out, false)?;
+    term::clear_from_row(out, top, false)?;
 
     write_lines(
         out,
-        display_lines,
+        &display_lines,
         cols,
         line_wrapping,
         left_mark,
-        line_numbers.is_on(),
-        total_line_count,
+        gutter.width(line_numbers.is_on(), digits),
     )
 }
 
 pub fn write_from_pagerstate(out: &mut impl Write, ps: &mut PagerState) -> Result<(), MinusError> {
+    if ps.split.is_some() {
+        return write_split_panes(out, ps);
+    }
     let line_count = ps.screen.formatted_lines_count();
 
     // Reduce one row for prompt/messages
@@ -289,78 +366,184 @@ pub fn write_from_pagerstate(out: &mut impl Write, ps: &mut PagerState) -> Resul
         ps.upper_mark = line_count.saturating_sub(writable_rows);
     }
 
+    ps.load_rows(ps.upper_mark, lower_mark);
+
     // Add \r to ensure cursor is placed at the beginning of each row
-    let display_lines: &[String] = ps
-        .screen
-        .get_formatted_lines_with_bounds(ps.upper_mark, lower_mark);
+    let display_lines = numbered_rows(ps, ps.upper_mark, lower_mark);
 
     write_lines(
         out,
-        display_lines,
+        &display_lines,
         ps.cols,
         ps.screen.line_wrapping,
         ps.left_mark,
-        ps.line_numbers.is_on(),
-        ps.screen.line_count(),
+        ps.screen.gutter_width(ps.line_numbers),
     )
 }
 
+/// Rows of the current buffer from `upper_mark` up to `lower_mark` with their relative line
+/// numbers filled in and the selected text highlighted
+fn numbered_rows(ps: &PagerState, upper_mark: usize, lower_mark: usize) -> Cow<'_, [Row]> {
+    let rows = ps.screen.gutter.fill_relative(
+        ps.screen
+            .get_formatted_lines_with_bounds(upper_mark, lower_mark),
+        upper_mark,
+        ps.line_numbers.is_on(),
+        &ps.lines_to_row_map,
+        ps.screen.line_number_digits(),
+        ps.screen.dropped_lines,
+    );
+    let Some(selection) = ps.selection else {
+        return rows;
+    };
+    let gutter_width = ps.screen.gutter_width(ps.line_numbers);
+    let highlighted = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            selection.columns(upper_mark + i).map_or_else(
+                || row.clone(),
+                |columns| {
+                    let (gutter, text) = split_gutter(row, gutter_width);
+                    gutter.to_string() + &selection::highlight(text, &columns)
+                },
+            )
+        })
+        .collect();
+    Cow::Owned(highlighted)
+}
+
+/// Write both panes of a split view along with the separator between them
+///
+/// Like [`write_from_pagerstate`], the upper mark of each pane is adjusted so that it never
+/// scrolls past the last row of text.
+fn write_split_panes(out: &mut impl Write, ps: &mut PagerState) -> Result<(), MinusError> {
+    const SEPARATOR_SPEC: &str = "\x1b[2m";
+    const RESET: &str = "\x1b[0m";
+
+    let Some(mut split) = ps.split else {
+        return Ok(());
+    };
+    let line_count = ps.screen.formatted_lines_count();
+    let (top_rows, bottom_rows) = PagerState::pane_heights(ps.rows.saturating_sub(1));
+    let (focused_rows, other_rows) = if split.bottom_focused {
+        (bottom_rows, top_rows)
+    } else {
+        (top_rows, bottom_rows)
+    };
+    ps.upper_mark = ps.upper_mark.min(line_count.saturating_sub(focused_rows));
+    split.other_upper_mark = split
+        .other_upper_mark
+        .min(line_count.saturating_sub(other_rows));
+    ps.split = Some(split);
+
+    let (top_mark, bottom_mark) = if split.bottom_focused {
+        (split.other_upper_mark, ps.upper_mark)
+    } else {
+        (ps.upper_mark, split.other_upper_mark)
+    };
+
+    write_pane(out, ps, top_mark, top_rows)?;
+    term::move_cursor(out, 0, (ps.top + top_rows).try_into().unwrap(), false)?;
+    // The arrow points towards the focused pane
+    let arrow = if split.bottom_focused { '▼' } else { '▲' };
+    writeln!(
+        out,
+        "\r{SEPARATOR_SPEC}{arrow}{line}{RESET}",
+        line = "─".repeat(ps.cols.saturating_sub(1))
+    )?;
+    write_pane(out, ps, bottom_mark, bottom_rows)
+}
+
+/// Write `rows` rows starting from `upper_mark` from the current position of the cursor
+fn write_pane(
+    out: &mut impl Write,
+    ps: &mut PagerState,
+    upper_mark: usize,
+    rows: usize,
+) -> Result<(), MinusError> {
+    let lower_mark = upper_mark.saturating_add(rows);
+    ps.load_rows(upper_mark, lower_mark);
+    let lines = numbered_rows(ps, upper_mark, lower_mark);
+    write_lines(
+        out,
+        &lines,
+        ps.cols,
+        ps.screen.line_wrapping,
+        ps.left_mark,
+        ps.screen.gutter_width(ps.line_numbers),
+    )?;
+    // Fill the rest of the pane if there isn't enough text
+    for _ in lines.len()..rows {
+        writeln!(out, "\r")?;
+    }
+    Ok(())
+}
+
 pub fn write_lines(
     out: &mut impl Write,
     lines: &[String],
     cols: usize,
     line_wrapping: bool,
     left_mark: usize,
-    line_numbers: bool,
-    line_count: usize,
+    gutter_width: usize,
 ) -> crate::Result {
     if line_wrapping {
         write_raw_lines(out, lines, Some("\r"))
     } else {
-        write_lines_in_horizontal_scroll(out, lines, cols, left_mark, line_numbers, line_count)
+        write_lines_in_horizontal_scroll(out, lines, cols, left_mark, gutter_width)
     }
 }
 
+/// Write the part of `lines` that is visible when scrolled `start` columns to the right
+///
+/// The gutter that takes up the first `gutter_width` columns of each line is not scrolled.
 pub fn write_lines_in_horizontal_scroll(
     out: &mut impl Write,
     lines: &[String],
     cols: usize,
     start: usize,
-    line_numbers: bool,
-    line_count: usize,
+    gutter_width: usize,
 ) -> crate::Result {
-    let line_number_ascii_seq_len = if line_numbers { 8 } else { 0 };
-    let line_number_padding = if line_numbers {
-        minus_core::utils::digits(line_count) + LineNumbers::EXTRA_PADDING + 3
-    } else {
-        0
-    };
-    let shifted_start = if line_numbers {
-        start + line_number_padding + line_number_ascii_seq_len
-    } else {
-        start
-    };
+    let cols = cols.saturating_sub(gutter_width);
 
     for line in lines {
-        let end = shifted_start + cols.min(line.len().saturating_sub(shifted_start))
-            - line_number_padding;
-
-        if start < line.len() {
-            if line_numbers {
-                writeln!(
-                    out,
-                    "\r{}{}",
-                    &line[0..line_number_padding + line_number_ascii_seq_len],
-                    &line[shifted_start..end]
-                )?;
-            } else {
-                writeln!(out, "\r{}", &line[shifted_start..end])?;
+        let (gutter, text) = split_gutter(line, gutter_width);
+        writeln!(out, "\r{gutter}{}", visible_columns(text, start, cols))?;
+    }
+    Ok(())
+}
+
+/// The `cols` columns of `text` starting at the column `start`
+///
+/// All the escape sequences of `text` are kept so that the visible part is styled the same way
+/// as it would be if the whole of `text` was displayed.
+fn visible_columns(text: &str, start: usize, cols: usize) -> String {
+    let end = start.saturating_add(cols);
+    let mut visible = String::new();
+    let mut column = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            visible.push(c);
+            if chars.next_if_eq(&'[').is_some() {
+                visible.push('[');
+                for c in chars.by_ref() {
+                    visible.push(c);
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
             }
-        } else {
-            writeln!(out, "\r")?;
+            continue;
         }
+        let width = textwrap::core::display_width(c.encode_utf8(&mut [0; 4]));
+        if column >= start && column + width <= end {
+            visible.push(c);
+        }
+        column += width;
     }
-    Ok(())
+    visible
 }
 
 /// Write lines to the the output
@@ -1,6 +1,9 @@
 #![allow(clippy::shadow_unrelated)]
 #![allow(clippy::cast_possible_truncation)]
-use super::{draw_for_change, draw_full, write_from_pagerstate, write_prompt};
+use super::{
+    draw_for_change, draw_full, write_from_pagerstate, write_lines_in_horizontal_scroll,
+    write_prompt,
+};
 use crate::{LineNumbers, PagerState};
 use std::fmt::Write;
 
@@ -16,7 +19,7 @@ fn short_no_line_numbers() {
     let lines = "A line\nAnother line";
     let mut pager = PagerState::new().unwrap();
 
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.format_lines();
 
     let mut out = Vec::with_capacity(lines.len());
@@ -52,7 +55,7 @@ fn long_no_line_numbers() {
     let mut pager = PagerState::new().unwrap();
     // One extra line for prompt
     pager.rows = 4;
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.format_lines();
 
     assert!(write_from_pagerstate(&mut out, &mut pager).is_ok());
@@ -65,7 +68,10 @@ fn long_no_line_numbers() {
 
     // This ensures that asking for a position other than 0 works.
     let mut out = Vec::with_capacity(lines.len());
-    pager.screen.orig_text = "Another line\nThird line\nFourth line\nFifth line\n".to_string();
+    pager
+        .screen
+        .set_text("Another line\nThird line\nFourth line\nFifth line\n")
+        .unwrap();
     pager.upper_mark = 1;
     pager.format_lines();
 
@@ -97,7 +103,7 @@ fn short_with_line_numbers() {
 
     let mut out = Vec::with_capacity(lines.len());
     let mut pager = PagerState::new().unwrap();
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.line_numbers = LineNumbers::Enabled;
     pager.format_lines();
 
@@ -132,7 +138,7 @@ fn long_with_line_numbers() {
     let mut out = Vec::with_capacity(lines.len());
     let mut pager = PagerState::new().unwrap();
     pager.rows = 4;
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.line_numbers = LineNumbers::Enabled;
     pager.format_lines();
 
@@ -184,7 +190,7 @@ fn big_line_numbers_are_padded() {
     let mut pager = PagerState::new().unwrap();
     pager.upper_mark = 95;
     pager.rows = 11;
-    pager.screen.orig_text = lines;
+    pager.screen.set_text(&lines).unwrap();
     pager.line_numbers = LineNumbers::AlwaysOn;
     pager.format_lines();
 
@@ -227,7 +233,7 @@ fn draw_short_no_line_numbers() {
 
     let mut out = Vec::with_capacity(lines.len());
     let mut pager = PagerState::new().unwrap();
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.line_numbers = LineNumbers::AlwaysOff;
     pager.format_lines();
 
@@ -259,7 +265,7 @@ fn draw_long_no_line_numbers() {
     let mut out = Vec::with_capacity(lines.len());
     let mut pager = PagerState::new().unwrap();
     pager.rows = 3;
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.format_lines();
 
     assert!(draw_full(&mut out, &mut pager).is_ok());
@@ -298,7 +304,7 @@ fn draw_short_with_line_numbers() {
     let lines = "A line\nAnother line";
     let mut out = Vec::with_capacity(lines.len());
     let mut pager = PagerState::new().unwrap();
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.line_numbers = LineNumbers::Enabled;
     pager.format_lines();
 
@@ -329,7 +335,7 @@ fn draw_long_with_line_numbers() {
     let mut out = Vec::with_capacity(lines.len());
     let mut pager = PagerState::new().unwrap();
     pager.rows = 3;
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.line_numbers = LineNumbers::Enabled;
     pager.format_lines();
 
@@ -377,7 +383,7 @@ fn draw_big_line_numbers_are_padded() {
     let mut out = Vec::with_capacity(lines.len());
     let mut pager = PagerState::new().unwrap();
     pager.upper_mark = 95;
-    pager.screen.orig_text = lines;
+    pager.screen.set_text(&lines).unwrap();
     pager.line_numbers = LineNumbers::Enabled;
     pager.format_lines();
 
@@ -402,7 +408,7 @@ fn draw_wrapping_line_numbers() {
 
     let mut out = Vec::new();
     let mut pager = PagerState::new().unwrap();
-    pager.screen.orig_text = lines;
+    pager.screen.set_text(&lines).unwrap();
     pager.cols = 30;
     pager.upper_mark = 2;
     pager.line_numbers = LineNumbers::Enabled;
@@ -421,7 +427,7 @@ fn draw_help_message() {
 
     let mut out = Vec::with_capacity(lines.len());
     let mut pager = PagerState::new().unwrap();
-    pager.screen.orig_text = lines.to_string();
+    pager.screen.set_text(lines).unwrap();
     pager.line_numbers = LineNumbers::AlwaysOff;
     pager.format_prompt();
 
@@ -431,12 +437,40 @@ fn draw_help_message() {
     assert!(res.contains("minus"));
 }
 
+#[test]
+fn long_prompt_is_cut() {
+    let mut pager = PagerState::new().unwrap();
+    pager.cols = 6;
+    pager.prompt = "préfixé préfixé".to_string();
+    pager.format_prompt();
+    assert!(pager.displayed_prompt.contains("préfix"));
+    assert!(!pager.displayed_prompt.contains("préfixé"));
+
+    // No room is left for the prompt next to the indicators
+    pager.cols = 2;
+    pager.follow_output = true;
+    pager.loading = 1;
+    pager.format_prompt();
+    assert!(!pager.displayed_prompt.contains('p'));
+}
+
+#[cfg(feature = "static_output")]
+#[test]
+fn static_prompt_shows_end() {
+    let mut pager = PagerState::new().unwrap();
+    *pager.running.lock() = crate::RunMode::Static;
+    pager.input_complete = true;
+    pager.append_str("a\nb\n");
+    pager.format_prompt();
+    assert!(pager.displayed_prompt.contains("(END)"));
+}
+
 #[test]
 fn test_draw_no_overflow() {
     const TEXT: &str = "This is a line of text to the pager";
     let mut out = Vec::with_capacity(TEXT.len());
     let mut pager = PagerState::new().unwrap();
-    pager.screen.orig_text = TEXT.to_string();
+    pager.screen.set_text(TEXT).unwrap();
     pager.format_lines();
     draw_full(&mut out, &mut pager).unwrap();
     assert!(String::from_utf8(out)
@@ -444,6 +478,14 @@ fn test_draw_no_overflow() {
         .contains(TEXT));
 }
 
+#[test]
+fn horizontal_scroll_keeps_escape_sequences() {
+    let lines = ["ab\x1b[31mcdé\x1b[0mfg".to_string()];
+    let mut out = Vec::new();
+    write_lines_in_horizontal_scroll(&mut out, &lines, 3, 1, 0).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "\rb\x1b[31mcd\x1b[0m\n");
+}
+
 #[cfg(test)]
 mod draw_for_change_tests {
     use super::{draw_for_change, write_prompt};
@@ -465,7 +507,7 @@ mod draw_for_change_tests {
         };
         let mut ps = PagerState::new().unwrap();
         ps.upper_mark = 0;
-        ps.screen.orig_text = lines;
+        ps.screen.set_text(&lines).unwrap();
         ps.format_lines();
         ps.format_prompt();
         ps
@@ -488,7 +530,7 @@ mod draw_for_change_tests {
         for line in &ps.screen.formatted_lines[9..12] {
             writeln!(res, "\r{line}").unwrap();
         }
-        write_prompt(&mut res, &ps.displayed_prompt, ps.rows as u16).unwrap();
+        write_prompt(&mut res, &ps.displayed_prompt, ps.prompt_row()).unwrap();
 
         draw_for_change(&mut out, &mut ps, &mut 3).unwrap();
 
@@ -512,7 +554,7 @@ mod draw_for_change_tests {
         for line in &ps.screen.formatted_lines[50..59] {
             writeln!(res, "\r{line}").unwrap();
         }
-        write_prompt(&mut res, &ps.displayed_prompt, ps.rows as u16).unwrap();
+        write_prompt(&mut res, &ps.displayed_prompt, ps.prompt_row()).unwrap();
 
         draw_for_change(&mut out, &mut ps, &mut 50).unwrap();
 
@@ -523,6 +565,7 @@ mod draw_for_change_tests {
     fn no_overflow_change() {
         let mut ps = create_pager_state();
         ps.screen.formatted_lines.truncate(5);
+        ps.screen.rows_count = 5;
         let mut out = Vec::with_capacity(100);
         let mut new_upper_mark = 10;
 
@@ -544,7 +587,7 @@ mod draw_for_change_tests {
         for line in &ps.screen.formatted_lines[20..29] {
             writeln!(res, "\r{line}").unwrap();
         }
-        write_prompt(&mut res, &ps.displayed_prompt, ps.rows as u16).unwrap();
+        write_prompt(&mut res, &ps.displayed_prompt, ps.prompt_row()).unwrap();
 
         draw_for_change(&mut out, &mut ps, &mut 20).unwrap();
 
@@ -565,7 +608,7 @@ mod draw_for_change_tests {
         for line in &ps.screen.formatted_lines[50..59] {
             writeln!(res, "\r{line}").unwrap();
         }
-        write_prompt(&mut res, &ps.displayed_prompt, ps.rows as u16).unwrap();
+        write_prompt(&mut res, &ps.displayed_prompt, ps.prompt_row()).unwrap();
 
         draw_for_change(&mut out, &mut ps, &mut 50).unwrap();
 
@@ -574,4 +617,25 @@ mod draw_for_change_tests {
 
         assert_eq!(out, res);
     }
+
+    #[test]
+    fn inline_redraw() {
+        let mut ps = create_pager_state();
+        ps.inline_rows = Some(5);
+        ps.rows = 5;
+        ps.top = 3;
+        let mut out = Vec::with_capacity(100);
+
+        // Scrolling the terminal would also move the text above the pager
+        let mut res = Vec::new();
+        write!(res, "{}{}", MoveTo(0, 3), Clear(ClearType::FromCursorDown)).unwrap();
+        for line in &ps.screen.formatted_lines[2..6] {
+            writeln!(res, "\r{line}").unwrap();
+        }
+        write_prompt(&mut res, &ps.displayed_prompt, 7).unwrap();
+
+        draw_for_change(&mut out, &mut ps, &mut 2).unwrap();
+
+        assert_eq!(out, res);
+    }
 }
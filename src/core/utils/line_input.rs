@@ -0,0 +1,133 @@
+//! Reading a line of plain text at the prompt
+//!
+//! This is used by the prompts that ask for a value rather than a pattern, like the position to
+//! go to, the name of a file to save to or a shell command. Unlike the search prompt, it has no
+//! history, search options or incremental search, hence it does not need the `search` feature.
+
+use std::convert::TryInto;
+use std::io::Write;
+use std::time::Duration;
+
+use crossterm::{
+    cursor,
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    terminal::{Clear, ClearType},
+};
+use parking_lot::Mutex;
+
+use super::term;
+use crate::{backend::EventSource, error::MinusError, PagerState};
+
+/// Text being typed at the prompt
+struct LineInput {
+    prompt_char: char,
+    /// Characters typed so far
+    chars: Vec<char>,
+    /// Index of the character in front of which the next one is inserted
+    cursor: usize,
+    prompt_row: u16,
+}
+
+impl LineInput {
+    /// Column of the terminal where the cursor is shown, the prompt character takes the first one
+    fn cursor_column(&self) -> u16 {
+        self.cursor.saturating_add(1).try_into().unwrap_or(u16::MAX)
+    }
+
+    /// Redraw the prompt and place the cursor
+    fn write(&self, out: &mut impl Write) -> crate::Result {
+        term::move_cursor(out, 0, self.prompt_row, false)?;
+        write!(
+            out,
+            "\r{}{}{}",
+            Clear(ClearType::CurrentLine),
+            self.prompt_char,
+            self.chars.iter().collect::<String>()
+        )?;
+        term::move_cursor(out, self.cursor_column(), self.prompt_row, true)
+    }
+
+    /// Apply a key press to the text
+    ///
+    /// Returns `Some(true)` if the input has been confirmed, `Some(false)` if it has been
+    /// cancelled and `None` if the user is still typing.
+    fn handle_event(
+        &mut self,
+        out: &mut impl Write,
+        ev: &Event,
+    ) -> Result<Option<bool>, MinusError> {
+        let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = ev
+        else {
+            return Ok(None);
+        };
+        match (code, *modifiers) {
+            (KeyCode::Esc, KeyModifiers::NONE) => return Ok(Some(false)),
+            (KeyCode::Enter, KeyModifiers::NONE) => return Ok(Some(true)),
+            (KeyCode::Backspace, KeyModifiers::NONE) if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            (KeyCode::Delete, KeyModifiers::NONE) if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            (KeyCode::Left, KeyModifiers::NONE) => self.cursor = self.cursor.saturating_sub(1),
+            (KeyCode::Right, KeyModifiers::NONE) => {
+                self.cursor = self.cursor.saturating_add(1).min(self.chars.len());
+            }
+            (KeyCode::Home, KeyModifiers::NONE) => self.cursor = 0,
+            (KeyCode::End, KeyModifiers::NONE) => self.cursor = self.chars.len(),
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                self.chars.insert(self.cursor, *c);
+                self.cursor += 1;
+            }
+            _ => return Ok(None),
+        }
+        self.write(out)?;
+        Ok(None)
+    }
+}
+
+/// Fetch a line of plain text at the prompt, which starts with `prompt_char`
+///
+/// Events are read from `events` until the line is either confirmed with `Enter` or cancelled
+/// with `Esc`. Returns `None` if it has been cancelled.
+pub fn fetch_line(
+    out: &mut impl Write,
+    ps: &PagerState,
+    events: &Mutex<dyn EventSource + '_>,
+    prompt_char: char,
+) -> Result<Option<String>, MinusError> {
+    let mut input = LineInput {
+        prompt_char,
+        chars: Vec::new(),
+        cursor: 0,
+        prompt_row: ps.prompt_row(),
+    };
+    input.write(out)?;
+    write!(out, "{}", cursor::Show)?;
+    out.flush()?;
+
+    let mut events = events.lock();
+    let confirmed = loop {
+        if events
+            .poll(Duration::from_millis(100))
+            .map_err(|e| MinusError::HandleEvent(e.into()))?
+        {
+            let ev = events
+                .read()
+                .map_err(|e| MinusError::HandleEvent(e.into()))?;
+            if let Some(confirmed) = input.handle_event(out, &ev)? {
+                break confirmed;
+            }
+        }
+    };
+    drop(events);
+
+    term::move_cursor(out, 0, input.prompt_row, false)?;
+    write!(out, "{}{}", Clear(ClearType::CurrentLine), cursor::Hide)?;
+    out.flush()?;
+
+    Ok(confirmed.then(|| input.chars.into_iter().collect()))
+}
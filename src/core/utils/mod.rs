@@ -1,4 +1,7 @@
 pub mod display;
+pub mod line_input;
+pub mod selection;
+pub mod syntax;
 pub mod term;
 
 /// Return the number of digits in `num`
@@ -36,4 +39,17 @@ impl LinesRowMap {
     pub fn get(&self, ln: usize) -> Option<&usize> {
         self.0.get(ln)
     }
+
+    /// Remove the first `lines` lines which occupy the first `rows` rows
+    pub fn drop_lines(&mut self, lines: usize, rows: usize) {
+        self.0.drain(..lines.min(self.0.len()));
+        for row in &mut self.0 {
+            *row -= rows;
+        }
+    }
+
+    /// Returns the line which occupies the given row
+    pub fn line_of_row(&self, row: usize) -> usize {
+        self.0.partition_point(|&r| r <= row).saturating_sub(1)
+    }
 }
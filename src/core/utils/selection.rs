@@ -0,0 +1,215 @@
+//! Selecting text with the mouse and copying it to the clipboard
+//!
+//! Since minus captures the mouse, the terminal can't select text by itself. Instead, minus keeps
+//! track of the text selected by dragging with the left button and highlights it. When the
+//! button is released, the selected text is copied with the OSC 52 escape sequence, which asks
+//! the terminal to put it on the system clipboard.
+
+use std::ops::Range;
+
+/// Escape sequence that turns on reverse video for the selected text
+const REVERSE: &str = "\x1b[7m";
+/// Escape sequence that turns off reverse video
+const NO_REVERSE: &str = "\x1b[27m";
+
+/// A position in the formatted rows of the text
+///
+/// The column counts the display columns after the gutter, including the columns hidden by
+/// horizontal scrolling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub row: usize,
+    pub column: usize,
+}
+
+/// Text selected with the mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    /// Position where the button was pressed
+    pub anchor: Position,
+    /// Position where the mouse was last dragged to, if it has been dragged
+    pub head: Option<Position>,
+}
+
+impl Selection {
+    pub const fn new(anchor: Position) -> Self {
+        Self { anchor, head: None }
+    }
+
+    /// First and last selected positions, both of which are included in the selection
+    ///
+    /// Returns `None` if the mouse has not been dragged yet.
+    pub fn bounds(&self) -> Option<(Position, Position)> {
+        let head = self.head?;
+        Some((self.anchor.min(head), self.anchor.max(head)))
+    }
+
+    /// Move the selection along with the text once its first `rows` rows are dropped
+    ///
+    /// The part of the selection on the dropped rows is cut off. Returns `false` if none of it
+    /// is left.
+    pub fn drop_rows(&mut self, rows: usize) -> bool {
+        let last = self.head.map_or(self.anchor, |head| head.max(self.anchor));
+        if last.row < rows {
+            return false;
+        }
+        let shift = |position: &mut Position| {
+            *position = if position.row < rows {
+                Position { row: 0, column: 0 }
+            } else {
+                Position {
+                    row: position.row - rows,
+                    column: position.column,
+                }
+            };
+        };
+        shift(&mut self.anchor);
+        if let Some(head) = &mut self.head {
+            shift(head);
+        }
+        true
+    }
+
+    /// Selected columns of `row`
+    pub fn columns(&self, row: usize) -> Option<Range<usize>> {
+        let (start, end) = self.bounds()?;
+        if row < start.row || row > end.row {
+            return None;
+        }
+        let first = if row == start.row { start.column } else { 0 };
+        let last = if row == end.row {
+            end.column + 1
+        } else {
+            usize::MAX
+        };
+        Some(first..last)
+    }
+}
+
+/// Display `columns` of `text` in reverse video
+///
+/// Escape sequences inside the selected columns may turn off the reverse video, so it is turned
+/// on again after each of them.
+pub fn highlight(text: &str, columns: &Range<usize>) -> String {
+    let mut highlighted = String::with_capacity(text.len() + 2 * REVERSE.len());
+    let mut column = 0;
+    let mut reversed = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            highlighted.push(c);
+            if chars.next_if_eq(&'[').is_some() {
+                highlighted.push('[');
+                for c in chars.by_ref() {
+                    highlighted.push(c);
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            if reversed {
+                highlighted.push_str(REVERSE);
+            }
+            continue;
+        }
+        if !reversed && columns.contains(&column) {
+            highlighted.push_str(REVERSE);
+            reversed = true;
+        } else if reversed && column >= columns.end {
+            highlighted.push_str(NO_REVERSE);
+            reversed = false;
+        }
+        highlighted.push(c);
+        column += textwrap::core::display_width(c.encode_utf8(&mut [0; 4]));
+    }
+    if reversed {
+        highlighted.push_str(NO_REVERSE);
+    }
+    highlighted
+}
+
+/// Byte index of the character of `text` at `column`
+///
+/// `text` must not contain escape sequences. Columns past the end give the length of `text`.
+pub fn byte_of_column(text: &str, column: usize) -> usize {
+    let mut width = 0;
+    for (i, c) in text.char_indices() {
+        width += textwrap::core::display_width(c.encode_utf8(&mut [0; 4]));
+        if width > column {
+            return i;
+        }
+    }
+    text.len()
+}
+
+/// OSC 52 escape sequence that copies `text` to the clipboard
+pub fn osc52(text: &[u8]) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text))
+}
+
+/// Encode `bytes` in base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len() * 4 / 3 + 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |group, (i, &b)| group | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(
+                    ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize],
+                ));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, byte_of_column, highlight, Position, Selection};
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64("héllo\n".as_bytes()), "aMOpbGxvCg==");
+    }
+
+    #[test]
+    fn selected_columns() {
+        let mut selection = Selection::new(Position { row: 3, column: 4 });
+        assert_eq!(selection.columns(3), None);
+        selection.head = Some(Position { row: 1, column: 6 });
+        assert_eq!(selection.columns(0), None);
+        assert_eq!(selection.columns(1), Some(6..usize::MAX));
+        assert_eq!(selection.columns(2), Some(0..usize::MAX));
+        assert_eq!(selection.columns(3), Some(0..5));
+        assert_eq!(selection.columns(4), None);
+    }
+
+    #[test]
+    fn highlights_columns() {
+        assert_eq!(highlight("abcdef", &(2..4)), "ab\x1b[7mcd\x1b[27mef");
+        assert_eq!(highlight("abc", &(1..usize::MAX)), "a\x1b[7mbc\x1b[27m");
+        assert_eq!(
+            highlight("a\x1b[31mbc\x1b[0md", &(1..3)),
+            "a\x1b[31m\x1b[7mbc\x1b[0m\x1b[7m\x1b[27md"
+        );
+    }
+
+    #[test]
+    fn columns_to_bytes() {
+        assert_eq!(byte_of_column("héllo", 2), 3);
+        assert_eq!(byte_of_column("a😀b", 2), 1);
+        assert_eq!(byte_of_column("a😀b", 3), 5);
+        assert_eq!(byte_of_column("abc", 10), 3);
+    }
+}
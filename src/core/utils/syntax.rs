@@ -0,0 +1,174 @@
+//! Keeps track of the state of the [`Highlighter`] of a buffer
+use std::{any::Any, borrow::Cow, collections::BTreeMap, io, sync::Arc};
+
+use crate::screen::{storage::TextStore, syntax::Highlighter};
+
+/// Type erased [`Highlighter::State`](crate::screen::syntax::Highlighter::State)
+type State = Box<dyn Any + Send>;
+
+/// Object safe version of [`Highlighter`]
+trait DynHighlighter: Send + Sync {
+    fn initial_state(&self) -> State;
+    fn clone_state(&self, state: &State) -> State;
+    fn highlight_line<'a>(&self, line: &'a str, state: &mut State) -> Cow<'a, str>;
+}
+
+impl<H: Highlighter> DynHighlighter for H {
+    fn initial_state(&self) -> State {
+        Box::<H::State>::default()
+    }
+
+    fn clone_state(&self, state: &State) -> State {
+        Box::new(downcast::<H>(state).clone())
+    }
+
+    fn highlight_line<'a>(&self, line: &'a str, state: &mut State) -> Cow<'a, str> {
+        let state = state
+            .downcast_mut::<H::State>()
+            .expect("State of a different highlighter");
+        Highlighter::highlight_line(self, line, state)
+    }
+}
+
+fn downcast<H: Highlighter>(state: &State) -> &H::State {
+    state
+        .downcast_ref::<H::State>()
+        .expect("State of a different highlighter")
+}
+
+/// Number of lines after which the state of the highlighter is saved
+const CHECKPOINT_INTERVAL: usize = 64;
+
+/// A [`Highlighter`] along with the states it had at some of the lines of the text
+///
+/// Lines have to be highlighted in order, starting from a line given to
+/// [`SyntaxHighlighting::seek`].
+pub struct SyntaxHighlighting {
+    highlighter: Arc<dyn DynHighlighter>,
+    /// State at the start of the first line
+    ///
+    /// This is the initial state unless the oldest lines have been dropped.
+    first: State,
+    /// States at the start of every [`CHECKPOINT_INTERVAL`]th line
+    checkpoints: BTreeMap<usize, State>,
+    /// State at the start of the last line of the text
+    ///
+    /// The last line is highlighted again whenever text is appended to it, or highlighted once
+    /// more to get the state for the next line.
+    last: Option<(usize, State)>,
+    /// Line that will be highlighted next along with its state
+    next: (usize, State),
+}
+
+impl SyntaxHighlighting {
+    pub(crate) fn new(highlighter: impl Highlighter) -> Self {
+        Self::with(Arc::new(highlighter))
+    }
+
+    fn with(highlighter: Arc<dyn DynHighlighter>) -> Self {
+        Self {
+            first: highlighter.initial_state(),
+            checkpoints: BTreeMap::new(),
+            last: None,
+            next: (0, highlighter.initial_state()),
+            highlighter,
+        }
+    }
+
+    /// Get another instance of the same highlighter without any of the saved states
+    #[cfg(feature = "search")]
+    pub(crate) fn fork(&self) -> Self {
+        let mut fork = Self::with(Arc::clone(&self.highlighter));
+        fork.first = self.highlighter.clone_state(&self.first);
+        fork.reset();
+        fork
+    }
+
+    /// Forget the saved states and start again from the first line
+    pub(crate) fn reset(&mut self) {
+        self.checkpoints.clear();
+        self.last = None;
+        self.next = (0, self.highlighter.clone_state(&self.first));
+    }
+
+    /// Forget all of the states, for when the text is replaced
+    pub(crate) fn clear(&mut self) {
+        self.first = self.highlighter.initial_state();
+        self.reset();
+    }
+
+    /// Get the state at the start of `line`
+    ///
+    /// The lines between the closest saved state before `line` and `line` are read from `store`
+    /// and highlighted again.
+    fn state_at(&self, store: &dyn TextStore, line: usize) -> io::Result<State> {
+        let checkpoint = self.checkpoints.range(..=line).next_back();
+        let last = self
+            .last
+            .as_ref()
+            .filter(|(idx, _)| *idx <= line)
+            .map(|(idx, state)| (idx, state));
+        let (start, state) = checkpoint
+            .into_iter()
+            .chain(last)
+            .max_by_key(|(idx, _)| **idx)
+            .map_or((&0, &self.first), |closest| closest);
+        let mut state = self.highlighter.clone_state(state);
+        for text in store.lines_from(*start).take(line - start) {
+            self.highlighter.highlight_line(&text?, &mut state);
+        }
+        Ok(state)
+    }
+
+    /// Continue highlighting from the start of `line`
+    pub(crate) fn seek(&mut self, store: &dyn TextStore, line: usize) -> io::Result<()> {
+        if self.next.0 != line {
+            self.next = (line, self.state_at(store, line)?);
+        }
+        Ok(())
+    }
+
+    /// Highlight the line at `idx`, which must be the line following the previous one
+    ///
+    /// `last` tells whether this is the last line of the text.
+    pub(crate) fn highlight_line<'a>(
+        &mut self,
+        idx: usize,
+        line: &'a str,
+        last: bool,
+    ) -> Cow<'a, str> {
+        debug_assert_eq!(idx, self.next.0, "Lines highlighted out of order");
+        let state = &mut self.next.1;
+        if idx > 0 && matches!(idx % CHECKPOINT_INTERVAL, 0) {
+            self.checkpoints
+                .insert(idx, self.highlighter.clone_state(state));
+        }
+        if last {
+            self.last = Some((idx, self.highlighter.clone_state(state)));
+        }
+        self.next.0 = idx + 1;
+        self.highlighter.highlight_line(line, state)
+    }
+
+    /// Move the saved states to account for the first `lines` lines being dropped from `store`
+    ///
+    /// This must be called before the lines are dropped as the state at the new first line is
+    /// computed from them.
+    pub(crate) fn drop_lines(&mut self, store: &dyn TextStore, lines: usize) -> io::Result<()> {
+        self.first = self.state_at(store, lines)?;
+        let kept = self.checkpoints.split_off(&lines);
+        self.checkpoints = kept
+            .into_iter()
+            .map(|(idx, state)| (idx - lines, state))
+            .collect();
+        if let Some((idx, _)) = &mut self.last {
+            *idx = idx.saturating_sub(lines);
+        }
+        if self.next.0 >= lines {
+            self.next.0 -= lines;
+        } else {
+            self.next = (0, self.highlighter.clone_state(&self.first));
+        }
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use crossterm::{
     terminal::{self, Clear},
     tty::IsTty,
 };
-use std::io;
+use std::io::{self, Write};
 
 /// Setup the terminal
 ///
@@ -43,6 +43,44 @@ pub fn setup(stdout: &io::Stdout) -> std::result::Result<(), SetupError> {
     Ok(())
 }
 
+/// Setup the terminal for paging inline in `rows` rows below the cursor
+///
+/// Unlike [`setup`], it stays on the main screen. It starts a new line if the cursor is not at the
+/// start of one and prints enough newlines to make room for the pager, which scrolls the text
+/// above it up if the cursor is near the bottom of the screen. Then [raw mode] is enabled and the
+/// cursor is hidden.
+///
+/// Returns the row where the pager starts.
+///
+/// # Errors
+/// The function will return with an error if `stdout` is not a terminal or if it cannot execute
+/// commands on the terminal. See [`SetupError`].
+///
+/// [raw mode]: ../../../crossterm/terminal/index.html#raw-mode
+pub fn setup_inline(stdout: &io::Stdout, rows: u16) -> std::result::Result<u16, SetupError> {
+    let mut out = stdout.lock();
+
+    if !out.is_tty() {
+        return Err(SetupError::InvalidTerminal);
+    }
+
+    terminal::enable_raw_mode().map_err(|e| SetupError::RawMode(e.into()))?;
+    let (column, _) = cursor::position().map_err(|e| SetupError::InlineArea(e.into()))?;
+    if column > 0 {
+        write!(out, "\r\n").map_err(|e| SetupError::InlineArea(e.into()))?;
+    }
+    if rows > 1 {
+        write!(out, "{}", "\n".repeat((rows - 1).into()))
+            .and_then(|()| execute!(out, cursor::MoveUp(rows - 1)))
+            .map_err(|e| SetupError::InlineArea(e.into()))?;
+    }
+    let (_, top) = cursor::position().map_err(|e| SetupError::InlineArea(e.into()))?;
+    execute!(out, event::EnableMouseCapture)
+        .map_err(|e| SetupError::EnableMouseCapture(e.into()))?;
+    execute!(out, cursor::Hide).map_err(|e| SetupError::HideCursor(e.into()))?;
+    Ok(top)
+}
+
 /// Cleans up the terminal
 ///
 /// The function will clean up the terminal and set it back to its original state,
@@ -78,6 +116,22 @@ pub fn cleanup(
     }
 }
 
+/// Cleans up the terminal after [`setup_inline`]
+///
+/// It does the same as [`cleanup`] except for switching screens, so the text drawn by the pager
+/// stays on the screen.
+///
+/// ## Errors
+/// The function will return with an error if it fails to do execute commands on the
+/// terminal. See [`CleanupError`]
+pub fn cleanup_inline(mut out: impl io::Write) -> std::result::Result<(), CleanupError> {
+    execute!(out, cursor::Show).map_err(|e| CleanupError::ShowCursor(e.into()))?;
+    terminal::disable_raw_mode().map_err(|e| CleanupError::DisableRawMode(e.into()))?;
+    execute!(out, event::DisableMouseCapture)
+        .map_err(|e| CleanupError::DisableMouseCapture(e.into()))?;
+    Ok(())
+}
+
 /// Moves the terminal cursor to given x, y coordinates
 ///
 /// The `flush` parameter will immediately flush the buffer if it is set to `true`
@@ -101,3 +155,20 @@ pub fn clear_entire_screen(out: &mut impl io::Write, flush: bool) -> crate::Resu
     }
     Ok(())
 }
+
+/// Clears the screen from the start of `row` downwards, leaving the rows above it untouched
+///
+/// The entire screen is cleared if `row` is the first row. The cursor is left at the start of
+/// `row`.
+pub fn clear_from_row(out: &mut impl io::Write, row: u16, flush: bool) -> crate::Result {
+    move_cursor(out, 0, row, false)?;
+    if row == 0 {
+        clear_entire_screen(out, flush)
+    } else {
+        queue!(out, Clear(terminal::ClearType::FromCursorDown))?;
+        if flush {
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -5,11 +5,13 @@ use crate::Pager;
 /// Starts a asynchronously running pager
 ///
 /// This means that data and configuration can be fed into the pager while it is running.
+/// Once all the data has been fed, [`Pager::end_of_input`] lets the pager quit right away if
+/// all of it fits on the screen.
 ///
 /// See [examples](../index.html#examples) on how to use this function.
 ///
 /// # Panics
-/// This function will panic if another instance of minus is already running.
+/// This function will panic if `pager` is already running.
 ///
 /// # Errors
 /// The function will return with an error if it encounters a error during paging.
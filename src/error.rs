@@ -52,6 +52,9 @@ pub enum SetupError {
 
     #[error("Couldn't determine the terminal size")]
     TerminalSize(TermError),
+
+    #[error("Failed to make room for the pager below the cursor")]
+    InlineArea(TermError),
 }
 
 /// Errors that can occur during clean up
@@ -69,6 +72,9 @@ pub enum CleanupError {
 
     #[error("Failed to switch back to main screen")]
     LeaveAlternateScreen(TermError),
+
+    #[error("Failed to clear the prompt")]
+    ClearPrompt(TermError),
 }
 
 /// Errors that can happen while running
@@ -94,7 +100,13 @@ pub enum MinusError {
     FmtWriteError(#[from] std::fmt::Error),
 
     #[error("Failed to send data to the receiver")]
-    Communication(#[from] crossbeam_channel::SendError<Command>),
+    Communication(#[from] flume::SendError<Command>),
+
+    #[error("Failed to read the input")]
+    ReadInput(std::io::Error),
+
+    #[error("Failed to access the stored text")]
+    Storage(std::io::Error),
 
     #[error("Failed to convert between some primitives")]
     Conversion,
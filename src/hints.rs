@@ -0,0 +1,331 @@
+//! Opening the links and file locations displayed on the screen
+//!
+//! Output like build logs is often full of URLs and locations like `src/main.rs:12:5`. After the
+//! application sets a [`HintAction`] with [`Pager::set_hint_action`](crate::Pager::set_hint_action),
+//! users can press `o` to label each of them on the screen, like the hints modes of tmux and
+//! kitty. Typing a label opens its [`Hint`] with the action, while any other key cancels it.
+//!
+//! The action is either a function of the application or a command built from a template like
+//! `$EDITOR +{line} {path}`. The command takes over the terminal while it runs, after which the
+//! pager is displayed again.
+//!
+//! # Example
+//! ```
+//! use minus::{
+//!     hints::{Hint, HintAction},
+//!     Pager,
+//! };
+//!
+//! let pager = Pager::new();
+//! pager.push_str("error: src/main.rs:12:5\nsee https://docs.rs/minus\n")?;
+//! pager.set_hint_action(Some(HintAction::callback(|hint| match hint {
+//!     Hint::Url(url) => println!("opening {url}"),
+//!     Hint::Location { path, line, .. } => println!("opening {path} at line {line}"),
+//! })))?;
+//! # Ok::<(), minus::MinusError>(())
+//! ```
+use std::{ops::Range, sync::Arc};
+
+/// A link or file location found on the screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hint {
+    /// A URL, like `https://docs.rs`
+    Url(String),
+    /// A file location, like `src/main.rs:12` or `src/main.rs:12:5`
+    Location {
+        path: String,
+        line: usize,
+        column: Option<usize>,
+    },
+}
+
+/// What to do with the [`Hint`] selected by the user
+#[derive(Clone)]
+pub enum HintAction {
+    /// Call the function with the selected hint
+    ///
+    /// The function is called while the pager is displayed, so it must not use the terminal or
+    /// wait for the [`Pager`](crate::Pager).
+    Callback(Arc<dyn Fn(&Hint) + Send + Sync>),
+    /// Run the command built from the template for the kind of the selected hint
+    ///
+    /// The templates are split into words at whitespace. A word like `$EDITOR` is replaced with
+    /// the words of that environment variable, and `{url}`, `{path}`, `{line}` and `{column}`
+    /// within words are replaced with the parts of the hint. A missing column is given as `1`.
+    /// Hints without a template are not labelled.
+    ///
+    /// The pager is suspended while the command runs.
+    Command {
+        /// Template for URLs, like `xdg-open {url}`
+        url: Option<String>,
+        /// Template for file locations, like `$EDITOR +{line} {path}`
+        location: Option<String>,
+    },
+}
+
+impl HintAction {
+    /// Create a [`HintAction::Callback`] calling `f`
+    pub fn callback<F>(f: F) -> Self
+    where
+        F: Fn(&Hint) + Send + Sync + 'static,
+    {
+        Self::Callback(Arc::new(f))
+    }
+
+    /// Whether this action can open `hint`
+    pub(crate) const fn opens(&self, hint: &Hint) -> bool {
+        match self {
+            Self::Callback(_) => true,
+            Self::Command { url, location } => match hint {
+                Hint::Url(_) => url.is_some(),
+                Hint::Location { .. } => location.is_some(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Debug for HintAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Callback(_) => write!(f, "Callback"),
+            Self::Command { url, location } => f
+                .debug_struct("Command")
+                .field("url", url)
+                .field("location", location)
+                .finish(),
+        }
+    }
+}
+
+/// A hint along with its label and where the label is displayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Label {
+    pub(crate) text: String,
+    pub(crate) hint: Hint,
+    pub(crate) column: usize,
+    pub(crate) row: usize,
+}
+
+/// Hints labelled on the screen and the part of a label typed so far
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Hints {
+    pub(crate) labels: Vec<Label>,
+    pub(crate) typed: String,
+}
+
+impl Hints {
+    /// Characters used for the labels, the ones easiest to type come first
+    const ALPHABET: &'static str = "asdfghjklqwertyuiopzxcvbnm";
+
+    /// Label the `hints`, each of which is given with the column and row where it is displayed
+    pub(crate) fn new(hints: Vec<(Hint, usize, usize)>) -> Self {
+        let alphabet: Vec<char> = Self::ALPHABET.chars().collect();
+        // All labels have the same length so that none of them is the start of another
+        let two_chars = hints.len() > alphabet.len();
+        let labels = hints
+            .into_iter()
+            .take(alphabet.len() * alphabet.len())
+            .enumerate()
+            .map(|(i, (hint, column, row))| {
+                let label = if two_chars {
+                    [alphabet[i / alphabet.len()], alphabet[i % alphabet.len()]]
+                        .iter()
+                        .collect()
+                } else {
+                    alphabet[i].to_string()
+                };
+                Label {
+                    text: label,
+                    hint,
+                    column,
+                    row,
+                }
+            })
+            .collect();
+        Self {
+            labels,
+            typed: String::new(),
+        }
+    }
+
+    /// Labels that start with the part typed so far
+    pub(crate) fn matching(&self) -> impl Iterator<Item = &Label> {
+        self.labels
+            .iter()
+            .filter(move |l| l.text.starts_with(&self.typed))
+    }
+}
+
+/// Characters that separate a link or location from the surrounding text
+const fn is_delimiter(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(
+            c,
+            '"' | '\'' | '`' | '<' | '>' | '(' | ')' | '[' | ']' | '|'
+        )
+}
+
+/// Find the links and file locations in `text`, which must not contain escape sequences
+///
+/// Returns each hint along with its range of bytes in `text`.
+pub(crate) fn find_hints(text: &str) -> Vec<(Range<usize>, Hint)> {
+    let mut hints = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match start {
+            None if !is_delimiter(c) => start = Some(i),
+            Some(s) if is_delimiter(c) => {
+                start = None;
+                let word = text[s..i].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+                if let Some((offset, hint)) = parse_word(word) {
+                    hints.push((s + offset..s + word.len(), hint));
+                }
+            }
+            _ => {}
+        }
+    }
+    hints
+}
+
+/// Parse a URL or a file location out of `word`
+///
+/// Returns the hint along with the offset at which it starts in `word`.
+fn parse_word(word: &str) -> Option<(usize, Hint)> {
+    if let Some(separator) = word.find("://") {
+        // The scheme is made up of the letters before the separator
+        let scheme = word[..separator]
+            .rfind(|c: char| !c.is_ascii_alphabetic())
+            .map_or(0, |i| i + 1);
+        if scheme == separator || separator + 3 == word.len() {
+            return None;
+        }
+        return Some((scheme, Hint::Url(word[scheme..].to_string())));
+    }
+    let mut parts = word.splitn(3, ':');
+    let path = parts.next()?;
+    let line = parts.next()?.parse().ok()?;
+    let column = match parts.next() {
+        Some(column) => Some(column.parse().ok()?),
+        None => None,
+    };
+    // Make sure that it is a path and not something like a time
+    if path.is_empty() || !path.contains(['/', '.']) {
+        return None;
+    }
+    Some((
+        0,
+        Hint::Location {
+            path: path.to_string(),
+            line,
+            column,
+        },
+    ))
+}
+
+/// Build the arguments of the command from `template` for `hint`
+///
+/// # Errors
+/// Returns an error message if an environment variable used by the template is not set
+pub(crate) fn command_line(template: &str, hint: &Hint) -> Result<Vec<String>, String> {
+    let (url, path, line, column) = match hint {
+        Hint::Url(url) => (url.as_str(), "", String::new(), String::new()),
+        Hint::Location { path, line, column } => (
+            "",
+            path.as_str(),
+            line.to_string(),
+            column.unwrap_or(1).to_string(),
+        ),
+    };
+    let mut args = Vec::new();
+    for word in template.split_whitespace() {
+        match word.strip_prefix('$') {
+            Some(name)
+                if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                let value = std::env::var(name).map_err(|_| format!("${name} is not set"))?;
+                args.extend(value.split_whitespace().map(String::from));
+            }
+            _ => args.push(
+                [
+                    ("url", url),
+                    ("path", path),
+                    ("line", &line),
+                    ("column", &column),
+                ]
+                .iter()
+                .fold(word.to_string(), |arg, (name, value)| {
+                    arg.replace(&format!("{{{name}}}"), value)
+                }),
+            ),
+        }
+    }
+    if args.is_empty() {
+        return Err("the command is empty".to_string());
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_hints, Hint, Hints};
+
+    fn location(path: &str, line: usize, column: Option<usize>) -> Hint {
+        Hint::Location {
+            path: path.to_string(),
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    fn finds_urls_and_locations() {
+        let text = "--> src/main.rs:12:5, see (https://docs.rs/minus). at 12:30 in lib.rs:7:";
+        assert_eq!(
+            find_hints(text),
+            vec![
+                (4..20, location("src/main.rs", 12, Some(5))),
+                (27..48, Hint::Url("https://docs.rs/minus".to_string())),
+                (63..71, location("lib.rs", 7, None)),
+            ]
+        );
+        assert_eq!(
+            find_hints("url=http://a.b"),
+            vec![(4..14, Hint::Url("http://a.b".to_string()))]
+        );
+        assert!(find_hints("http:// 12:30:00 foo:bar").is_empty());
+    }
+
+    #[test]
+    fn labels() {
+        let hint = |i| (location("a.rs", i, None), 0, 0);
+        let hints = Hints::new((0..3).map(hint).collect());
+        let labels: Vec<_> = hints.labels.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(labels, ["a", "s", "d"]);
+
+        let mut hints = Hints::new((0..30).map(hint).collect());
+        assert_eq!(hints.labels[0].text, "aa");
+        assert_eq!(hints.labels[27].text, "ss");
+        hints.typed.push('s');
+        assert_eq!(hints.matching().count(), 4);
+    }
+
+    #[test]
+    fn command_lines() {
+        use super::command_line;
+
+        let hint = location("src/main.rs", 12, None);
+        assert_eq!(
+            command_line("vi +{line} {path}:{column}", &hint).unwrap(),
+            ["vi", "+12", "src/main.rs:1"]
+        );
+        std::env::set_var("MINUS_TEST_OPENER", "open -a");
+        assert_eq!(
+            command_line("$MINUS_TEST_OPENER {url}", &Hint::Url("x://y".to_string())).unwrap(),
+            ["open", "-a", "x://y"]
+        );
+        assert_eq!(
+            command_line("$MINUS_TEST_UNSET {path}", &hint).unwrap_err(),
+            "$MINUS_TEST_UNSET is not set"
+        );
+    }
+}
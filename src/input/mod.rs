@@ -248,6 +248,27 @@ pub enum InputEvent {
     /// Move to the previous nth match in the given direction
     #[cfg(feature = "search")]
     MoveToPrevMatch(usize),
+    /// `&`, Display only the lines matching a pattern
+    #[cfg(feature = "search")]
+    Filter,
+    /// `+`, Highlight the matches of a pattern in a color of its own
+    #[cfg(feature = "search")]
+    AddHighlight,
+    /// `-`, Remove the highlight of a pattern
+    #[cfg(feature = "search")]
+    RemoveHighlight,
+    /// `:`, Go to a line number, a percentage of the text or a byte offset
+    Goto,
+    /// `s`, Save the text to a file
+    ///
+    /// Ignored if saving is turned off with
+    /// [Pager::allow_saving](crate::pager::Pager::allow_saving).
+    Save,
+    /// `!`, Run a shell command while the pager is suspended
+    ///
+    /// Ignored unless running other programs is turned on with
+    /// [Pager::allow_external_commands](crate::pager::Pager::allow_external_commands).
+    Shell,
     /// Control follow mode.
     ///
     /// When set to true, minus ensures that the user's screen always follows the end part of the
@@ -256,6 +277,65 @@ pub enum InputEvent {
     /// This is similar to [Pager::follow_output](crate::pager::Pager::follow_output) except that
     /// this is used to control it from the user's side.
     FollowOutput(bool),
+    /// Display the buffer at the given index
+    ///
+    /// Sent by `]` and `[` to move to the next and previous buffer respectively. See
+    /// [Pager::add_buffer](crate::pager::Pager::add_buffer) for more info on buffers.
+    SwitchBuffer(usize),
+    /// Split the view into two panes or go back to a single one
+    ///
+    /// Sent by `W`. This is similar to [Pager::split_view](crate::pager::Pager::split_view)
+    /// except that this is used to control it from the user's side.
+    SplitView(bool),
+    /// `Ctrl+W`, move the focus to the other pane of a split view
+    SwitchPane,
+    /// `m` or `'`, the next character typed names the mark to set or jump to
+    ReadMark(MarkAction),
+    /// Set the mark with the given name on the line at the top of the screen
+    SetMark(char),
+    /// Jump to the line of the mark with the given name
+    JumpToMark(char),
+    /// Copy the lines from the mark with the given name up to the line at the top of the screen
+    /// to the clipboard
+    CopyToMark(char),
+    /// Pressing the left mouse button, starts selecting text at the given column and row of the
+    /// screen
+    StartSelection(usize, usize),
+    /// Dragging with the left mouse button, extends the selection up to the given column and row
+    /// of the screen
+    ExtendSelection(usize, usize),
+    /// Releasing the left mouse button, copies the selected text to the clipboard
+    CopySelection,
+    /// Label the links and file locations on the screen or remove the labels
+    ///
+    /// Sent by `o` to label them, see the [hints](crate::hints) module for more info. While the
+    /// labels are displayed, any key other than a character removes them.
+    ShowHints(bool),
+    /// A character of the label of a hint has been typed
+    SelectHint(char),
+    /// `v`, Open the text in the editor of the user while the pager is suspended
+    ///
+    /// Ignored unless running other programs is turned on with
+    /// [Pager::allow_external_commands](crate::pager::Pager::allow_external_commands).
+    Edit,
+    /// `Ctrl+Z`, Stop the process like the shell does for job control
+    ///
+    /// The pager is suspended until the process is continued, for example with `fg`. This only
+    /// has an effect on Unix.
+    Suspend,
+}
+
+/// What to do with the mark named by the next character typed
+///
+/// See [`InputEvent::ReadMark`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MarkAction {
+    /// Set the mark, sent by `m`
+    Set,
+    /// Jump to the mark, sent by `'`
+    Jump,
+    /// Copy the lines up to the mark, sent by `y`
+    Copy,
 }
 
 /// Classifies the input and returns the appropriate [`InputEvent`]
@@ -297,11 +377,11 @@ where
         }
     });
     map.add_key_events(&["u", "c-u"], |_, ps| {
-        let half_screen = ps.rows / 2;
+        let half_screen = ps.pane_rows().saturating_add(1) / 2;
         InputEvent::UpdateUpperMark(ps.upper_mark.saturating_sub(half_screen))
     });
     map.add_key_events(&["d", "c-d"], |_, ps| {
-        let half_screen = ps.rows / 2;
+        let half_screen = ps.pane_rows().saturating_add(1) / 2;
         InputEvent::UpdateUpperMark(ps.upper_mark.saturating_add(half_screen))
     });
     map.add_key_events(&["g"], |_, _| InputEvent::UpdateUpperMark(0));
@@ -326,19 +406,56 @@ where
             .unwrap_or(&(usize::MAX - 1));
         InputEvent::UpdateUpperMark(row_to_go)
     });
+    map.add_key_events(&["%"], |_, ps| {
+        let percent = ps.prefix_num.parse::<usize>().unwrap_or(0);
+        InputEvent::UpdateUpperMark(ps.row_of_line(ps.line_at_percent(percent)))
+    });
     map.add_key_events(&["pageup"], |_, ps| {
-        InputEvent::UpdateUpperMark(ps.upper_mark.saturating_sub(ps.rows - 1))
+        InputEvent::UpdateUpperMark(ps.upper_mark.saturating_sub(ps.pane_rows()))
     });
     map.add_key_events(&["pagedown", "space"], |_, ps| {
-        InputEvent::UpdateUpperMark(ps.upper_mark.saturating_add(ps.rows - 1))
+        InputEvent::UpdateUpperMark(ps.upper_mark.saturating_add(ps.pane_rows()))
+    });
+    map.add_key_events(&["W", "s-w"], |_, ps| {
+        InputEvent::SplitView(ps.split.is_none())
+    });
+    map.add_key_events(&["c-w"], |_, _| InputEvent::SwitchPane);
+    map.add_key_events(&["m"], |_, _| InputEvent::ReadMark(MarkAction::Set));
+    map.add_key_events(&["'"], |_, _| InputEvent::ReadMark(MarkAction::Jump));
+    map.add_key_events(&["y"], |_, _| InputEvent::ReadMark(MarkAction::Copy));
+    map.add_key_events(&["o"], |_, ps| {
+        if ps.hint_action.is_some() {
+            InputEvent::ShowHints(true)
+        } else {
+            InputEvent::Ignore
+        }
+    });
+    map.add_key_events(&["v"], |_, _| InputEvent::Edit);
+    #[cfg(unix)]
+    map.add_key_events(&["c-z"], |_, _| InputEvent::Suspend);
+    map.add_key_events(&["]"], |_, ps| {
+        let position = ps.prefix_num.parse::<usize>().unwrap_or(1);
+        InputEvent::SwitchBuffer((ps.buffer_index() + position) % ps.buffer_count())
+    });
+    map.add_key_events(&["["], |_, ps| {
+        let position = ps.prefix_num.parse::<usize>().unwrap_or(1) % ps.buffer_count();
+        InputEvent::SwitchBuffer(
+            (ps.buffer_index() + ps.buffer_count() - position) % ps.buffer_count(),
+        )
     });
     map.add_key_events(&["c-l"], |_, ps| {
         InputEvent::UpdateLineNumber(!ps.line_numbers)
     });
+    map.add_key_events(&[":"], |_, _| InputEvent::Goto);
+    map.add_key_events(&["s"], |_, _| InputEvent::Save);
+    map.add_key_events(&["!"], |_, _| InputEvent::Shell);
     #[cfg(feature = "search")]
     {
         map.add_key_events(&["/"], |_, _| InputEvent::Search(SearchMode::Forward));
         map.add_key_events(&["?"], |_, _| InputEvent::Search(SearchMode::Reverse));
+        map.add_key_events(&["&"], |_, _| InputEvent::Filter);
+        map.add_key_events(&["+"], |_, _| InputEvent::AddHighlight);
+        map.add_key_events(&["dash"], |_, _| InputEvent::RemoveHighlight);
         map.add_key_events(&["n"], |_, ps| {
             let position = ps.prefix_num.parse::<usize>().unwrap_or(1);
 
@@ -369,6 +486,19 @@ where
     map.add_mouse_events(&["scroll:down"], |_, ps| {
         InputEvent::UpdateUpperMark(ps.upper_mark.saturating_add(5))
     });
+    map.add_mouse_events(&["left:down"], |ev, _| {
+        let Event::Mouse(MouseEvent { column, row, .. }) = ev else {
+            unreachable!();
+        };
+        InputEvent::StartSelection(column.into(), row.into())
+    });
+    map.add_mouse_events(&["left:drag"], |ev, _| {
+        let Event::Mouse(MouseEvent { column, row, .. }) = ev else {
+            unreachable!();
+        };
+        InputEvent::ExtendSelection(column.into(), row.into())
+    });
+    map.add_mouse_events(&["left:up"], |_, _| InputEvent::CopySelection);
 
     map.add_key_events(&["c-s-h", "c-h"], |_, ps| {
         InputEvent::HorizontalScroll(!ps.screen.line_wrapping)
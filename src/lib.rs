@@ -89,6 +89,39 @@
 //! }
 //! ```
 //!
+//! ## async
+//!
+//! With the `async` feature, the pager can run on an async task without dedicating a thread to
+//! it. Use [`Pager::with_capacity`] along with the `async` variants of the data functions to make
+//! the application wait when the pager can't keep up.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "async")]
+//! # {
+//! use minus::{MinusError, Pager};
+//! use std::time::Duration;
+//! use tokio::{join, time::sleep};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), MinusError> {
+//!     // Initialize the pager
+//!     let pager = Pager::with_capacity(10);
+//!     // Asynchronously send data to the pager
+//!     let increment = async {
+//!         for i in 0..=100_u32 {
+//!             pager.push_str_async(format!("{i}\n")).await?;
+//!             sleep(Duration::from_millis(100)).await;
+//!         }
+//!         Result::<_, MinusError>::Ok(())
+//!     };
+//!     let (res1, res2) = join!(pager.run_async(), increment);
+//!     res1?;
+//!     res2?;
+//!     Ok(())
+//! }
+//! # }
+//! ```
+//!
 //! ## Static output
 //! ```rust,no_run
 //! use std::fmt::Write;
@@ -131,15 +164,36 @@
 //! | Ctrl+D/d          | Scroll down by half a screen                                                 |
 //! | g                 | Go to the very top of the output                                             |
 //! | \[n\] G           | Go to the very bottom of the output. If n is present, goes to that line      |
+//! | \[n\] %           | Go to n percent of the way through the output                                |
 //! | Mouse scroll Up   | Scroll up by 5 lines                                                         |
 //! | Mouse scroll Down | Scroll down by 5 lines                                                       |
+//! | Mouse left drag   | Select text and copy it to the clipboard when the button is released         |
 //! | Ctrl+L            | Toggle line numbers if not forced enabled/disabled                           |
 //! | Ctrl+f            | Toggle [follow-mode]                                                         |
+//! | \[n\] \]          | Go to the nth next buffer. If n is omitted, go to the next buffer            |
+//! | \[n\] \[          | Go to the nth previous buffer. If n is omitted, go to the previous buffer    |
+//! | W                 | Split the view into two panes or go back to a single one                     |
+//! | Ctrl+W            | Move the focus to the other pane of a split view                             |
+//! | m\<letter\>       | Set a mark named by the letter on the line at the top of the screen          |
+//! | '\<letter\>       | Go back to the line of a mark                                                |
+//! | y\<letter\>       | Copy the lines from a mark up to the line at the top of the screen           |
+//! | o                 | Label the links and file locations on the screen to open one, see [`hints`]  |
+//! | v                 | Open the text in the editor set in `$VISUAL` or `$EDITOR`                    |
+//! | Ctrl+Z            | Suspend the pager and the application on Unix, resume them with `fg`         |
 //! | /                 | Start forward search                                                         |
 //! | ?                 | Start backward search                                                        |
 //! | Esc               | Cancel search input                                                          |
 //! | n                 | Go to the next search match                                                  |
 //! | p                 | Go to the next previous match                                                |
+//! | &                 | Display only the lines matching a pattern, `!` before the pattern inverts it |
+//! | +                 | Highlight the matches of a pattern in a color of its own                     |
+//! | -                 | Remove the highlight of a pattern, or all highlights if it is empty          |
+//! | :                 | Go to a line number `N`, a percentage `N%` or a byte offset `Nb`             |
+//! | s                 | Save the text to a file, see the [`save`] module                             |
+//! | !                 | Run a shell command, or start a shell if the command is empty                |
+//!
+//! `v` and `!` run other programs, so they do nothing unless the application turns them on with
+//! [Pager::allow_external_commands].
 //!
 //! End-applications are free to change these bindings to better suit their needs. See docs for
 //! [Pager::set_input_classifier] function and [input] module.
@@ -158,6 +212,10 @@
 //! | Ctrl+Arrow right  | Move cursor towards right word by word              |
 //! | Home              | Move cursor at the beginning pf search query        |
 //! | End               | Move cursor at the end pf search query              |
+//! | Arrow Up          | Recall the previous query from the search history   |
+//! | Arrow Down        | Recall the next query from the search history       |
+//! | Ctrl+R            | Toggle matching the query as plain text             |
+//! | Alt+C             | Switch between exact, smart and ignored case        |
 //!
 //! Currently these cannot be changed by applications but this may be supported in the future.
 //!
@@ -167,13 +225,17 @@
 //! [follow-mode]: struct.Pager.html#method.follow_output
 //! [paging]: https://en.wikipedia.org/wiki/Terminal_pager
 //! [README]: https://github.com/arijit79/minus#motivation
+pub mod backend;
 #[cfg(feature = "dynamic_output")]
 mod dynamic_pager;
 pub mod error;
+pub mod hints;
 pub mod input;
 #[path = "core/mod.rs"]
 mod minus_core;
 mod pager;
+pub mod prompt;
+pub mod save;
 pub mod screen;
 #[cfg(feature = "search")]
 #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
@@ -181,6 +243,12 @@ pub mod search;
 pub mod state;
 #[cfg(feature = "static_output")]
 mod static_pager;
+#[cfg(any(feature = "dynamic_output", feature = "static_output"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "dynamic_output", feature = "static_output")))
+)]
+pub mod testing;
 
 #[cfg(feature = "dynamic_output")]
 pub use dynamic_pager::dynamic_paging;
@@ -228,6 +296,9 @@ pub enum ExitStrategy {
 ///
 /// This implements [`Not`](std::ops::Not) to allow turning on/off line numbers
 /// when they where not locked in by the binary displaying the text.
+///
+/// The look of the line numbers can be changed with
+/// [`Pager::set_gutter`](crate::Pager::set_gutter).
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum LineNumbers {
     /// Enable line numbers permanently, cannot be turned off by user.
@@ -243,8 +314,6 @@ pub enum LineNumbers {
 }
 
 impl LineNumbers {
-    const EXTRA_PADDING: usize = 5;
-
     /// Returns `true` if `self` can be inverted (i.e, `!self != self`), see
     /// the documentation for the variants to know if they are invertible or
     /// not.
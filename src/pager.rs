@@ -1,11 +1,38 @@
 //! Proivdes the [Pager] type
 
-use crate::{error::MinusError, input, minus_core::commands::Command, ExitStrategy, LineNumbers};
-use crossbeam_channel::{Receiver, Sender};
-use std::fmt;
+#[cfg(any(feature = "dynamic_output", feature = "static_output"))]
+use crate::{
+    backend::{EventSource, Terminal},
+    minus_core::init,
+    RunMode,
+};
+use crate::{
+    error::MinusError,
+    hints::HintAction,
+    input,
+    minus_core::{commands::Command, utils::syntax::SyntaxHighlighting},
+    prompt::PromptTheme,
+    save::SaveOptions,
+    screen::{gutter::Gutter, storage::TextStore, syntax::Highlighter},
+    ExitStrategy, LineNumbers,
+};
+use flume::{Receiver, Sender};
+use parking_lot::Mutex;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, Read},
+    path::PathBuf,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
 
 #[cfg(feature = "search")]
-use crate::search::SearchOpts;
+use {
+    crate::search::{CaseSensitivity, Filter, Highlight, SearchOpts},
+    crossterm::style::ContentStyle,
+    regex::Regex,
+};
 
 /// A communication bridge between the main application and the pager.
 ///
@@ -38,6 +65,10 @@ use crate::search::SearchOpts;
 pub struct Pager {
     pub(crate) tx: Sender<Command>,
     pub(crate) rx: Receiver<Command>,
+    /// Describes whether this pager is running and in which mode
+    pub(crate) running: Arc<Mutex<crate::RunMode>>,
+    /// Zero based line numbers of the marks, shared with the running pager
+    pub(crate) marks: Arc<Mutex<BTreeMap<char, usize>>>,
 }
 
 impl Pager {
@@ -49,8 +80,39 @@ impl Pager {
     /// ```
     #[must_use]
     pub fn new() -> Self {
-        let (tx, rx) = crossbeam_channel::unbounded();
-        Self { tx, rx }
+        let (tx, rx) = flume::unbounded();
+        Self {
+            tx,
+            rx,
+            running: Arc::new(Mutex::new(crate::RunMode::Uninitialized)),
+            marks: Arc::default(),
+        }
+    }
+
+    /// Initialize a new pager that holds at most `capacity` pending commands
+    ///
+    /// Each call to a function that sends data or configuration to the pager queues a command
+    /// which the pager applies as soon as it can. With a [`Pager::new`], this queue can grow
+    /// without limits if the application produces data faster than the pager can consume it.
+    /// Here, once `capacity` commands are pending, functions like [`Pager::push_str`] block
+    /// until the pager catches up, while their `async` variants wait without blocking.
+    ///
+    /// Note that the pager only starts consuming commands once it is running. Hence sending more
+    /// than `capacity` commands before starting it on the same thread blocks forever.
+    ///
+    /// # Example
+    /// ```
+    /// let pager = minus::Pager::with_capacity(100);
+    /// ```
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (tx, rx) = flume::bounded(capacity);
+        Self {
+            tx,
+            rx,
+            running: Arc::new(Mutex::new(crate::RunMode::Uninitialized)),
+            marks: Arc::default(),
+        }
     }
 
     /// Set the output text to this `t`
@@ -59,6 +121,9 @@ impl Pager {
     /// If you want to append text, use the [`Pager::push_str`] function or the
     /// [`write!`]/[`writeln!`] macros
     ///
+    /// This sets the text of the buffer that is being displayed. See [`Pager::add_buffer`] for
+    /// setting the text of other buffers.
+    ///
     /// # Errors
     /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
     /// could not be sent to the receiver
@@ -97,6 +162,52 @@ impl Pager {
         Ok(self.tx.send(Command::AppendData(s.into()))?)
     }
 
+    /// Append everything read from `reader` to the pager's output
+    ///
+    /// The data is read on a background thread in chunks and appended as it arrives, so the
+    /// pager can be started and used while the input is still being read. Invalid UTF-8 is
+    /// replaced with `U+FFFD`. A `[loading]` indicator is shown at the prompt until the end of the
+    /// input is reached, or with several readers, until all of them are done. If reading fails,
+    /// the error is shown as a message at the prompt.
+    ///
+    /// If the pager is created with [`Pager::with_capacity`], reading is paused whenever the
+    /// pager falls behind.
+    ///
+    /// The returned handle can be joined to wait until all of the input has been sent to the
+    /// pager.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the receiver.
+    ///
+    /// The thread returns a [`Err(MinusError::ReadInput)`](MinusError::ReadInput) if reading
+    /// from `reader` fails.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::fs::File;
+    ///
+    /// let pager = minus::Pager::new();
+    /// pager.push_reader(File::open("Cargo.toml").unwrap()).unwrap();
+    /// # #[cfg(feature = "dynamic_output")]
+    /// minus::dynamic_paging(pager).unwrap();
+    /// ```
+    pub fn push_reader(
+        &self,
+        reader: impl Read + Send + 'static,
+    ) -> Result<JoinHandle<Result<(), MinusError>>, MinusError> {
+        self.tx.send(Command::ReaderStarted)?;
+        let tx = self.tx.clone();
+        Ok(thread::spawn(move || {
+            let res = read_into(reader, &tx);
+            if let Err(MinusError::ReadInput(ref e)) = res {
+                tx.send(Command::SendMessage(format!("Failed to read input: {e}")))?;
+            }
+            tx.send(Command::ReaderFinished)?;
+            res
+        }))
+    }
+
     /// Set line number configuration for the pager
     ///
     /// See [`LineNumbers`] for available options
@@ -116,6 +227,34 @@ impl Pager {
         Ok(self.tx.send(Command::SetLineNumbers(l))?)
     }
 
+    /// Set the layout and styles of the gutter displayed before each line
+    ///
+    /// This changes how the line numbers look when they are turned on with
+    /// [`Pager::set_line_numbers`] and can add an extra column to the gutter. Each buffer has its
+    /// own gutter, this sets the one of the current buffer. See the
+    /// [`gutter`](crate::screen::gutter) module for more info.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::{screen::gutter::Gutter, LineNumbers, Pager};
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_line_numbers(LineNumbers::Enabled).unwrap();
+    /// pager.set_gutter(Gutter {
+    ///     separator: " | ".to_string(),
+    ///     relative: true,
+    ///     ..Gutter::default()
+    /// }).unwrap();
+    /// ```
+    pub fn set_gutter(&self, gutter: Gutter) -> crate::Result {
+        self.tx.send(Command::SetGutter(gutter))?;
+        Ok(())
+    }
+
     /// Set the text displayed at the bottom prompt
     ///
     /// # Panics
@@ -140,6 +279,67 @@ impl Pager {
         Ok(self.tx.send(Command::SetPrompt(text))?)
     }
 
+    /// Set the layout of the prompt
+    ///
+    /// `format` contains placeholders like `%l` for the line at the top of the screen, that are
+    /// replaced with their current values. See the [`prompt`](crate::prompt) module for all of
+    /// them. The indicators that are normally displayed on the right, like the search index and
+    /// `[F]`, are only displayed where their placeholders are placed. An empty format brings back
+    /// the default layout.
+    ///
+    /// # Panics
+    /// This function panics if the given format contains newline characters.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_prompt_format("%t ?b[%b] .line %l of %L (%p\\%)").unwrap();
+    /// ```
+    pub fn set_prompt_format(&self, format: impl Into<String>) -> crate::Result {
+        let format: String = format.into();
+        assert!(
+            !format.contains('\n'),
+            "Prompt format cannot contain newlines"
+        );
+        let format = if format.is_empty() {
+            None
+        } else {
+            Some(format)
+        };
+        self.tx.send(Command::SetPromptFormat(format))?;
+        Ok(())
+    }
+
+    /// Set the styles of the parts of the prompt
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use crossterm::style::{ContentStyle, Stylize};
+    /// use minus::{prompt::PromptTheme, Pager};
+    ///
+    /// let pager = Pager::new();
+    /// pager
+    ///     .set_prompt_theme(PromptTheme {
+    ///         message: ContentStyle::new().white().on_dark_magenta(),
+    ///         ..PromptTheme::default()
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn set_prompt_theme(&self, theme: PromptTheme) -> crate::Result {
+        self.tx.send(Command::SetPromptTheme(theme))?;
+        Ok(())
+    }
+
     /// Send a message to be displayed the prompt area
     ///
     /// The text message is temporary and will get cleared whenever the use
@@ -186,6 +386,39 @@ impl Pager {
         Ok(self.tx.send(Command::SetExitStrategy(es))?)
     }
 
+    /// Signal that all the data has been sent to the pager
+    ///
+    /// Until then, the user can't tell whether more data is coming. Afterwards, the prompt shows
+    /// `(END)` at the bottom of the text instead of the `Waiting for data...` shown in
+    /// [follow mode](Pager::follow_output). See the [`prompt`](crate::prompt) module for
+    /// displaying these with a prompt format.
+    ///
+    /// Like `less -F`, a dynamic pager then quits if all of the text fits on the screen and
+    /// prints it to the main screen instead, as if it was never started. This is useful for
+    /// streamed output like that of `git log`, which is often short. If this is called before
+    /// the pager starts, the pager is not started at all in that case. A pager
+    /// [drawn inline](Pager::set_inline) leaves the text where it is. This can be turned off
+    /// with [`Pager::set_run_no_overflow`].
+    ///
+    /// [`Pager::push_reader`] does not call this by itself, since the text may come from more
+    /// than one source.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the receiver
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.push_str("a short commit log\n").unwrap();
+    /// pager.end_of_input().unwrap();
+    /// ```
+    pub fn end_of_input(&self) -> Result<(), MinusError> {
+        Ok(self.tx.send(Command::EndOfInput)?)
+    }
+
     /// Set whether to display pager if there's less data than
     /// available screen height
     ///
@@ -195,9 +428,9 @@ impl Pager {
     /// Setting this to true will cause a full pager to start and display the data
     /// even if there is less number of lines to display than available rows.
     ///
-    /// This is only available in static output mode as the size of the data is
-    /// known beforehand.
-    /// In async output the pager can receive more data anytime
+    /// In static output mode, the size of the data is known beforehand. In dynamic
+    /// output mode, the pager can receive more data anytime, hence this only applies
+    /// once [`Pager::end_of_input`] has been called.
     ///
     /// By default this is set to false
     ///
@@ -211,8 +444,6 @@ impl Pager {
     /// let pager = Pager::new();
     /// pager.set_run_no_overflow(true).expect("Failed to communicate with the pager");
     /// ```
-    #[cfg(feature = "static_output")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "static_output")))]
     pub fn set_run_no_overflow(&self, val: bool) -> Result<(), MinusError> {
         Ok(self.tx.send(Command::SetRunNoOverflow(val))?)
     }
@@ -306,6 +537,40 @@ impl Pager {
         Ok(self.tx.send(Command::AddExitCallback(cb))?)
     }
 
+    /// Run `f` while the pager is suspended
+    ///
+    /// The pager gives the terminal back by restoring it to the state it was in before the pager
+    /// started and stops reading user input while `f` runs. This lets `f` run interactive
+    /// programs like a shell or an editor. Once `f` returns, the pager takes over the terminal
+    /// again and redraws the screen.
+    ///
+    /// `f` runs on the thread of the pager after the commands sent before it have been applied,
+    /// hence it must not wait for the pager to apply any other command. If the pager has not
+    /// started yet, `f` runs when it starts, before it takes over the terminal.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the receiver
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager
+    ///     .suspend_with(|| {
+    ///         let _ = std::process::Command::new("git").arg("status").status();
+    ///     })
+    ///     .expect("Failed to communicate with the pager");
+    /// ```
+    pub fn suspend_with<F>(&self, f: F) -> crate::Result
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.tx.send(Command::SuspendWith(Box::new(f)))?;
+        Ok(())
+    }
+
     /// Override the condition for running incremental search
     ///
     /// See [Incremental Search](../search/index.html#incremental-search) to know more on how this
@@ -324,6 +589,220 @@ impl Pager {
         Ok(())
     }
 
+    /// Set how the letter case is treated when searching
+    ///
+    /// By default, the case is matched exactly. The user can also cycle through the modes with
+    /// `Alt+C` at the search prompt. If a search is active, its matches are found again.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::{Pager, search::CaseSensitivity};
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_search_case(CaseSensitivity::Smart).unwrap();
+    /// ```
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    pub fn set_search_case(&self, case_sensitivity: CaseSensitivity) -> crate::Result {
+        self.tx.send(Command::SetSearchCase(case_sensitivity))?;
+        Ok(())
+    }
+
+    /// Set whether search queries are matched as plain text rather than as regular expressions
+    ///
+    /// This is useful for searching text like `foo(bar)` without escaping it. The user can also
+    /// toggle it with `Ctrl+R` at the search prompt. If a search is active, its matches are
+    /// found again.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_literal_search(true).unwrap();
+    /// ```
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    pub fn set_literal_search(&self, literal: bool) -> crate::Result {
+        self.tx.send(Command::SetLiteralSearch(literal))?;
+        Ok(())
+    }
+
+    /// Add queries to the search history
+    ///
+    /// The user can recall the queries in the search history with `Up` and `Down` at the search
+    /// prompt. This can be used to offer commonly searched patterns. The queries are added as
+    /// the most recent ones, in the given order.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.add_search_history(["ERROR", "WARN(ING)?"]).unwrap();
+    /// ```
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    pub fn add_search_history<I, S>(&self, queries: I) -> crate::Result
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let queries = queries.into_iter().map(Into::into).collect();
+        self.tx.send(Command::AddSearchHistory(queries))?;
+        Ok(())
+    }
+
+    /// Keep the search history in the file at `path`
+    ///
+    /// The queries already stored in the file are loaded into the history and the history is
+    /// written back to it after each search. The file is created if it does not exist.
+    ///
+    /// Errors encountered while accessing the file are shown at the prompt.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```no_run
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_search_history_file("/home/user/.myapp_history").unwrap();
+    /// ```
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    pub fn set_search_history_file(&self, path: impl Into<PathBuf>) -> crate::Result {
+        self.tx.send(Command::SetSearchHistoryFile(path.into()))?;
+        Ok(())
+    }
+
+    /// Display only the lines that pass `filter`
+    ///
+    /// The hidden lines are still kept and the displayed lines keep their original line
+    /// numbers. Searching and scrolling only consider the displayed lines and text that is
+    /// appended later is filtered as well. Each buffer has its own filter. Pass `None` to
+    /// display all the lines again.
+    ///
+    /// The user can also set a filter by pressing `&` and typing a pattern. A pattern starting
+    /// with `!` displays only the lines that don't match it and an empty pattern removes the filter.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::{Pager, search::Filter};
+    /// use regex::Regex;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_filter(Some(Filter::new(Regex::new("ERROR|WARN").unwrap()))).unwrap();
+    /// // Hide the debug messages instead
+    /// pager.set_filter(Some(Filter::inverted(Regex::new("DEBUG").unwrap()))).unwrap();
+    /// ```
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    pub fn set_filter(&self, filter: Option<Filter>) -> crate::Result {
+        self.tx.send(Command::SetFilter(filter))?;
+        Ok(())
+    }
+
+    /// Display the matches of `pattern` in the given `style`
+    ///
+    /// Any number of patterns can be highlighted at once, each in its own style. Unlike the search
+    /// term, they are highlighted in all buffers and can't be jumped between with `n` and `p`.
+    /// Adding a pattern that is already highlighted changes its style. Where a highlight overlaps
+    /// with a search match, the search match takes precedence.
+    ///
+    /// The user can also highlight a pattern by pressing `+` and typing it. These highlights get
+    /// a background color that is not used by other highlights yet.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use crossterm::style::{Color, ContentStyle, Stylize};
+    /// use minus::Pager;
+    /// use regex::Regex;
+    ///
+    /// let pager = Pager::new();
+    /// let request_id = Regex::new("req-[0-9a-f]{8}").unwrap();
+    /// pager.add_highlight(request_id, ContentStyle::new().with(Color::Yellow)).unwrap();
+    /// pager.add_highlight(Regex::new("ERROR").unwrap(), ContentStyle::new().red().bold()).unwrap();
+    /// ```
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    pub fn add_highlight(&self, pattern: Regex, style: ContentStyle) -> crate::Result {
+        self.tx
+            .send(Command::AddHighlight(Highlight::new(pattern, style)))?;
+        Ok(())
+    }
+
+    /// Stop highlighting `pattern`
+    ///
+    /// `pattern` is compared with the source text of the patterns passed to
+    /// [`Pager::add_highlight`]. The user can also remove a highlight by pressing `-` and typing
+    /// its pattern.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use crossterm::style::{ContentStyle, Stylize};
+    /// use minus::Pager;
+    /// use regex::Regex;
+    ///
+    /// let pager = Pager::new();
+    /// pager.add_highlight(Regex::new("ERROR").unwrap(), ContentStyle::new().red()).unwrap();
+    /// pager.remove_highlight("ERROR").unwrap();
+    /// ```
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    pub fn remove_highlight(&self, pattern: impl Into<String>) -> crate::Result {
+        self.tx.send(Command::RemoveHighlight(pattern.into()))?;
+        Ok(())
+    }
+
+    /// Stop highlighting all the patterns
+    ///
+    /// This does not affect the highlighting of search matches.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.clear_highlights().unwrap();
+    /// ```
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    pub fn clear_highlights(&self) -> crate::Result {
+        self.tx.send(Command::ClearHighlights)?;
+        Ok(())
+    }
+
     /// Control whether to show the prompt
     ///
     /// Many applications don't want the prompt to be displayed at all. This function can be used to completely turn
@@ -373,16 +852,662 @@ impl Pager {
         self.tx.send(Command::FollowOutput(follow_output))?;
         Ok(())
     }
-}
 
-impl Default for Pager {
-    fn default() -> Self {
-        Self::new()
+    /// Set where the text given to the pager is stored
+    ///
+    /// The text already present in the pager is discarded and the text inside `store` is
+    /// displayed instead. Any text sent to the pager afterwards is appended to `store`.
+    ///
+    /// By default, all the text is kept in memory. See the [storage](crate::screen::storage)
+    /// module for the other options.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::{screen::storage::FileStore, Pager};
+    /// use std::io::Cursor;
+    ///
+    /// let pager = Pager::new();
+    /// let store = FileStore::new(Cursor::new(b"Hello\nWorld\n".to_vec())).unwrap();
+    /// pager.set_storage(store).unwrap();
+    /// ```
+    pub fn set_storage(&self, store: impl TextStore + 'static) -> crate::Result {
+        self.tx.send(Command::SetStorage(Box::new(store)))?;
+        Ok(())
     }
-}
 
-impl fmt::Write for Pager {
+    /// Set the maximum number of formatted rows to keep in memory
+    ///
+    /// Before displaying the text, minus breaks it into rows that fit on the terminal. By
+    /// default, all of these rows are kept in memory. When a limit is set, only the rows around
+    /// the part that is being displayed are kept and the rest are formatted again from the
+    /// text when they are scrolled to. Passing `None` removes the limit.
+    ///
+    /// The limit is raised to the number of rows of the terminal if it is smaller.
+    ///
+    /// This can be combined with [`Pager::set_storage`] to page huge amounts of text
+    /// with little memory.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_max_formatted_rows(Some(10_000)).unwrap();
+    /// ```
+    pub fn set_max_formatted_rows(&self, rows: Option<usize>) -> crate::Result {
+        self.tx.send(Command::SetMaxFormattedRows(rows))?;
+        Ok(())
+    }
+
+    /// Set the maximum number of lines to keep
+    ///
+    /// Once the text grows beyond this many lines, the oldest lines are dropped. This keeps the
+    /// memory used by endless streams, like the output of `tail -f`, from growing without bound.
+    /// Passing `None` removes the limit.
+    ///
+    /// The view and the search matches stay on the same text as lines are dropped. When line
+    /// numbers are shown, the lines that are kept keep their numbers, so the oldest line is
+    /// numbered after the lines that were dropped. The last line is always kept, even if the
+    /// limit is `0`.
+    ///
+    /// A [`FileStore`] only removes the dropped lines from its stream if it can truncate it, see
+    /// [`FileStore::truncate_with`].
+    ///
+    /// [`FileStore`]: crate::screen::storage::FileStore
+    /// [`FileStore::truncate_with`]: crate::screen::storage::FileStore::truncate_with
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_max_lines(Some(10_000)).unwrap();
+    /// pager.follow_output(true).unwrap();
+    /// ```
+    pub fn set_max_lines(&self, lines: Option<usize>) -> crate::Result {
+        self.tx.send(Command::SetMaxLines(lines))?;
+        Ok(())
+    }
+
+    /// Save the text of the current buffer to the file at `path`
+    ///
+    /// The file is created if it does not exist and replaced if it does. `options` decide which
+    /// part of the text is saved and whether it keeps its colors. Whether the text was saved is
+    /// shown at the prompt. This works even if saving is turned off for users with
+    /// [`Pager::allow_saving`]. See the [`save`](crate::save) module for more info.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::{save::SaveOptions, Pager};
+    ///
+    /// let pager = Pager::new();
+    /// pager.push_str("some output\n").unwrap();
+    /// pager.save_to_file("output.log", SaveOptions::default()).unwrap();
+    /// ```
+    pub fn save_to_file(&self, path: impl Into<PathBuf>, options: SaveOptions) -> crate::Result {
+        self.tx.send(Command::SaveToFile(path.into(), options))?;
+        Ok(())
+    }
+
+    /// Set the options used when users save the text by pressing `s`
+    ///
+    /// By default, all of the text is saved as it is.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::{
+    ///     save::{SaveOptions, SaveRegion},
+    ///     Pager,
+    /// };
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_save_options(SaveOptions {
+    ///     strip_ansi: true,
+    ///     region: SaveRegion::Marks('a', 'b'),
+    /// }).unwrap();
+    /// ```
+    pub fn set_save_options(&self, options: SaveOptions) -> crate::Result {
+        self.tx.send(Command::SetSaveOptions(options))?;
+        Ok(())
+    }
+
+    /// Set whether users can save the text to a file by pressing `s`
+    ///
+    /// Saving is allowed by default. Applications that should not let users write files, for
+    /// example because they run with more privileges than their users, can turn it off.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.allow_saving(false).unwrap();
+    /// ```
+    pub fn allow_saving(&self, allowed: bool) -> crate::Result {
+        self.tx.send(Command::AllowSaving(allowed))?;
+        Ok(())
+    }
+
+    /// Set whether users can run other programs from the pager
+    ///
+    /// This covers opening the text in an editor with `v` and running a shell command with `!`.
+    /// Like `less` with `LESSSECURE` set, both are turned off by default since the pager may run
+    /// with more privileges than its users, and applications have to opt in to them. This does
+    /// not affect [`Pager::suspend_with`] and the commands of a
+    /// [`HintAction`](crate::hints::HintAction), which are chosen by the application.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.allow_external_commands(true).unwrap();
+    /// ```
+    pub fn allow_external_commands(&self, allowed: bool) -> crate::Result {
+        self.tx.send(Command::AllowExternalCommands(allowed))?;
+        Ok(())
+    }
+
+    /// Draw the pager inline in `rows` rows below the cursor instead of on the alternate screen
+    ///
+    /// Like `fzf --height`, the pager takes up the given number of rows, including the prompt,
+    /// and the text above it stays on the screen. When the pager quits, the last page it
+    /// displayed is left behind in place of the prompt and the scrollback keeps it as well. The
+    /// number of rows is capped to the height of the terminal. Passing `None` goes back to the
+    /// alternate screen, which is the default.
+    ///
+    /// This only takes effect if it is called before the pager starts.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_inline(Some(12)).unwrap();
+    /// ```
+    pub fn set_inline(&self, rows: Option<usize>) -> crate::Result {
+        self.tx.send(Command::SetInline(rows))?;
+        Ok(())
+    }
+
+    /// Highlight the syntax of the text with `highlighter`
+    ///
+    /// The highlighter is called for each line as it gets formatted for display, so the text can
+    /// be given to the pager without any colors. Each buffer has its own highlighter, this sets
+    /// the one of the current buffer. See the [`syntax`](crate::screen::syntax) module for more
+    /// info.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "syntax")]
+    /// # {
+    /// use minus::{screen::syntax::JsonHighlighter, Pager};
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_highlighter(JsonHighlighter).unwrap();
+    /// pager.set_text(r#"{"name": "minus", "stars": 300}"#).unwrap();
+    /// # }
+    /// ```
+    pub fn set_highlighter(&self, highlighter: impl Highlighter) -> crate::Result {
+        self.tx
+            .send(Command::SetHighlighter(Some(SyntaxHighlighting::new(
+                highlighter,
+            ))))?;
+        Ok(())
+    }
+
+    /// Remove the highlighter of the current buffer
+    ///
+    /// See [`Pager::set_highlighter`] for more info.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.remove_highlighter().unwrap();
+    /// ```
+    pub fn remove_highlighter(&self) -> crate::Result {
+        self.tx.send(Command::SetHighlighter(None))?;
+        Ok(())
+    }
+
+    /// Set what to do with the links and file locations selected by users in hints mode
+    ///
+    /// Users can press `o` to label the links and file locations on the screen and type a label
+    /// to open it with `action`. Passing `None` turns hints mode off, which is the default. See
+    /// the [`hints`](crate::hints) module for more info.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::{hints::HintAction, Pager};
+    ///
+    /// let pager = Pager::new();
+    /// pager
+    ///     .set_hint_action(Some(HintAction::callback(|hint| eprintln!("{hint:?}"))))
+    ///     .unwrap();
+    /// ```
+    pub fn set_hint_action(&self, action: Option<HintAction>) -> crate::Result {
+        self.tx.send(Command::SetHintAction(action))?;
+        Ok(())
+    }
+
+    /// Set the mark named `name` on the zero based line number `line`
+    ///
+    /// The user can jump to a mark by pressing `'` followed by its name and set a mark on the
+    /// line at the top of the screen by pressing `m` followed by a name. Marks stay on their
+    /// lines when the text is wrapped again and move along with them when the oldest lines are
+    /// dropped. Each buffer has marks of its own, this sets the mark in the buffer being
+    /// displayed.
+    ///
+    /// Unlike most other functions, this takes effect immediately, even before the pager is
+    /// started.
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.push_str("starting\nERROR: disk full\n").unwrap();
+    /// pager.set_mark('e', 1);
+    /// assert_eq!(pager.marks().get(&'e'), Some(&1));
+    /// ```
+    pub fn set_mark(&self, name: char, line: usize) {
+        self.marks.lock().insert(name, line);
+    }
+
+    /// Remove the mark named `name`, returning the line it was set on
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_mark('a', 10);
+    /// assert_eq!(pager.remove_mark('a'), Some(10));
+    /// assert!(pager.marks().is_empty());
+    /// ```
+    #[allow(clippy::must_use_candidate)]
+    pub fn remove_mark(&self, name: char) -> Option<usize> {
+        self.marks.lock().remove(&name)
+    }
+
+    /// Get all the marks of the buffer being displayed along with their zero based line numbers
+    ///
+    /// This includes the marks set by the user. See [`Pager::set_mark`] for more info.
+    #[must_use]
+    pub fn marks(&self) -> BTreeMap<char, usize> {
+        self.marks.lock().clone()
+    }
+
+    /// Split the view into two panes showing different parts of the text
+    ///
+    /// The panes are stacked on top of each other and scroll independently. Only the focused
+    /// pane responds to scrolling and searching, the user can move the focus to the other pane
+    /// with `Ctrl+W`. Both panes start at the current position.
+    ///
+    /// When `false` is passed, the view goes back to a single pane showing the focused one.
+    /// The user can also toggle the split with `W`.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.split_view(true).unwrap();
+    /// ```
+    pub fn split_view(&self, split: bool) -> crate::Result {
+        self.tx.send(Command::SplitView(split))?;
+        Ok(())
+    }
+
+    /// Add a buffer named `name` holding `text`
+    ///
+    /// The pager can hold several documents, called buffers, and display one of them at a time.
+    /// It starts with a single buffer having an empty name. The user can move to the next and
+    /// previous buffers with `]` and `[` while the name and position of the current buffer are
+    /// shown at the prompt.
+    ///
+    /// Each buffer keeps its own scroll position, search and line number mode. A new buffer
+    /// starts with the line number mode of the buffer that is being displayed.
+    ///
+    /// If a buffer named `name` already exists, its text is replaced with `text`.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    ///
+    /// # Example
+    /// ```
+    /// use minus::Pager;
+    ///
+    /// let pager = Pager::new();
+    /// pager.set_text("The first buffer").unwrap();
+    /// pager.add_buffer("notes.txt", "Another buffer").unwrap();
+    /// pager.switch_buffer("notes.txt").unwrap();
+    /// ```
+    pub fn add_buffer(&self, name: impl Into<String>, text: impl Into<String>) -> crate::Result {
+        self.tx.send(Command::AddBuffer(name.into(), text.into()))?;
+        Ok(())
+    }
+
+    /// Append `text` to the buffer named `name`
+    ///
+    /// Nothing is done if there is no such buffer.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    pub fn push_str_to_buffer(
+        &self,
+        name: impl Into<String>,
+        text: impl Into<String>,
+    ) -> crate::Result {
+        self.tx
+            .send(Command::AppendBufferData(name.into(), text.into()))?;
+        Ok(())
+    }
+
+    /// Remove the buffer named `name`
+    ///
+    /// If the buffer is being displayed, the next buffer is displayed in its place. Nothing is
+    /// done if there is no such buffer or it is the only buffer.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    pub fn remove_buffer(&self, name: impl Into<String>) -> crate::Result {
+        self.tx.send(Command::RemoveBuffer(name.into()))?;
+        Ok(())
+    }
+
+    /// Display the buffer named `name`
+    ///
+    /// Nothing is done if there is no such buffer.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the mus's receiving end
+    pub fn switch_buffer(&self, name: impl Into<String>) -> crate::Result {
+        self.tx.send(Command::SwitchBuffer(name.into()))?;
+        Ok(())
+    }
+
+    /// Run the pager on a custom [`Terminal`], reading user input from `events`
+    ///
+    /// This is what [`dynamic_paging`](crate::dynamic_paging) and [`page_all`](crate::page_all)
+    /// do with the standard output and [`CrosstermEvents`](crate::backend::CrosstermEvents).
+    /// It allows applications that own their output stream to draw the pager onto it, for
+    /// example a pseudo terminal they have allocated or an in-memory buffer.
+    ///
+    /// `mode` has the same meaning as calling the respective function. Note that in
+    /// [`RunMode::Static`], the data is written directly to `terminal` without starting the
+    /// pager if [`Terminal::is_interactive`] returns `false`.
+    ///
+    /// See the [backend](crate::backend) module for more information.
+    ///
+    /// # Panics
+    /// This function will panic if `mode` is [`RunMode::Uninitialized`] or if this pager is
+    /// already running.
+    ///
+    /// # Errors
+    /// The function will return with an error if it encounters a error during paging.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use minus::{backend::{CrosstermEvents, Terminal}, Pager, RunMode};
+    /// use std::io::{self, Write};
+    ///
+    /// // A terminal that stores everything that minus draws
+    /// struct Buffer(Vec<u8>);
+    ///
+    /// impl Write for Buffer {
+    ///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    ///         self.0.write(buf)
+    ///     }
+    ///     fn flush(&mut self) -> io::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// impl Terminal for Buffer {
+    ///     fn size(&self) -> io::Result<(u16, u16)> {
+    ///         Ok((80, 24))
+    ///     }
+    /// }
+    ///
+    /// let pager = Pager::new();
+    /// pager.push_str("Some text").unwrap();
+    /// # #[cfg(feature = "dynamic_output")]
+    /// pager.run_with(RunMode::Dynamic, &mut Buffer(Vec::new()), CrosstermEvents).unwrap();
+    /// ```
+    #[cfg(any(feature = "dynamic_output", feature = "static_output"))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "dynamic_output", feature = "static_output")))
+    )]
+    pub fn run_with<T, E>(&self, mode: RunMode, terminal: &mut T, events: E) -> crate::Result
+    where
+        T: Terminal,
+        E: EventSource,
+    {
+        init::init_core_with(self, mode, terminal, events)
+    }
+}
+
+/// Functions for using minus from asynchronous code
+///
+/// None of these require a dedicated thread. While the search prompt is open though, the
+/// thread polling [`Pager::run_async`] is blocked waiting for the user's input.
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl Pager {
+    /// Same as [`Pager::set_text`] but waits without blocking if the pager is full
+    ///
+    /// See [`Pager::with_capacity`] for more information.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the receiver
+    pub async fn set_text_async(&self, s: impl Into<String>) -> Result<(), MinusError> {
+        Ok(self.tx.send_async(Command::SetData(s.into())).await?)
+    }
+
+    /// Same as [`Pager::push_str`] but waits without blocking if the pager is full
+    ///
+    /// See [`Pager::with_capacity`] for more information.
+    ///
+    /// # Errors
+    /// This function will return a [`Err(MinusError::Communication)`](MinusError::Communication) if the data
+    /// could not be sent to the receiver
+    pub async fn push_str_async(&self, s: impl Into<String>) -> Result<(), MinusError> {
+        Ok(self.tx.send_async(Command::AppendData(s.into())).await?)
+    }
+
+    /// Run the pager on the current task
+    ///
+    /// This is the asynchronous counterpart of [`dynamic_paging`](crate::dynamic_paging). It
+    /// draws on the standard output and reads user input from crossterm's
+    /// [`EventStream`](crossterm::event::EventStream).
+    ///
+    /// Since minus does not depend on an async runtime, it has no timer to wake it up. Hence the
+    /// spinner shown while [waiting for data](crate::prompt#end-of-the-input) is not animated.
+    ///
+    /// # Panics
+    /// This function will panic if this pager is already running.
+    ///
+    /// # Errors
+    /// The function will return with an error if it encounters a error during paging.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use minus::{MinusError, Pager};
+    /// use std::time::Duration;
+    /// use tokio::{join, time::sleep};
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<(), MinusError> {
+    ///     let pager = Pager::with_capacity(10);
+    ///     let increment = async {
+    ///         for i in 0..=100_u32 {
+    ///             pager.push_str_async(format!("{i}\n")).await?;
+    ///             sleep(Duration::from_millis(100)).await;
+    ///         }
+    ///         Result::<_, MinusError>::Ok(())
+    ///     };
+    ///     let (res1, res2) = join!(pager.run_async(), increment);
+    ///     res1?;
+    ///     res2?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn run_async(&self) -> crate::Result {
+        init::init_core_async(
+            self,
+            &mut std::io::stdout(),
+            crossterm::event::EventStream::new(),
+        )
+        .await
+    }
+
+    /// Run the pager on the current task, drawing on `terminal` and reading user input from
+    /// `events`
+    ///
+    /// This is the asynchronous counterpart of [`Pager::run_with`]. The pager stops once
+    /// `events` ends.
+    ///
+    /// # Panics
+    /// This function will panic if this pager is already running.
+    ///
+    /// # Errors
+    /// The function will return with an error if it encounters a error during paging.
+    pub async fn run_async_with<T, S>(&self, terminal: &mut T, events: S) -> crate::Result
+    where
+        T: Terminal,
+        S: futures_util::Stream<Item = std::io::Result<crossterm::event::Event>> + Unpin + Send,
+    {
+        init::init_core_async(self, terminal, events).await
+    }
+}
+
+impl Default for Pager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Write for Pager {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         self.push_str(s).map_err(|_| fmt::Error)
     }
 }
+
+/// Size of the chunks read by [`Pager::push_reader`]
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Read `reader` till the end and send the data to the pager
+///
+/// UTF-8 sequences that are split across two reads are held back until the rest of the
+/// sequence arrives.
+fn read_into(mut reader: impl Read, tx: &Sender<Command>) -> Result<(), MinusError> {
+    let mut buf = vec![0; READ_CHUNK_SIZE];
+    // Bytes of an incomplete UTF-8 sequence at the end of the last read
+    let mut pending = Vec::new();
+
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(MinusError::ReadInput(e)),
+        };
+        pending.extend_from_slice(&buf[..n]);
+
+        let mut text = String::with_capacity(pending.len());
+        let mut rest = pending.as_slice();
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    text.push_str(s);
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    text.push_str(&String::from_utf8_lossy(valid));
+                    match e.error_len() {
+                        // The sequence may be completed by the next read
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                        Some(len) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                    }
+                }
+            }
+        }
+        pending = rest.to_vec();
+
+        if !text.is_empty() {
+            tx.send(Command::AppendData(text))?;
+        }
+    }
+
+    if !pending.is_empty() {
+        tx.send(Command::AppendData(
+            String::from_utf8_lossy(&pending).into_owned(),
+        ))?;
+    }
+    Ok(())
+}
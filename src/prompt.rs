@@ -0,0 +1,358 @@
+//! Customizing the prompt displayed at the bottom of the pager
+//!
+//! The look of each part of the prompt can be changed by setting a [`PromptTheme`] with
+//! [`Pager::set_prompt_theme`](crate::Pager::set_prompt_theme).
+//!
+//! The text of the prompt can be given a layout with
+//! [`Pager::set_prompt_format`](crate::Pager::set_prompt_format). Similar to the prompts of
+//! `less`, the format string contains placeholders that are replaced with the current
+//! values when the prompt is displayed:
+//!
+//! | Placeholder | Value                                                                   |
+//! |-------------|-------------------------------------------------------------------------|
+//! | `%t`        | Text set with [`Pager::set_prompt`](crate::Pager::set_prompt)           |
+//! | `%l`        | Number of the line at the top of the screen                             |
+//! | `%L`        | Total number of lines                                                   |
+//! | `%p`        | Percentage of the text that is above the bottom of the screen           |
+//! | `%b`        | Name of the current buffer                                              |
+//! | `%B`        | Position of the current buffer and the number of buffers, like `1/3`    |
+//! | `%s`        | Index of the current search match and the number of matches, like `2/9` |
+//! | `%f`        | `[F]` when [follow mode](crate::Pager::follow_output) is on             |
+//! | `%e`        | `(END)` or `Waiting for data...` at the bottom of the text, see below   |
+//! | `%%`        | A literal `%`                                                           |
+//!
+//! Placeholders that have no value, like `%s` when nothing is searched, are replaced with
+//! nothing. A part of the format can be displayed only when a placeholder has a value by
+//! writing it as `?x` followed by the part and a `.`, where `x` is the letter of the
+//! placeholder. Another part to display when it has no value can be added after a `:`, like
+//! in `?s match %s:no matches.`. Any character preceded by `\` is displayed as is, which is
+//! useful for displaying `?`, `:` or `.` inside of these parts.
+//!
+//! # End of the input
+//! Once the bottom of the text is reached, the prompt shows `(END)` if the application has
+//! signalled that all the data has been given with [`Pager::end_of_input`](crate::Pager::end_of_input).
+//! Until then, in follow mode, it shows a spinner followed by `Waiting for data...` like `less`
+//! does. Without a format, these are shown along with the other indicators on the right. The
+//! spinner is not animated with [`Pager::run_async`](crate::Pager::run_async), which has no
+//! timer to turn it.
+//!
+//! # Example
+//! ```
+//! use crossterm::style::{ContentStyle, Stylize};
+//! use minus::{prompt::PromptTheme, Pager};
+//!
+//! let pager = Pager::new();
+//! pager.set_prompt_format("%b line %l/%L %p\\%?s  match %s.?f  %f.")?;
+//! pager.set_prompt_theme(PromptTheme {
+//!     prompt: ContentStyle::new().white().on_dark_blue(),
+//!     ..PromptTheme::default()
+//! })?;
+//! # Ok::<(), minus::MinusError>(())
+//! ```
+use crossterm::style::{ContentStyle, Stylize};
+use std::{fmt::Write as _, iter::Peekable, str::Chars};
+
+/// Styles for the parts of the prompt
+///
+/// The default theme gives the prompt a black background, messages a red background and the
+/// search index a blue one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptTheme {
+    /// Style of the prompt text and the space filling the rest of the line
+    pub prompt: ContentStyle,
+    /// Style of the messages sent with [`Pager::send_message`](crate::Pager::send_message)
+    pub message: ContentStyle,
+    /// Style of the number typed before a command, like the `5` of `5j`
+    pub prefix_num: ContentStyle,
+    /// Style of the index of the current search match
+    pub search_index: ContentStyle,
+    /// Style of the follow mode indicator
+    pub follow_mode: ContentStyle,
+    /// Style of the indicator shown while data is being loaded or awaited
+    pub loading: ContentStyle,
+    /// Style of the `(END)` indicator shown at the bottom of the text once all the data has been
+    /// given
+    pub end: ContentStyle,
+}
+
+impl Default for PromptTheme {
+    fn default() -> Self {
+        Self {
+            prompt: ContentStyle::new().grey().on_black().dim(),
+            message: ContentStyle::new().black().on_dark_red().bold(),
+            prefix_num: ContentStyle::new().black().on_dark_yellow(),
+            search_index: ContentStyle::new().black().on_dark_blue(),
+            follow_mode: ContentStyle::new().bold(),
+            loading: ContentStyle::new().dim(),
+            end: ContentStyle::new().reverse(),
+        }
+    }
+}
+
+/// Part of the prompt displayed in a single style
+pub(crate) type Segment = (String, ContentStyle);
+
+/// Values of the placeholders of a prompt format
+pub(crate) struct PromptValues<'a> {
+    pub text: &'a str,
+    pub top_line: usize,
+    pub line_count: usize,
+    pub percent: usize,
+    pub buffer_name: &'a str,
+    /// Position of the current buffer, empty when there is only one buffer
+    pub buffer_position: String,
+    /// Position of the current search match, empty when nothing is searched
+    pub search_index: String,
+    pub follow_output: bool,
+    /// Indicator of the state of the input, empty when there is none
+    pub status: Segment,
+}
+
+impl PromptValues<'_> {
+    /// Value of the placeholder `%placeholder` along with its style
+    ///
+    /// Returns `None` if there is no such placeholder.
+    fn get(&self, placeholder: char, theme: &PromptTheme) -> Option<Segment> {
+        let value = match placeholder {
+            't' => self.text.to_string(),
+            'l' => self.top_line.to_string(),
+            'L' => self.line_count.to_string(),
+            'p' => self.percent.to_string(),
+            'b' => self.buffer_name.to_string(),
+            'B' => self.buffer_position.clone(),
+            's' => return Some((self.search_index.clone(), theme.search_index)),
+            'f' => {
+                let value = if self.follow_output { "[F]" } else { "" };
+                return Some((value.to_string(), theme.follow_mode));
+            }
+            'e' => return Some(self.status.clone()),
+            '%' => "%".to_string(),
+            _ => return None,
+        };
+        Some((value, theme.prompt))
+    }
+}
+
+/// Fill `format` with `values`, returning the parts of the prompt in their styles
+pub(crate) fn render(format: &str, values: &PromptValues, theme: &PromptTheme) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    render_until(
+        &mut format.chars().peekable(),
+        values,
+        theme,
+        &mut segments,
+        true,
+        &[],
+    );
+    segments
+}
+
+/// Render the format up to one of the `stop` characters, returning the one that was found
+///
+/// Nothing is added to `segments` if `display` is false, the format is only skipped over.
+fn render_until(
+    format: &mut Peekable<Chars>,
+    values: &PromptValues,
+    theme: &PromptTheme,
+    segments: &mut Vec<Segment>,
+    display: bool,
+    stop: &[char],
+) -> Option<char> {
+    let push = |segments: &mut Vec<Segment>, text: &str, style: ContentStyle| {
+        if !display || text.is_empty() {
+            return;
+        }
+        match segments.last_mut() {
+            Some((last, last_style)) if *last_style == style => last.push_str(text),
+            _ => segments.push((text.to_string(), style)),
+        }
+    };
+    while let Some(c) = format.next() {
+        match c {
+            c if stop.contains(&c) => return Some(c),
+            '\\' => {
+                if let Some(escaped) = format.next() {
+                    push(segments, escaped.encode_utf8(&mut [0; 4]), theme.prompt);
+                }
+            }
+            '%' => match format.peek().and_then(|p| values.get(*p, theme)) {
+                Some((value, style)) => {
+                    format.next();
+                    push(segments, &value, style);
+                }
+                None => push(segments, "%", theme.prompt),
+            },
+            '?' => {
+                let Some(placeholder) = format.next() else {
+                    break;
+                };
+                let has_value =
+                    matches!(values.get(placeholder, theme), Some((value, _)) if !value.is_empty());
+                let end = render_until(
+                    format,
+                    values,
+                    theme,
+                    segments,
+                    display && has_value,
+                    &[':', '.'],
+                );
+                if end == Some(':') {
+                    render_until(
+                        format,
+                        values,
+                        theme,
+                        segments,
+                        display && !has_value,
+                        &['.'],
+                    );
+                }
+            }
+            c => push(segments, c.encode_utf8(&mut [0; 4]), theme.prompt),
+        }
+    }
+    None
+}
+
+/// Lay out the prompt on a line of `cols` columns
+///
+/// The `left` segments are cut to leave room for the `right` ones, which are aligned to the
+/// right edge. The space between them is given the style of the last segment on the left, or
+/// `fill` if there is none.
+pub(crate) fn layout(
+    left: Vec<Segment>,
+    right: &[Segment],
+    cols: usize,
+    fill: ContentStyle,
+) -> String {
+    let right_len: usize = right.iter().map(|(text, _)| text.chars().count()).sum();
+    let mut room = cols.saturating_sub(right_len);
+    let mut line = String::with_capacity(cols + 64);
+    let mut fill = fill;
+    for (text, style) in left {
+        if room == 0 {
+            break;
+        }
+        let len = text.chars().count();
+        let text = if len > room {
+            text.chars().take(room).collect()
+        } else {
+            text
+        };
+        room -= len.min(room);
+        fill = style;
+        write!(line, "{}", style.apply(text)).unwrap();
+    }
+    if room > 0 {
+        write!(line, "{}", fill.apply(" ".repeat(room))).unwrap();
+    }
+    // The indicators are cut as well if they don't fit on their own
+    let mut room = cols;
+    for (text, style) in right {
+        if room == 0 {
+            break;
+        }
+        let len = text.chars().count();
+        if len > room {
+            write!(
+                line,
+                "{}",
+                style.apply(text.chars().take(room).collect::<String>())
+            )
+            .unwrap();
+        } else {
+            write!(line, "{}", style.apply(text)).unwrap();
+        }
+        room -= len.min(room);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layout, render, PromptTheme, PromptValues};
+    use crossterm::style::{ContentStyle, Stylize};
+
+    fn values() -> PromptValues<'static> {
+        PromptValues {
+            text: "file.txt",
+            top_line: 5,
+            line_count: 120,
+            percent: 20,
+            buffer_name: "logs",
+            buffer_position: "2/3".to_string(),
+            search_index: String::new(),
+            follow_output: false,
+            status: (String::new(), ContentStyle::new()),
+        }
+    }
+
+    fn render_text(format: &str, values: &PromptValues) -> String {
+        render(format, values, &PromptTheme::default())
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            render_text("%t: %l/%L %p%% [%b %B]", &values()),
+            "file.txt: 5/120 20% [logs 2/3]"
+        );
+        // Unknown placeholders are displayed as they are
+        assert_eq!(render_text("%x 100%", &values()), "%x 100%");
+    }
+
+    #[test]
+    fn conditionals() {
+        let mut values = values();
+        let format = "%l?s match %s:no matches.?f %f.";
+        assert_eq!(render_text(format, &values), "5no matches");
+
+        values.search_index = "2/9".to_string();
+        values.follow_output = true;
+        assert_eq!(render_text(format, &values), "5 match 2/9 [F]");
+
+        assert_eq!(render_text("?b\\?\\:\\..end", &values), "?:.end");
+        values.status = ("(END)".to_string(), ContentStyle::new());
+        assert_eq!(render_text("%l?e %e:none.", &values), "5 (END)");
+        assert_eq!(render_text("?sfound", &values), "found");
+    }
+
+    #[test]
+    fn styles() {
+        let mut values = values();
+        values.search_index = "1/2".to_string();
+        let theme = PromptTheme {
+            search_index: ContentStyle::new().red(),
+            ..PromptTheme::default()
+        };
+        assert_eq!(
+            render("%l %s", &values, &theme),
+            [
+                ("5 ".to_string(), theme.prompt),
+                ("1/2".to_string(), theme.search_index),
+            ]
+        );
+    }
+
+    #[test]
+    fn layout_fits_line() {
+        let style = ContentStyle::new();
+        let right = [("[F]".to_string(), style.bold())];
+        assert_eq!(
+            layout(vec![("prompt".to_string(), style)], &right, 12, style),
+            format!("prompt   {}", "[F]".bold())
+        );
+        // The left part is cut to leave room for the indicators
+        assert_eq!(
+            layout(vec![("long prompt".to_string(), style)], &right, 8, style),
+            format!("long {}", "[F]".bold())
+        );
+        // Nothing is written past the last column
+        assert_eq!(
+            layout(vec![("prompt".to_string(), style)], &right, 2, style),
+            format!("{}", "[F".bold())
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Saving the text of the pager to a file
+//!
+//! Piped input is lost once the pager quits. Like the `s` command of `less`, users can press `s`
+//! and type a file name to write the text of the current buffer to that file. To avoid overwriting
+//! files by accident, it refuses to replace a file that already exists. The outcome is reported at
+//! the prompt.
+//!
+//! Which part of the text is written and whether colors are kept is decided by the
+//! [`SaveOptions`] set with [`Pager::set_save_options`](crate::Pager::set_save_options). Since
+//! this lets users create files wherever the application can, applications that should not allow
+//! it can turn the command off with [`Pager::allow_saving`](crate::Pager::allow_saving).
+//!
+//! Applications can also save the text themselves with
+//! [`Pager::save_to_file`](crate::Pager::save_to_file).
+//!
+//! # Example
+//! ```
+//! use minus::{
+//!     save::{SaveOptions, SaveRegion},
+//!     Pager,
+//! };
+//!
+//! let pager = Pager::new();
+//! pager.push_str("\x1b[31mERROR\x1b[0m: disk full\n")?;
+//! // Save the text between the marks `a` and `b` without the colors
+//! pager.set_mark('a', 0);
+//! pager.set_mark('b', 0);
+//! let options = SaveOptions {
+//!     strip_ansi: true,
+//!     region: SaveRegion::Marks('a', 'b'),
+//! };
+//! pager.save_to_file("errors.log", options)?;
+//! # Ok::<(), minus::MinusError>(())
+//! ```
+use std::borrow::Cow;
+
+/// Part of the text that is saved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SaveRegion {
+    /// All of the text
+    #[default]
+    All,
+    /// Only the lines displayed with the current [filter](crate::Pager::set_filter)
+    ///
+    /// All of the text is saved if no filter is set.
+    #[cfg(feature = "search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
+    Filtered,
+    /// The lines from one [mark](crate::Pager::set_mark) up to another, including both of them
+    ///
+    /// The marks can be given in any order.
+    Marks(char, char),
+}
+
+/// Options for saving the text to a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// Remove the ANSI escape sequences, such as colors, from the text
+    pub strip_ansi: bool,
+    /// Part of the text that is saved
+    pub region: SaveRegion,
+}
+
+/// Remove the ANSI escape sequences from `line`
+pub(crate) fn strip_ansi(line: &str) -> Cow<'_, str> {
+    if !line.contains('\x1b') {
+        return Cow::Borrowed(line);
+    }
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Control sequences end with a character in the range `@` to `~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // Operating system commands end with BEL or `ESC \`
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Cow::Owned(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_ansi, SaveOptions, SaveRegion};
+    use crate::PagerState;
+
+    fn saved(ps: &PagerState, options: SaveOptions) -> String {
+        let mut out = Vec::new();
+        ps.write_saved_text(&mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn strips_escape_sequences() {
+        assert_eq!(strip_ansi("\x1b[1;31mred\x1b[0m text"), "red text");
+        assert_eq!(
+            strip_ansi("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07"),
+            "link"
+        );
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
+    #[test]
+    fn whole_text() {
+        let mut ps = PagerState::new().unwrap();
+        ps.append_str("\x1b[32mone\x1b[0m\ntwo");
+        assert_eq!(
+            saved(&ps, SaveOptions::default()),
+            "\x1b[32mone\x1b[0m\ntwo"
+        );
+        let options = SaveOptions {
+            strip_ansi: true,
+            ..SaveOptions::default()
+        };
+        ps.append_str("\n");
+        assert_eq!(saved(&ps, options), "one\ntwo\n");
+    }
+
+    #[test]
+    fn between_marks() {
+        let mut ps = PagerState::new().unwrap();
+        ps.append_str("a\nb\nc\nd\n");
+        ps.marks.lock().extend([('x', 2), ('y', 1)]);
+        let options = |region| SaveOptions {
+            strip_ansi: false,
+            region,
+        };
+        assert_eq!(saved(&ps, options(SaveRegion::Marks('x', 'y'))), "b\nc\n");
+
+        let mut out = Vec::new();
+        let err = ps
+            .write_saved_text(&mut out, options(SaveRegion::Marks('x', 'z')))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "mark 'z' is not set");
+    }
+
+    #[test]
+    #[cfg(feature = "search")]
+    fn filtered_lines() {
+        use crate::search::Filter;
+        use regex::Regex;
+
+        let mut ps = PagerState::new().unwrap();
+        ps.append_str("error 1\ninfo\nerror 2\n");
+        let options = SaveOptions {
+            strip_ansi: false,
+            region: SaveRegion::Filtered,
+        };
+        assert_eq!(saved(&ps, options), "error 1\ninfo\nerror 2\n");
+        ps.set_filter(Some(Filter::new(Regex::new("error").unwrap())));
+        assert_eq!(saved(&ps, options), "error 1\nerror 2\n");
+    }
+
+    #[test]
+    fn reports_outcome() {
+        let path = std::env::temp_dir().join(format!("minus-save-{}", std::process::id()));
+        let mut ps = PagerState::new().unwrap();
+        ps.append_str("a\nb\n");
+        ps.save_to_file(&path, SaveOptions::default(), true);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\n");
+        assert_eq!(
+            ps.message,
+            Some(format!("Saved 2 lines to {}", path.display()))
+        );
+
+        // Existing files are only replaced when asked to
+        ps.save_to_file(&path, SaveOptions::default(), false);
+        assert!(matches!(&ps.message, Some(m) if m.starts_with("Failed to save to")));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
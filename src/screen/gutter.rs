@@ -0,0 +1,282 @@
+//! Customizing the gutter displayed before each line
+//!
+//! When line numbers are turned on, each line is preceded by its number. The look of the line
+//! numbers can be changed by setting a [`Gutter`] with
+//! [`Pager::set_gutter`](crate::Pager::set_gutter). Besides the width, separator and styles of the
+//! numbers, the gutter can show the numbers relative to the line at the top of the screen, like
+//! the `relativenumber` option of Vim.
+//!
+//! Applications can also add a column of their own to the gutter with a [`GutterColumn`], for
+//! example to display the time at which each line was received or markers for changed lines.
+//! The column is displayed even when line numbers are turned off.
+//!
+//! # Example
+//! ```
+//! use crossterm::style::{ContentStyle, Stylize};
+//! use minus::{
+//!     screen::gutter::{Gutter, GutterColumn, NumberWidth},
+//!     LineNumbers, Pager,
+//! };
+//!
+//! let pager = Pager::new();
+//! pager.set_line_numbers(LineNumbers::Enabled)?;
+//! pager.set_gutter(Gutter {
+//!     width: NumberWidth::Fixed(6),
+//!     separator: " │ ".to_string(),
+//!     number_style: ContentStyle::new().dark_grey(),
+//!     relative: true,
+//!     // Mark the lines containing errors
+//!     column: Some(GutterColumn::new(2, |_idx, line| {
+//!         if line.contains("error") { "! " } else { "" }.to_string()
+//!     })),
+//!     ..Gutter::default()
+//! })?;
+//! # Ok::<(), minus::MinusError>(())
+//! ```
+use crossterm::style::{ContentStyle, Stylize};
+use std::{borrow::Cow, sync::Arc};
+
+use crate::{minus_core::utils::LinesRowMap, screen::Row};
+
+/// How many columns the line numbers take up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberWidth {
+    /// Wide enough for the number of the last line, with `padding` more columns before it
+    ///
+    /// The gutter gets wider as more lines are added to the text.
+    Fit {
+        /// Number of columns before the widest line number
+        padding: usize,
+    },
+    /// Always the given number of columns
+    ///
+    /// Numbers that need more columns push the rest of the row to the right.
+    Fixed(usize),
+}
+
+/// Extra column of the gutter, filled in by the application
+///
+/// See the [module level documentation](self) for more info.
+#[derive(Clone)]
+pub struct GutterColumn {
+    width: usize,
+    #[allow(clippy::type_complexity)]
+    text: Arc<dyn Fn(usize, &str) -> String + Send + Sync>,
+}
+
+impl GutterColumn {
+    /// Create a column that is `width` columns wide
+    ///
+    /// `text` is called with the index and the text of each line when the line is formatted for
+    /// display, and returns the text displayed in the column next to the first row of that line.
+    /// Text that is narrower than `width` is padded with spaces. The text can contain ANSI escape
+    /// sequences but must not be wider than `width` or contain newlines.
+    pub fn new<F>(width: usize, text: F) -> Self
+    where
+        F: Fn(usize, &str) -> String + Send + Sync + 'static,
+    {
+        Self {
+            width,
+            text: Arc::new(text),
+        }
+    }
+
+    /// Returns the number of columns that the column takes up
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+}
+
+impl std::fmt::Debug for GutterColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GutterColumn")
+            .field("width", &self.width)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Layout and styles of the gutter
+///
+/// The gutter consists of the line number, followed by the separator and the extra
+/// [`column`](Gutter::column). The default gutter displays bold numbers followed by a `.`, like
+/// `  12. `.
+#[derive(Debug, Clone)]
+pub struct Gutter {
+    /// How many columns the line numbers take up
+    pub width: NumberWidth,
+    /// Text displayed between the line number and the rest of the row
+    pub separator: String,
+    /// Style of the line numbers
+    pub number_style: ContentStyle,
+    /// Style of the separator
+    pub separator_style: ContentStyle,
+    /// Whether to display the distance of each line from the line at the top of the screen
+    /// instead of its number
+    ///
+    /// The line at the top still displays its own number.
+    pub relative: bool,
+    /// Extra column displayed after the separator
+    pub column: Option<GutterColumn>,
+}
+
+impl Default for Gutter {
+    fn default() -> Self {
+        Self {
+            width: NumberWidth::Fit { padding: 5 },
+            separator: ". ".to_string(),
+            // If minus is run under test, the numbers are not made bold because the escape
+            // sequences add extra difficulty while writing tests
+            number_style: if cfg!(test) {
+                ContentStyle::new()
+            } else {
+                ContentStyle::new().bold()
+            },
+            separator_style: ContentStyle::new(),
+            relative: false,
+            column: None,
+        }
+    }
+}
+
+impl Gutter {
+    /// Number of columns taken up by the line numbers when the text has `digits` digit long line
+    /// numbers
+    pub(crate) const fn number_width(&self, digits: usize) -> usize {
+        match self.width {
+            NumberWidth::Fit { padding } => digits + padding,
+            NumberWidth::Fixed(width) => width,
+        }
+    }
+
+    /// Total number of columns taken up by the gutter
+    pub(crate) fn width(&self, line_numbers: bool, digits: usize) -> usize {
+        let numbers = if line_numbers {
+            self.number_width(digits) + textwrap::core::display_width(&self.separator)
+        } else {
+            0
+        };
+        numbers + self.column.as_ref().map_or(0, GutterColumn::width)
+    }
+
+    /// Gutter of a row of the line `line` at index `idx`
+    ///
+    /// Only the first row of a line gets the line number and the extra column, the other rows are
+    /// padded with spaces. In relative mode, the number is left blank to be filled in by
+    /// [`Gutter::fill_relative`] when the rows are displayed.
+    pub(crate) fn render(
+        &self,
+        line_numbers: bool,
+        digits: usize,
+        idx: usize,
+        line: &str,
+        is_first_row: bool,
+    ) -> String {
+        let mut gutter = String::new();
+        if line_numbers {
+            let width = self.number_width(digits);
+            if is_first_row && !self.relative {
+                gutter.push_str(&self.number(idx + 1, width));
+            } else {
+                gutter.push_str(&" ".repeat(width));
+            }
+            if is_first_row {
+                gutter.push_str(&self.separator_style.apply(&self.separator).to_string());
+            } else {
+                gutter.push_str(&" ".repeat(textwrap::core::display_width(&self.separator)));
+            }
+        }
+        if let Some(column) = &self.column {
+            let text = if is_first_row {
+                (column.text)(idx, line)
+            } else {
+                String::new()
+            };
+            let padding = column
+                .width
+                .saturating_sub(textwrap::core::display_width(&text));
+            gutter.push_str(&text);
+            gutter.push_str(&" ".repeat(padding));
+        }
+        gutter
+    }
+
+    /// `number` right aligned in `width` columns
+    fn number(&self, number: usize, width: usize) -> String {
+        self.number_style
+            .apply(format!("{number: >width$}"))
+            .to_string()
+    }
+
+    /// Fill in the relative line numbers of `rows`, the first of which is the row at `upper_mark`
+    ///
+    /// The numbers are relative to the line at `upper_mark`, which itself is numbered after
+    /// `line_offset` dropped lines. `rows` are returned unchanged if relative line numbers are
+    /// not turned on.
+    pub(crate) fn fill_relative<'a>(
+        &self,
+        rows: &'a [Row],
+        upper_mark: usize,
+        line_numbers: bool,
+        lines_to_row_map: &LinesRowMap,
+        digits: usize,
+        line_offset: usize,
+    ) -> Cow<'a, [Row]> {
+        if !line_numbers || !self.relative {
+            return Cow::Borrowed(rows);
+        }
+        let width = self.number_width(digits);
+        let top_line = lines_to_row_map.line_of_row(upper_mark);
+        let numbered = rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let line = lines_to_row_map.line_of_row(upper_mark + i);
+                let is_first_row = lines_to_row_map.get(line) == Some(&(upper_mark + i));
+                // The blank number is made up of `width` spaces
+                match row.get(width..) {
+                    Some(rest) if is_first_row => {
+                        let number = if line == top_line {
+                            line_offset + line + 1
+                        } else {
+                            line.abs_diff(top_line)
+                        };
+                        self.number(number, width) + rest
+                    }
+                    _ => row.clone(),
+                }
+            })
+            .collect();
+        Cow::Owned(numbered)
+    }
+}
+
+/// Split `row` after the gutter that is `width` columns wide
+///
+/// The escape sequences right after the gutter are kept with it.
+pub(crate) fn split_gutter(row: &str, width: usize) -> (&str, &str) {
+    if width == 0 {
+        return ("", row);
+    }
+    let mut taken = 0;
+    let mut chars = row.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\x1b' {
+            // Skip over the whole escape sequence
+            if matches!(chars.peek(), Some((_, '['))) {
+                chars.next();
+                for (_, c) in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        if taken >= width {
+            return row.split_at(i);
+        }
+        taken += textwrap::core::display_width(c.encode_utf8(&mut [0; 4]));
+    }
+    (row, "")
+}
@@ -2,17 +2,26 @@
 //!
 //! This module is still a work is progress and is subject to change.
 use crate::{
-    minus_core::{self, utils::LinesRowMap},
+    minus_core::{
+        self,
+        utils::{syntax::SyntaxHighlighting, LinesRowMap},
+    },
     LineNumbers,
 };
 #[cfg(feature = "search")]
 use regex::Regex;
 
-use std::borrow::Cow;
+use std::{borrow::Cow, io, ops::Range};
 
 #[cfg(feature = "search")]
 use {crate::search, std::collections::BTreeSet};
 
+pub mod gutter;
+pub mod storage;
+pub mod syntax;
+use gutter::Gutter;
+use storage::{MemoryStore, TextStore};
+
 // |||||||||||||||||||||||||||||||||||||||||||||||||||||||
 //  TYPES TO BETTER DESCRIBE THE PURPOSE OF STRINGS
 // |||||||||||||||||||||||||||||||||||||||||||||||||||||||
@@ -34,9 +43,27 @@ pub type OwnedTextBlock = String;
 /// Most of the functions of this type are cheap as minus does a lot of caching of the analysis
 /// behind the scenes
 pub struct Screen {
-    pub(crate) orig_text: OwnedTextBlock,
+    /// Storage for the original text
+    pub(crate) store: Box<dyn TextStore>,
+    /// Formatted rows that are currently kept in memory
+    ///
+    /// These are the rows starting from [`Screen::window_start`]. Unless
+    /// [`Screen::max_formatted_rows`] is set, this contains all of the rows.
     pub(crate) formatted_lines: Rows,
+    /// Index of the row at which [`Screen::formatted_lines`] starts
+    pub(crate) window_start: usize,
+    /// Total number of rows that the text occupies
+    pub(crate) rows_count: usize,
+    /// Maximum number of formatted rows to keep in memory
+    pub(crate) max_formatted_rows: Option<usize>,
+    /// Maximum number of lines to keep, the oldest lines are dropped beyond this
+    pub(crate) max_lines: Option<usize>,
     pub(crate) line_count: usize,
+    /// Number of lines dropped from the start of the text due to [`Screen::max_lines`]
+    ///
+    /// The line numbers of the remaining lines are offset by this so that they keep their
+    /// numbers.
+    pub(crate) dropped_lines: usize,
     pub(crate) max_line_length: usize,
     /// Unterminated lines
     /// Keeps track of the number of lines at the last of [PagerState::formatted_lines] which are
@@ -46,14 +73,21 @@ pub struct Screen {
     ///
     /// Its negation gives the state of whether horizontal scrolling is allowed.
     pub(crate) line_wrapping: bool,
+    /// Filter deciding which lines are displayed
+    #[cfg(feature = "search")]
+    pub(crate) filter: Option<search::Filter>,
+    /// Highlighter for the syntax of the text
+    pub(crate) syntax: Option<SyntaxHighlighting>,
+    /// Layout of the line numbers and the extra column before each line
+    pub(crate) gutter: Gutter,
 }
 
 impl Screen {
     /// Get the actual number of physical rows that the text that will actually occupy on the
     /// terminal
     #[must_use]
-    pub fn formatted_lines_count(&self) -> usize {
-        self.formatted_lines.len()
+    pub const fn formatted_lines_count(&self) -> usize {
+        self.rows_count
     }
     /// Get the number of [`Lines`](std::str::Lines) in the text.
     #[must_use]
@@ -61,22 +95,137 @@ impl Screen {
         self.line_count
     }
     /// Returns all the [Rows] within the bounds
+    ///
+    /// Only the rows that are kept in memory are returned. Use
+    /// [`PagerState::load_rows`](crate::PagerState::load_rows) beforehand to make sure that all
+    /// of them are available.
     pub(crate) fn get_formatted_lines_with_bounds(&self, start: usize, end: usize) -> &[Row] {
-        if start >= self.formatted_lines_count() || start > end {
+        let window_end = self.window_start + self.formatted_lines.len();
+        let start = start.max(self.window_start);
+        let end = end.min(window_end);
+        if start >= end {
             &[]
-        } else if end >= self.formatted_lines_count() {
-            &self.formatted_lines[start..]
         } else {
-            &self.formatted_lines[start..end]
+            &self.formatted_lines[start - self.window_start..end - self.window_start]
         }
     }
 
+    /// Returns `true` if all the rows from `start` up to `end` are kept in memory
+    pub(crate) fn has_rows(&self, start: usize, end: usize) -> bool {
+        let end = end.min(self.rows_count);
+        start >= end
+            || (start >= self.window_start && end <= self.window_start + self.formatted_lines.len())
+    }
+
+    /// Range of rows that should be kept in memory when displaying `rows` rows from `start`
+    pub(crate) fn window_around(&self, start: usize, rows: usize) -> Range<usize> {
+        self.max_formatted_rows.map_or(0..usize::MAX, |max| {
+            let max = max.max(rows);
+            let from = start.saturating_sub((max - rows) / 2);
+            from..from.saturating_add(max)
+        })
+    }
+
+    /// Drop the formatted rows that are not needed for displaying `rows` rows from `start`
+    pub(crate) fn trim_window(&mut self, start: usize, rows: usize) {
+        let keep = self.window_around(start, rows);
+        let window_end = self.window_start + self.formatted_lines.len();
+        if keep.start <= self.window_start && keep.end >= window_end {
+            return;
+        }
+        // Keep as many rows as allowed, preferring the ones closer to `start`
+        let len = keep.end - keep.start;
+        let from = keep.start.clamp(
+            self.window_start,
+            window_end.saturating_sub(len).max(self.window_start),
+        );
+        let to = from.saturating_add(len).min(window_end);
+        self.formatted_lines.truncate(to - self.window_start);
+        self.formatted_lines.drain(..from - self.window_start);
+        self.window_start = from;
+    }
+
+    /// Drop the first `lines` lines of the text which occupy the first `rows` rows
+    pub(crate) fn drop_lines(&mut self, lines: usize, rows: usize) -> io::Result<()> {
+        if let Some(syntax) = &mut self.syntax {
+            syntax.drop_lines(&*self.store, lines)?;
+        }
+        self.store.drop_lines(lines)?;
+        self.dropped_lines += lines.min(self.line_count);
+        self.line_count = self.line_count.saturating_sub(lines);
+        self.rows_count = self.rows_count.saturating_sub(rows);
+        let dropped_window = rows
+            .saturating_sub(self.window_start)
+            .min(self.formatted_lines.len());
+        self.formatted_lines.drain(..dropped_window);
+        self.window_start = self.window_start.saturating_sub(rows);
+        Ok(())
+    }
+
+    /// Number of digits in the number of the last line
+    pub(crate) const fn line_number_digits(&self) -> usize {
+        minus_core::utils::digits(self.dropped_lines + self.line_count)
+    }
+
+    /// Number of columns taken up by the gutter before each row
+    pub(crate) fn gutter_width(&self, line_numbers: LineNumbers) -> usize {
+        self.gutter
+            .width(line_numbers.is_on(), self.line_number_digits())
+    }
+
     /// Get the length of the longest [Line] in the text.
     #[must_use]
     pub const fn get_max_line_length(&self) -> usize {
         self.max_line_length
     }
 
+    /// Replace the text with `text`
+    pub(crate) fn set_text(&mut self, text: &str) -> io::Result<()> {
+        if let Some(syntax) = &mut self.syntax {
+            syntax.clear();
+        }
+        self.store.clear()?;
+        self.dropped_lines = 0;
+        self.store.push_str(text)
+    }
+
+    /// Returns the text as a string
+    ///
+    /// Line endings are normalized to `\n`.
+    #[cfg(test)]
+    pub(crate) fn orig_text(&self) -> String {
+        let mut text = self
+            .store
+            .lines_from(0)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if self.store.line_count() > 0 && self.store.is_terminated() {
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Write the original text to `out`
+    ///
+    /// A line break is written after the last line only if the text ends with one.
+    #[cfg(feature = "static_output")]
+    pub(crate) fn write_text(
+        &self,
+        out: &mut impl std::io::Write,
+    ) -> Result<(), crate::error::MinusError> {
+        for (i, line) in self.store.lines_from(0).enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            write!(out, "{}", line.map_err(crate::error::MinusError::Storage)?)?;
+        }
+        if self.store.line_count() > 0 && self.store.is_terminated() {
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
     /// Insert the text into the []
     pub(crate) fn push_screen_buf(
         &mut self,
@@ -84,49 +233,85 @@ impl Screen {
         line_numbers: LineNumbers,
         cols: u16,
         #[cfg(feature = "search")] search_term: &Option<Regex>,
-    ) -> FormatResult {
-        // If the last line of self.screen.orig_text is not terminated by than the first line of
+        #[cfg(feature = "search")] highlights: &[search::Highlight],
+    ) -> io::Result<FormatResult> {
+        // If the last line of the stored text is not terminated by than the first line of
         // the incoming text is part of that line so we also need to take care of that.
         //
-        // Appropriately in that case we set the last lne of self.screen.orig_text as attachment
+        // Appropriately in that case we set the last lne of the stored text as attachment
         // text for the FormatOpts.
-        let clean_append = self.orig_text.ends_with('\n') || self.orig_text.is_empty();
+        let clean_append = self.store.is_terminated();
+        let attachment = if clean_append {
+            None
+        } else {
+            self.store
+                .lines_from(self.store.line_count().saturating_sub(1))
+                .next()
+                .transpose()?
+                .map(Cow::into_owned)
+        };
+        if let Some(syntax) = &mut self.syntax {
+            let first_line = self.line_count.saturating_sub(usize::from(!clean_append));
+            syntax.seek(&*self.store, first_line)?;
+        }
+        self.store.push_str(text)?;
+
         // We check if number of digits in current line count change during this text push.
         let old_lc = self.line_count();
 
         // Conditionally appends to [`self.formatted_lines`] or changes the last unterminated rows of
         // [`self.formatted_lines`]
         //
-        // `num_unterminated` is the current number of lines returned by [`self.make_append_str`]
-        // that should be truncated from [`self.formatted_lines`] to update the last line
-        self.formatted_lines
-            .truncate(self.formatted_lines.len() - self.unterminated);
-
-        let append_props = {
-            let attachment = if clean_append {
-                None
+        // `self.unterminated` is the current number of rows at the end that belong to the last line.
+        // These are formatted again along with the incoming text.
+        //
+        // The rows are formatted into the window only if it reaches up to the last row,
+        // otherwise they are dropped after being counted
+        let formatted_lines_count = self.rows_count - self.unterminated;
+        let into_window = self.window_start + self.formatted_lines.len() == self.rows_count;
+        let mut dropped_rows = Vec::new();
+        let buffer = if into_window {
+            if self.window_start > formatted_lines_count {
+                self.formatted_lines.clear();
+                self.window_start = formatted_lines_count;
             } else {
-                self.orig_text.lines().last()
-            };
-
-            let formatted_lines_count = self.formatted_lines.len();
+                self.formatted_lines
+                    .truncate(formatted_lines_count - self.window_start);
+            }
+            &mut self.formatted_lines
+        } else {
+            &mut dropped_rows
+        };
 
+        let append_props = {
             let append_opts = FormatOpts {
-                buffer: &mut self.formatted_lines,
+                buffer: &mut *buffer,
                 text,
-                attachment,
+                attachment: attachment.as_deref(),
                 line_numbers,
-                formatted_lines_count,
+                gutter: &self.gutter,
+                line_offset: self.dropped_lines,
+                // The rows of the attachment are subtracted by `format_text_block` itself
+                formatted_lines_count: self.rows_count,
                 lines_count: old_lc,
                 prev_unterminated: self.unterminated,
                 cols: cols.into(),
                 line_wrapping: self.line_wrapping,
                 #[cfg(feature = "search")]
                 search_term,
+                #[cfg(feature = "search")]
+                filter: self.filter.as_ref(),
+                #[cfg(feature = "search")]
+                highlights,
+                syntax: self.syntax.as_mut(),
             };
             format_text_block(append_opts)
         };
-        self.orig_text.push_str(text);
+        self.rows_count = if into_window {
+            self.window_start + self.formatted_lines.len()
+        } else {
+            formatted_lines_count + dropped_rows.len()
+        };
 
         let (num_unterminated, lines_formatted, max_line_length) = (
             append_props.num_unterminated,
@@ -140,7 +325,7 @@ impl Screen {
         }
 
         self.unterminated = num_unterminated;
-        append_props
+        Ok(append_props)
     }
 }
 
@@ -148,11 +333,20 @@ impl Default for Screen {
     fn default() -> Self {
         Self {
             line_wrapping: true,
-            orig_text: String::with_capacity(100 * 1024),
-            formatted_lines: Vec::with_capacity(500 * 1024),
+            store: Box::<MemoryStore>::default(),
+            formatted_lines: Vec::new(),
+            window_start: 0,
+            rows_count: 0,
+            max_formatted_rows: None,
+            max_lines: None,
             line_count: 0,
+            dropped_lines: 0,
             max_line_length: 0,
             unterminated: 0,
+            #[cfg(feature = "search")]
+            filter: None,
+            syntax: None,
+            gutter: Gutter::default(),
         }
     }
 }
@@ -246,6 +440,11 @@ where
     pub attachment: Option<TextBlock<'a>>,
     /// Status of line numbers
     pub line_numbers: LineNumbers,
+    /// Layout of the line numbers and the extra column before each line
+    pub gutter: &'a Gutter,
+    /// Number of lines dropped before the first line of the text, which is added to the line
+    /// numbers
+    pub line_offset: usize,
     /// This is equal to the number of lines in [`PagerState::lines`](crate::state::PagerState::lines). This basically tells what line
     /// number the upcoming line will hold.
     pub lines_count: usize,
@@ -260,6 +459,14 @@ where
     /// Search term if a search is active
     #[cfg(feature = "search")]
     pub search_term: &'a Option<regex::Regex>,
+    /// Filter deciding which lines are displayed
+    #[cfg(feature = "search")]
+    pub filter: Option<&'a search::Filter>,
+    /// Highlights applied to the matching text
+    #[cfg(feature = "search")]
+    pub highlights: &'a [search::Highlight],
+    /// Highlighter for the syntax, positioned at the first line of `text` or `attachment`
+    pub syntax: Option<&'a mut SyntaxHighlighting>,
 
     /// Value of [PagerState::line_wrapping]
     pub line_wrapping: bool,
@@ -377,7 +584,8 @@ where
         clean_append: opts.attachment.is_none(),
     };
 
-    let line_number_digits = minus_core::utils::digits(opts.lines_count + to_format_size);
+    let line_number_digits =
+        minus_core::utils::digits(opts.line_offset + opts.lines_count + to_format_size);
 
     // Return if we have nothing to format
     if lines.is_empty() {
@@ -390,22 +598,33 @@ where
 
     {
         let line_numbers = opts.line_numbers;
+        let gutter = opts.gutter;
         let cols = opts.cols;
         let lines_count = opts.lines_count;
+        let line_offset = opts.line_offset;
         let line_wrapping = opts.line_wrapping;
         #[cfg(feature = "search")]
         let search_term = opts.search_term;
+        #[cfg(feature = "search")]
+        let filter = opts.filter;
+        #[cfg(feature = "search")]
+        let highlights = opts.highlights;
+        let syntax = &mut opts.syntax;
 
         let rest_lines =
             lines
                 .iter()
                 .take(lines.len().saturating_sub(1))
                 .flat_map(|(idx, line)| {
+                    let highlighted = syntax.as_deref_mut().map_or(Cow::Borrowed(*line), |s| {
+                        s.highlight_line(lines_count + idx, line, false)
+                    });
                     let fmt_line = formatted_line(
-                        line,
+                        &highlighted,
                         line_number_digits,
-                        lines_count + idx,
+                        line_offset + lines_count + idx,
                         line_numbers,
+                        gutter,
                         cols,
                         line_wrapping,
                         #[cfg(feature = "search")]
@@ -414,6 +633,10 @@ where
                         &mut fr.append_search_idx,
                         #[cfg(feature = "search")]
                         search_term,
+                        #[cfg(feature = "search")]
+                        filter,
+                        #[cfg(feature = "search")]
+                        highlights,
                     );
                     fr.lines_to_row_map.insert(formatted_row_count, true);
                     formatted_row_count += fmt_line.len();
@@ -426,11 +649,20 @@ where
         opts.buffer.extend_buffer(rest_lines);
     };
 
+    let last_idx = opts.lines_count + to_format_size - 1;
+    let last_text = lines.last().unwrap().1;
+    let highlighted = opts
+        .syntax
+        .as_deref_mut()
+        .map_or(Cow::Borrowed(last_text), |s| {
+            s.highlight_line(last_idx, last_text, true)
+        });
     let mut last_line = formatted_line(
-        lines.last().unwrap().1,
+        &highlighted,
         line_number_digits,
-        opts.lines_count + to_format_size - 1,
+        opts.line_offset + last_idx,
         opts.line_numbers,
+        opts.gutter,
         opts.cols,
         opts.line_wrapping,
         #[cfg(feature = "search")]
@@ -439,39 +671,16 @@ where
         &mut fr.append_search_idx,
         #[cfg(feature = "search")]
         opts.search_term,
+        #[cfg(feature = "search")]
+        opts.filter,
+        #[cfg(feature = "search")]
+        opts.highlights,
     );
     fr.lines_to_row_map.insert(formatted_row_count, true);
     if lines.last().unwrap().1.len() > fr.max_line_length {
         fr.max_line_length = lines.last().unwrap().1.len();
     }
 
-    #[cfg(feature = "search")]
-    {
-        // NOTE: VERY IMPORTANT BLOCK TO GET PROPER SEARCH INDEX
-        // Here is the current scenario: suppose you have text block like this (markers are present to denote where a
-        // new line begins).
-        //
-        // * This is line one row one
-        //   This is line one row two
-        //   This is line one row three
-        // * This is line two row one
-        //   This is line two row two
-        //   This is line two row three
-        //   This is line two row four
-        //
-        // and suppose a match is found at line 1 row 2 and line 2 row 4. So the index generated will be [1, 6].
-        // Let's say this text block is going to be appended to [PagerState::formatted_lines] from index 23.
-        // Now if directly append this generated index to [`PagerState::search_idx`], it will probably be wrong
-        // as these numbers are *relative to current text block*. The actual search index should have been 24, 30.
-        //
-        // To fix this we basically add the number of items in [`PagerState::formatted_lines`].
-        fr.append_search_idx = fr
-            .append_search_idx
-            .iter()
-            .map(|i| opts.formatted_lines_count + i)
-            .collect();
-    }
-
     // Calculate number of rows which are part of last line and are left unterminated  due to absence of \n
     fr.num_unterminated = if opts.text.ends_with('\n') {
         // If the last line ends with \n, then the line is complete so nothing is left as unterminated
@@ -479,16 +688,58 @@ where
     } else {
         last_line.len()
     };
+    formatted_row_count += last_line.len();
     opts.buffer.append_to_buffer(&mut last_line);
     fr.rows_formatted = formatted_row_count - opts.formatted_lines_count;
 
     fr
 }
 
+/// Number of rows that `line` takes up once formatted by [`formatted_line`]
+///
+/// The rows themselves are not built, which makes this much cheaper than formatting the line.
+/// The search matches are still added to `search_idx`.
+#[allow(clippy::too_many_arguments)]
+fn line_row_count(
+    line: &str,
+    len_line_number: usize,
+    line_numbers: LineNumbers,
+    gutter: &Gutter,
+    cols: usize,
+    line_wrapping: bool,
+    #[cfg(feature = "search")] formatted_idx: usize,
+    #[cfg(feature = "search")] search_idx: &mut BTreeSet<usize>,
+    #[cfg(feature = "search")] search_term: Option<&regex::Regex>,
+    #[cfg(feature = "search")] filter: Option<&search::Filter>,
+) -> usize {
+    #[cfg(feature = "search")]
+    if matches!(filter, Some(f) if !f.shows(line)) {
+        return 0;
+    }
+    let line_numbers = matches!(line_numbers, LineNumbers::Enabled | LineNumbers::AlwaysOn);
+    let rows = if line_wrapping {
+        let cols_avail = cols.saturating_sub(gutter.width(line_numbers, len_line_number));
+        textwrap::wrap(line, cols_avail)
+    } else {
+        vec![Cow::from(line)]
+    };
+
+    #[cfg(feature = "search")]
+    if let Some(st) = search_term {
+        for (wrap_idx, row) in rows.iter().enumerate() {
+            if search::line_matches(row, st) {
+                search_idx.insert(formatted_idx + wrap_idx);
+            }
+        }
+    }
+    rows.len()
+}
+
 /// Formats the given `line`
 ///
 /// - `line`: The line to format
 /// - `line_numbers`: tells whether to format the line with line numbers.
+/// - `gutter`: Layout of the line numbers and the extra column before the line
 /// - `len_line_number`: is the number of digits that number of lines in [`PagerState::lines`] occupy.
 ///     For example, this will be 2 if number of lines in [`PagerState::lines`] is 50 and 3 if
 ///     number of lines in [`PagerState::lines`] is 500. This is used for calculating the padding
@@ -498,6 +749,8 @@ where
 ///    [`PagerState::formatted_lines`](crate::state::PagerState::formatted_lines)
 /// - `cols`: Number of columns in the terminal
 /// - `search_term`: Contains the regex if a search is active
+/// - `filter`: Contains the filter if one is active. No rows are returned if it hides the line.
+/// - `highlights`: Highlights to apply to the matching text before the search matches
 ///
 /// [`PagerState::lines`]: crate::state::PagerState::lines
 #[allow(clippy::too_many_arguments)]
@@ -507,33 +760,30 @@ pub(crate) fn formatted_line<'a>(
     len_line_number: usize,
     idx: usize,
     line_numbers: LineNumbers,
+    gutter: &Gutter,
     cols: usize,
     line_wrapping: bool,
     #[cfg(feature = "search")] formatted_idx: usize,
     #[cfg(feature = "search")] search_idx: &mut BTreeSet<usize>,
     #[cfg(feature = "search")] search_term: &Option<regex::Regex>,
+    #[cfg(feature = "search")] filter: Option<&search::Filter>,
+    #[cfg(feature = "search")] highlights: &[search::Highlight],
 ) -> Rows {
     assert!(
         !line.contains('\n'),
         "Newlines found in appending line {:?}",
         line
     );
+    #[cfg(feature = "search")]
+    if matches!(filter, Some(f) if !f.shows(line)) {
+        return Vec::new();
+    }
     let line_numbers = matches!(line_numbers, LineNumbers::Enabled | LineNumbers::AlwaysOn);
+    let has_gutter = line_numbers || gutter.column.is_some();
 
-    // NOTE: Only relevant when line numbers are active
-    // Padding is the space that the actual line text will be shifted to accommodate for
-    // line numbers. This is equal to:-
-    // LineNumbers::EXTRA_PADDING + len_line_number + 1 (for '.') + 1 (for 1 space)
-    //
-    // We reduce this from the number of available columns as this space cannot be used for
-    // actual line display when wrapping the lines
-    let padding = len_line_number + LineNumbers::EXTRA_PADDING + 1;
-
-    let cols_avail = if line_numbers {
-        cols.saturating_sub(padding + 2)
-    } else {
-        cols
-    };
+    // The gutter takes up some of the columns, which cannot be used for the actual line display
+    // when wrapping the lines
+    let cols_avail = cols.saturating_sub(gutter.width(line_numbers, len_line_number));
 
     // Wrap the line and return an iterator over all the rows
     let mut enumerated_rows = if line_wrapping {
@@ -549,6 +799,12 @@ pub(crate) fn formatted_line<'a>(
     #[cfg_attr(not(feature = "search"), allow(unused_mut))]
     #[cfg_attr(not(feature = "search"), allow(unused_variables))]
     let mut handle_search = |row: &mut Cow<'a, str>, wrap_idx: usize| {
+        #[cfg(feature = "search")]
+        for highlight in highlights {
+            if let Some(highlighted_row) = highlight.apply(row) {
+                *row.to_mut() = highlighted_row;
+            }
+        }
         #[cfg(feature = "search")]
         if let Some(st) = search_term.as_ref() {
             let (highlighted_row, is_match) = search::highlight_line_matches(row, st, false);
@@ -559,34 +815,16 @@ pub(crate) fn formatted_line<'a>(
         }
     };
 
-    if line_numbers {
+    if has_gutter {
         let mut formatted_rows = Vec::with_capacity(256);
 
-        // Formatter for only when line numbers are active
-        // * If minus is run under test, ascii codes for making the numbers bol is not inserted because they add
-        // extra difficulty while writing tests
-        // * Line number is added only to the first row of a line. This makes a better UI overall
-        let formatter = |row: Cow<'_, str>, is_first_row: bool, idx: usize| {
-            format!(
-                "{bold}{number: >len$}{reset} {row}",
-                bold = if cfg!(not(test)) && is_first_row {
-                    crossterm::style::Attribute::Bold.to_string()
-                } else {
-                    String::new()
-                },
-                number = if is_first_row {
-                    (idx + 1).to_string() + "."
-                } else {
-                    String::new()
-                },
-                len = padding,
-                reset = if cfg!(not(test)) && is_first_row {
-                    crossterm::style::Attribute::Reset.to_string()
-                } else {
-                    String::new()
-                },
-                row = row
-            )
+        // The line number and the extra column are added only to the first row of a line. This
+        // makes a better UI overall
+        let formatter = |row: Cow<'_, str>, is_first_row: bool| {
+            let mut formatted =
+                gutter.render(line_numbers, len_line_number, idx, line, is_first_row);
+            formatted.push_str(&row);
+            formatted
         };
 
         // First format the first row separate from other rows, then the subsequent rows and finally join them
@@ -595,7 +833,7 @@ pub(crate) fn formatted_line<'a>(
             #[cfg_attr(not(feature = "search"), allow(unused_mut))]
             let mut row = enumerated_rows.next().unwrap().1;
             handle_search(&mut row, 0);
-            formatter(row, true, idx)
+            formatter(row, true)
         };
         formatted_rows.push(first_row);
 
@@ -603,7 +841,7 @@ pub(crate) fn formatted_line<'a>(
         #[cfg_attr(not(feature = "search"), allow(unused_variables))]
         let rows_left = enumerated_rows.map(|(wrap_idx, mut row)| {
             handle_search(&mut row, wrap_idx);
-            formatter(row, false, 0)
+            formatter(row, false)
         });
         formatted_rows.extend(rows_left);
 
@@ -620,29 +858,117 @@ pub(crate) fn formatted_line<'a>(
     }
 }
 
+/// Formats all the lines inside `store`, the first of which is numbered after `line_offset`
+/// dropped lines
+///
+/// Only the rows of the lines that overlap with `keep` are returned along with the index of the
+/// first returned row. The other lines are only wrapped to count their rows for the
+/// [`FormatResult`].
+///
+/// # Errors
+/// Returns an error if the text could not be read from `store`
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn make_format_lines(
-    text: &String,
+    store: &dyn TextStore,
     line_numbers: LineNumbers,
+    gutter: &Gutter,
+    line_offset: usize,
     cols: usize,
     line_wrapping: bool,
     #[cfg(feature = "search")] search_term: &Option<regex::Regex>,
-) -> (Rows, FormatResult) {
+    #[cfg(feature = "search")] filter: Option<&search::Filter>,
+    #[cfg(feature = "search")] highlights: &[search::Highlight],
+    mut syntax: Option<&mut SyntaxHighlighting>,
+    keep: Range<usize>,
+) -> io::Result<(Rows, usize, FormatResult)> {
+    let line_count = store.line_count();
+    let line_number_digits = minus_core::utils::digits(line_offset + line_count);
     let mut buffer = Vec::with_capacity(256);
-    let format_opts = FormatOpts {
-        buffer: &mut buffer,
-        text,
-        attachment: None,
-        line_numbers,
-        formatted_lines_count: 0,
-        lines_count: 0,
-        prev_unterminated: 0,
-        cols,
+    let mut buffer_start = None;
+
+    let mut fr = FormatResult {
+        lines_formatted: line_count,
+        rows_formatted: 0,
+        num_unterminated: 0,
         #[cfg(feature = "search")]
-        search_term,
-        line_wrapping,
+        append_search_idx: BTreeSet::new(),
+        lines_to_row_map: LinesRowMap::new(),
+        max_line_length: 0,
+        clean_append: true,
     };
-    let fr = format_text_block(format_opts);
-    (buffer, fr)
+
+    if let Some(syntax) = syntax.as_deref_mut() {
+        syntax.reset();
+    }
+    for (idx, line) in store.lines_from(0).enumerate() {
+        let line = line?;
+        let highlighted = syntax.as_deref_mut().map_or(Cow::Borrowed(&*line), |s| {
+            s.highlight_line(idx, &line, idx + 1 == line_count)
+        });
+        let row_start = fr.rows_formatted;
+        let outside = |row_count| row_start + row_count <= keep.start || row_start >= keep.end;
+
+        // Lines that may be outside of `keep` are counted first so that their rows are only
+        // built when needed
+        let counted = if row_start < keep.start || row_start >= keep.end {
+            let row_count = line_row_count(
+                &highlighted,
+                line_number_digits,
+                line_numbers,
+                gutter,
+                cols,
+                line_wrapping,
+                #[cfg(feature = "search")]
+                row_start,
+                #[cfg(feature = "search")]
+                &mut fr.append_search_idx,
+                #[cfg(feature = "search")]
+                search_term.as_ref(),
+                #[cfg(feature = "search")]
+                filter,
+            );
+            Some(row_count).filter(|&row_count| outside(row_count))
+        } else {
+            None
+        };
+        let row_count = counted.unwrap_or_else(|| {
+            let rows = formatted_line(
+                &highlighted,
+                line_number_digits,
+                line_offset + idx,
+                line_numbers,
+                gutter,
+                cols,
+                line_wrapping,
+                #[cfg(feature = "search")]
+                row_start,
+                #[cfg(feature = "search")]
+                &mut fr.append_search_idx,
+                #[cfg(feature = "search")]
+                search_term,
+                #[cfg(feature = "search")]
+                filter,
+                #[cfg(feature = "search")]
+                highlights,
+            );
+            let row_count = rows.len();
+            if !outside(row_count) {
+                buffer_start.get_or_insert(row_start);
+                buffer.extend(rows);
+            }
+            row_count
+        });
+        fr.lines_to_row_map.insert(row_start, true);
+        fr.max_line_length = fr.max_line_length.max(line.len());
+        fr.num_unterminated = row_count;
+        fr.rows_formatted = row_start + row_count;
+    }
+
+    if store.is_terminated() {
+        fr.num_unterminated = 0;
+    }
+    let buffer_start = buffer_start.unwrap_or(fr.rows_formatted);
+    Ok((buffer, buffer_start, fr))
 }
 
 #[cfg(test)]
@@ -0,0 +1,498 @@
+//! Backends for storing the text given to the pager
+//!
+//! By default, minus keeps all of the text in memory inside a [`MemoryStore`]. Applications that
+//! page huge amounts of text can instead keep it in a file with a [`FileStore`] or implement
+//! [`TextStore`] for their own kind of storage, for example a memory-mapped file.
+//!
+//! Only the text is kept in the store. To also limit the memory used by the rows formatted for
+//! display, see [`Pager::set_max_formatted_rows`](crate::Pager::set_max_formatted_rows).
+//!
+//! # Example
+//! ```no_run
+//! use minus::{screen::storage::FileStore, Pager};
+//! use std::fs::File;
+//!
+//! let pager = Pager::new();
+//! // Page a large log file without reading it into memory
+//! pager.set_storage(FileStore::new(File::open("large.log")?)?)?;
+//! pager.set_max_formatted_rows(Some(10_000))?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use parking_lot::Mutex;
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    convert::TryFrom,
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+/// Iterator over the lines of a [`TextStore`]
+pub type StoredLines<'a> = Box<dyn Iterator<Item = io::Result<Cow<'a, str>>> + 'a>;
+
+/// Storage for the text given to the pager
+///
+/// The text is only ever appended to or cleared entirely. minus reads it back line by line
+/// whenever it needs to format the text again, for example when the terminal is resized or the
+/// user scrolls to rows that are not kept in memory.
+pub trait TextStore: Send {
+    /// Append `text` to the end of the stored text
+    ///
+    /// # Errors
+    /// Returns an error if the text could not be stored
+    fn push_str(&mut self, text: &str) -> io::Result<()>;
+
+    /// Remove all of the stored text
+    ///
+    /// # Errors
+    /// Returns an error if the text could not be removed
+    fn clear(&mut self) -> io::Result<()>;
+
+    /// Remove the `count` oldest lines from the stored text
+    ///
+    /// All of the text is removed if it has `count` lines or less.
+    ///
+    /// # Errors
+    /// Returns an error if the lines could not be removed
+    fn drop_lines(&mut self, count: usize) -> io::Result<()>;
+
+    /// Returns the number of lines in the stored text
+    ///
+    /// This follows the same rules as [`str::lines`], hence a last line that is not terminated
+    /// by a newline is also counted.
+    fn line_count(&self) -> usize;
+
+    /// Returns `true` if the stored text is empty or ends with a newline
+    fn is_terminated(&self) -> bool;
+
+    /// Returns the lines starting from the zero based line number `start`
+    ///
+    /// Like [`str::lines`], the lines must not contain their line endings.
+    fn lines_from(&self, start: usize) -> StoredLines<'_>;
+
+    /// Returns the zero based number of the line containing the byte at `offset`
+    ///
+    /// Offsets are counted from the start of the stored text, including the line endings. An
+    /// offset beyond the end of the text gives the number of lines.
+    ///
+    /// The default implementation reads the lines from the start and assumes that they end with
+    /// `\n`. Stores that know where their lines start should override it.
+    ///
+    /// # Errors
+    /// Returns an error if the text could not be read
+    fn line_at_byte(&self, offset: u64) -> io::Result<usize> {
+        let line_count = self.line_count();
+        let mut line_start = 0;
+        for (idx, line) in self.lines_from(0).enumerate() {
+            // The last line may not be terminated by a newline
+            let terminated = idx + 1 < line_count || self.is_terminated();
+            let line_end = line_start + line?.len() as u64 + u64::from(terminated);
+            if offset < line_end {
+                return Ok(idx);
+            }
+            line_start = line_end;
+        }
+        Ok(line_count)
+    }
+}
+
+/// Keeps all of the text in memory
+///
+/// This is the default storage of the pager.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    text: String,
+    /// Byte index at which each line starts
+    line_starts: Vec<usize>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the stored text
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let offset = self.text.len();
+        if text.is_empty() {
+            return;
+        }
+        if self.is_terminated() {
+            self.line_starts.push(offset);
+        }
+        self.text.push_str(text);
+        let len = self.text.len();
+        self.line_starts.extend(
+            text.match_indices('\n')
+                .map(|(i, _)| offset + i + 1)
+                .filter(|&start| start < len),
+        );
+    }
+}
+
+impl From<&str> for MemoryStore {
+    fn from(text: &str) -> Self {
+        let mut store = Self::new();
+        store.push_text(text);
+        store
+    }
+}
+
+impl TextStore for MemoryStore {
+    fn push_str(&mut self, text: &str) -> io::Result<()> {
+        self.push_text(text);
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.text.clear();
+        self.line_starts.clear();
+        Ok(())
+    }
+
+    fn drop_lines(&mut self, count: usize) -> io::Result<()> {
+        let Some(&offset) = self.line_starts.get(count) else {
+            return self.clear();
+        };
+        self.text.drain(..offset);
+        self.line_starts.drain(..count);
+        for start in &mut self.line_starts {
+            *start -= offset;
+        }
+        Ok(())
+    }
+
+    fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.text.is_empty() || self.text.ends_with('\n')
+    }
+
+    fn lines_from(&self, start: usize) -> StoredLines<'_> {
+        let text = self
+            .line_starts
+            .get(start)
+            .map_or("", |&offset| &self.text[offset..]);
+        Box::new(text.lines().map(|l| Ok(Cow::Borrowed(l))))
+    }
+
+    fn line_at_byte(&self, offset: u64) -> io::Result<usize> {
+        if offset >= self.text.len() as u64 {
+            return Ok(self.line_count());
+        }
+        // The first line always starts at 0 so at least one start is not after the offset
+        Ok(self
+            .line_starts
+            .partition_point(|&start| start as u64 <= offset)
+            - 1)
+    }
+}
+
+/// Number of bytes read from a [`FileStore`] at once
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Function that shortens the stream of a [`FileStore`] to the given length in bytes
+pub type Truncate<F> = Box<dyn FnMut(&mut F, u64) -> io::Result<()> + Send>;
+
+/// Keeps the text in a file or any other seekable stream
+///
+/// Only the position of each line is kept in memory. Text pushed to the pager is written at the
+/// end of the stream. Invalid UTF-8 in the stream is replaced with `U+FFFD` when it is read.
+///
+/// A file opened only for reading can be used to page it as long as no text is pushed to the
+/// pager, in which case writing to it fails.
+///
+/// Streams can't be shortened through [`Write`] and [`Seek`], so the text is only removed from
+/// the stream if a function for truncating it is given with [`FileStore::truncate_with`].
+/// With one, clearing the text empties the stream and the lines dropped because of
+/// [`Pager::set_max_lines`](crate::Pager::set_max_lines) are removed from it once they take up
+/// as much space as the kept ones, so that an endless stream of text does not fill the disk.
+/// Without one, dropped lines are only forgotten and the stream keeps growing. Clearing the
+/// text, for example with [`Pager::set_text`](crate::Pager::set_text), then writes the new text
+/// from the start of the stream, which fails if the stream held text before the store was
+/// created since that text would be partly overwritten.
+///
+/// # Example
+/// ```no_run
+/// use minus::screen::storage::FileStore;
+/// use std::fs::{File, OpenOptions};
+///
+/// let file = OpenOptions::new()
+///     .read(true)
+///     .write(true)
+///     .create(true)
+///     .truncate(true)
+///     .open("pager.log")?;
+/// let store = FileStore::new(file)?.truncate_with(|file: &mut File, len| file.set_len(len));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct FileStore<F> {
+    inner: Mutex<F>,
+    index: LineIndex,
+    truncate: Option<Truncate<F>>,
+    /// Whether the stream held text before the store was created
+    had_content: bool,
+}
+
+impl<F> FileStore<F>
+where
+    F: Read + Write + Seek + Send,
+{
+    /// Create a store from `inner`, using its existing content as the initial text
+    ///
+    /// # Errors
+    /// Returns an error if `inner` could not be read
+    pub fn new(mut inner: F) -> io::Result<Self> {
+        let start = inner.seek(SeekFrom::Start(0))?;
+        let mut index = LineIndex {
+            end: start,
+            line_starts: Vec::new(),
+            terminated: true,
+        };
+        let mut buf = vec![0; FILE_CHUNK_SIZE];
+        loop {
+            match inner.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => index.push(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Self {
+            inner: Mutex::new(inner),
+            had_content: index.end > 0,
+            index,
+            truncate: None,
+        })
+    }
+
+    /// Remove text from the stream by calling `truncate` with the length to shorten it to
+    ///
+    /// For a [`File`](std::fs::File), this is [`File::set_len`](std::fs::File::set_len).
+    #[must_use]
+    pub fn truncate_with(
+        mut self,
+        truncate: impl FnMut(&mut F, u64) -> io::Result<()> + Send + 'static,
+    ) -> Self {
+        self.truncate = Some(Box::new(truncate));
+        self
+    }
+
+    /// Consume the store, returning the underlying stream
+    pub fn into_inner(self) -> F {
+        self.inner.into_inner()
+    }
+
+    /// Read the lines from `first` up to a chunk's worth of bytes, but at least one line
+    fn read_chunk(&self, first: usize) -> io::Result<Vec<String>> {
+        let starts = &self.index.line_starts;
+        let from = starts[first];
+        let mut last = first + 1;
+        while last < starts.len() && starts[last] - from < FILE_CHUNK_SIZE as u64 {
+            last += 1;
+        }
+        let to = starts.get(last).copied().unwrap_or(self.index.end);
+
+        let mut buf = vec![
+            0;
+            usize::try_from(to - from)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        ];
+        let mut inner = self.inner.lock();
+        inner.seek(SeekFrom::Start(from))?;
+        inner.read_exact(&mut buf)?;
+        drop(inner);
+
+        Ok(String::from_utf8_lossy(&buf)
+            .lines()
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    /// Move the kept text to the start of the stream and truncate it, once the forgotten text
+    /// before it takes up at least as much space
+    ///
+    /// This does nothing if the stream can't be truncated.
+    fn compact(&mut self) -> io::Result<()> {
+        let Some(truncate) = &mut self.truncate else {
+            return Ok(());
+        };
+        let Some(&start) = self.index.line_starts.first() else {
+            return Ok(());
+        };
+        let len = self.index.end - start;
+        if start < len.max(FILE_CHUNK_SIZE as u64) {
+            return Ok(());
+        }
+        // The kept text is not overwritten since it starts after the place it is moved to ends
+        let inner = self.inner.get_mut();
+        let mut buf = vec![0; FILE_CHUNK_SIZE];
+        let mut moved = 0;
+        while moved < len {
+            let n =
+                usize::try_from(len - moved).map_or(FILE_CHUNK_SIZE, |n| n.min(FILE_CHUNK_SIZE));
+            inner.seek(SeekFrom::Start(start + moved))?;
+            inner.read_exact(&mut buf[..n])?;
+            inner.seek(SeekFrom::Start(moved))?;
+            inner.write_all(&buf[..n])?;
+            moved += n as u64;
+        }
+        inner.flush()?;
+        truncate(inner, len)?;
+        for line_start in &mut self.index.line_starts {
+            *line_start -= start;
+        }
+        self.index.end = len;
+        Ok(())
+    }
+}
+
+impl<F> TextStore for FileStore<F>
+where
+    F: Read + Write + Seek + Send,
+{
+    fn push_str(&mut self, text: &str) -> io::Result<()> {
+        let inner = self.inner.get_mut();
+        inner.seek(SeekFrom::Start(self.index.end))?;
+        inner.write_all(text.as_bytes())?;
+        inner.flush()?;
+        self.index.push(text.as_bytes());
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        // The text pushed next is written from the start of the stream so that it does not keep
+        // growing. Without truncating, whatever remains after it is never read, but text that
+        // was not written by the pager must not be overwritten.
+        if let Some(truncate) = &mut self.truncate {
+            truncate(self.inner.get_mut(), 0)?;
+        } else if self.had_content {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the text of the stream can't be replaced without truncating it",
+            ));
+        }
+        self.had_content = false;
+        self.index.line_starts.clear();
+        self.index.terminated = true;
+        self.index.end = 0;
+        Ok(())
+    }
+
+    fn drop_lines(&mut self, count: usize) -> io::Result<()> {
+        if count >= self.index.line_starts.len() && (self.truncate.is_some() || !self.had_content) {
+            return self.clear();
+        }
+        // The dropped lines are forgotten right away and removed from the stream later on
+        self.index
+            .line_starts
+            .drain(..count.min(self.index.line_starts.len()));
+        self.compact()
+    }
+
+    fn line_count(&self) -> usize {
+        self.index.line_starts.len()
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.index.terminated
+    }
+
+    fn lines_from(&self, start: usize) -> StoredLines<'_> {
+        Box::new(FileLines {
+            store: self,
+            next: start,
+            buffered: VecDeque::new(),
+        })
+    }
+
+    fn line_at_byte(&self, offset: u64) -> io::Result<usize> {
+        let starts = &self.index.line_starts;
+        // Dropped lines are only forgotten, so the text starts where the first kept line does
+        let Some(&first) = starts.first() else {
+            return Ok(0);
+        };
+        let position = first.saturating_add(offset);
+        if position >= self.index.end {
+            return Ok(self.line_count());
+        }
+        Ok(starts.partition_point(|&start| start <= position) - 1)
+    }
+}
+
+/// Positions of the lines inside the stream of a [`FileStore`]
+struct LineIndex {
+    /// Position after the last byte of the text
+    end: u64,
+    /// Position at which each line starts
+    line_starts: Vec<u64>,
+    /// Whether the text is empty or ends with a newline
+    terminated: bool,
+}
+
+impl LineIndex {
+    /// Index `bytes` that are written right after the current end
+    fn push(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        if self.terminated {
+            self.line_starts.push(self.end);
+        }
+        let offset = self.end;
+        self.end += bytes.len() as u64;
+        let end = self.end;
+        self.line_starts.extend(
+            bytes
+                .iter()
+                .enumerate()
+                .filter(|(_, &b)| b == b'\n')
+                .map(|(i, _)| offset + i as u64 + 1)
+                .filter(|&start| start < end),
+        );
+        self.terminated = bytes.ends_with(b"\n");
+    }
+}
+
+/// Iterator over the lines of a [`FileStore`]
+struct FileLines<'a, F> {
+    store: &'a FileStore<F>,
+    /// Line number of the next line to read from the stream
+    next: usize,
+    buffered: VecDeque<String>,
+}
+
+impl<'a, F> Iterator for FileLines<'a, F>
+where
+    F: Read + Write + Seek + Send,
+{
+    type Item = io::Result<Cow<'a, str>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffered.is_empty() {
+            if self.next >= self.store.line_count() {
+                return None;
+            }
+            match self.store.read_chunk(self.next) {
+                Ok(lines) => {
+                    self.next += lines.len();
+                    self.buffered.extend(lines);
+                }
+                Err(e) => {
+                    // Don't try reading again after a failure
+                    self.next = usize::MAX;
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.buffered.pop_front().map(|l| Ok(Cow::Owned(l)))
+    }
+}
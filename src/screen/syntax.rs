@@ -0,0 +1,324 @@
+//! Hook for highlighting the syntax of the text
+//!
+//! Instead of colorizing all of the text before giving it to the pager, applications can set a
+//! [`Highlighter`] with [`Pager::set_highlighter`](crate::Pager::set_highlighter). minus calls it
+//! for each line right before the line is formatted for display, so only the lines that are
+//! actually formatted get highlighted. Since the pager receives the plain text, searches and
+//! filters work on it just like they do without a highlighter.
+//!
+//! Many formats can't be highlighted by looking at a single line, for example a string that spans
+//! several lines. Each highlighter has a [`State`](Highlighter::State) that is carried from one
+//! line to the next for this. minus keeps the state at some of the lines, so that it can start
+//! highlighting again in the middle of the text, for example when the text is appended to or
+//! when rows are formatted again after being dropped due to
+//! [`Pager::set_max_formatted_rows`](crate::Pager::set_max_formatted_rows).
+//!
+//! With the `syntax` feature, this module also provides highlighters for a few common formats:
+//! [`JsonHighlighter`], [`DiffHighlighter`] and [`LogHighlighter`].
+//!
+//! # Example
+//! ```
+//! use crossterm::style::Stylize;
+//! use minus::{screen::syntax::Highlighter, Pager};
+//! use std::borrow::Cow;
+//!
+//! /// Highlights the comments starting with `#`
+//! struct Comments;
+//!
+//! impl Highlighter for Comments {
+//!     type State = ();
+//!
+//!     fn highlight_line<'a>(&self, line: &'a str, _state: &mut ()) -> Cow<'a, str> {
+//!         match line.find('#') {
+//!             Some(start) => format!("{}{}", &line[..start], (&line[start..]).dark_grey()).into(),
+//!             None => line.into(),
+//!         }
+//!     }
+//! }
+//!
+//! let pager = Pager::new();
+//! pager.set_highlighter(Comments)?;
+//! pager.set_text("key = value # a comment")?;
+//! # Ok::<(), minus::MinusError>(())
+//! ```
+use std::borrow::Cow;
+
+#[cfg(feature = "syntax")]
+use {
+    crossterm::style::{ContentStyle, Stylize},
+    std::fmt::Write as _,
+};
+
+/// Highlights the syntax of the text line by line
+///
+/// See the [module level documentation](self) for more info.
+pub trait Highlighter: Send + Sync + 'static {
+    /// State carried from the end of one line to the start of the next
+    ///
+    /// The first line is highlighted with the [`Default`] state. Highlighters that only need to
+    /// look at the current line can use `()`.
+    type State: Clone + Default + Send + 'static;
+
+    /// Highlight `line`, returning it with the ANSI escape sequences for its styles
+    ///
+    /// `state` contains the state at the start of the line and should be updated to the state at
+    /// its end. The line does not contain the newline character. The returned text must not
+    /// contain any newlines and should only add escape sequences to the line, otherwise the text
+    /// displayed won't match the text that is searched.
+    fn highlight_line<'a>(&self, line: &'a str, state: &mut Self::State) -> Cow<'a, str>;
+}
+
+/// Append `text` to `out` in `style`
+#[cfg(feature = "syntax")]
+fn push_styled(out: &mut String, text: &str, style: ContentStyle) {
+    if text.is_empty() {
+        return;
+    }
+    write!(out, "{}", style.apply(text)).unwrap();
+}
+
+/// Highlights JSON, both pretty printed and one document per line
+///
+/// Keys, strings, numbers and literals each get their own color. Brackets are colored by how
+/// deeply they are nested.
+#[cfg(feature = "syntax")]
+#[cfg_attr(docsrs, doc(cfg(feature = "syntax")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonHighlighter;
+
+/// [`Highlighter::State`] of [`JsonHighlighter`]
+#[cfg(feature = "syntax")]
+#[cfg_attr(docsrs, doc(cfg(feature = "syntax")))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonState {
+    /// Number of brackets that are open
+    depth: usize,
+}
+
+#[cfg(feature = "syntax")]
+impl JsonHighlighter {
+    fn bracket_style(depth: usize) -> ContentStyle {
+        match depth % 3 {
+            0 => ContentStyle::new().yellow(),
+            1 => ContentStyle::new().magenta(),
+            _ => ContentStyle::new().cyan(),
+        }
+    }
+}
+
+#[cfg(feature = "syntax")]
+impl Highlighter for JsonHighlighter {
+    type State = JsonState;
+
+    fn highlight_line<'a>(&self, line: &'a str, state: &mut JsonState) -> Cow<'a, str> {
+        let mut out = String::with_capacity(line.len() * 2);
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            let len = match c {
+                '"' => {
+                    let mut escaped = false;
+                    let len = rest[1..]
+                        .find(|c| {
+                            let end = c == '"' && !escaped;
+                            escaped = c == '\\' && !escaped;
+                            end
+                        })
+                        .map_or(rest.len(), |end| end + 2);
+                    let is_key = rest[len..].trim_start().starts_with(':');
+                    let style = if is_key {
+                        ContentStyle::new().blue().bold()
+                    } else {
+                        ContentStyle::new().green()
+                    };
+                    push_styled(&mut out, &rest[..len], style);
+                    len
+                }
+                '{' | '[' => {
+                    push_styled(&mut out, &rest[..1], Self::bracket_style(state.depth));
+                    state.depth += 1;
+                    1
+                }
+                '}' | ']' => {
+                    state.depth = state.depth.saturating_sub(1);
+                    push_styled(&mut out, &rest[..1], Self::bracket_style(state.depth));
+                    1
+                }
+                '-' | '0'..='9' => {
+                    let len = rest
+                        .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                        .unwrap_or(rest.len());
+                    push_styled(&mut out, &rest[..len], ContentStyle::new().cyan());
+                    len
+                }
+                c if c.is_ascii_alphabetic() => {
+                    let len = rest
+                        .find(|c: char| !c.is_ascii_alphanumeric())
+                        .unwrap_or(rest.len());
+                    let word = &rest[..len];
+                    if matches!(word, "true" | "false" | "null") {
+                        push_styled(&mut out, word, ContentStyle::new().magenta());
+                    } else {
+                        out.push_str(word);
+                    }
+                    len
+                }
+                c => {
+                    out.push(c);
+                    c.len_utf8()
+                }
+            };
+            rest = &rest[len..];
+        }
+        Cow::Owned(out)
+    }
+}
+
+/// Highlights unified diffs as produced by `diff -u` and `git diff`
+///
+/// Added and removed lines are colored green and red, hunk headers cyan and file headers bold.
+/// The hunk headers are followed so that removed lines starting with `--` are not mistaken for
+/// file headers.
+#[cfg(feature = "syntax")]
+#[cfg_attr(docsrs, doc(cfg(feature = "syntax")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffHighlighter;
+
+/// [`Highlighter::State`] of [`DiffHighlighter`]
+#[cfg(feature = "syntax")]
+#[cfg_attr(docsrs, doc(cfg(feature = "syntax")))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffState {
+    /// Number of lines of the old file left in the current hunk
+    old: usize,
+    /// Number of lines of the new file left in the current hunk
+    new: usize,
+}
+
+#[cfg(feature = "syntax")]
+impl DiffState {
+    /// Parse the line counts from a hunk header like `@@ -1,5 +1,6 @@`
+    fn from_hunk_header(line: &str) -> Self {
+        let mut ranges = line.split_whitespace().skip(1);
+        let mut count = |prefix: char| {
+            ranges
+                .next()
+                .and_then(|range| range.strip_prefix(prefix))
+                .map_or(0, |range| {
+                    // A range without a count is a single line
+                    range
+                        .split_once(',')
+                        .map_or(Some(1), |(_, count)| count.parse().ok())
+                        .unwrap_or(0)
+                })
+        };
+        let old = count('-');
+        let new = count('+');
+        Self { old, new }
+    }
+}
+
+#[cfg(feature = "syntax")]
+impl Highlighter for DiffHighlighter {
+    type State = DiffState;
+
+    fn highlight_line<'a>(&self, line: &'a str, state: &mut DiffState) -> Cow<'a, str> {
+        let in_hunk = state.old > 0 || state.new > 0;
+        let style = if in_hunk {
+            match line.chars().next() {
+                Some('+') => {
+                    state.new = state.new.saturating_sub(1);
+                    ContentStyle::new().green()
+                }
+                Some('-') => {
+                    state.old = state.old.saturating_sub(1);
+                    ContentStyle::new().red()
+                }
+                // Lines like `\ No newline at end of file` don't count
+                Some('\\') => return Cow::Borrowed(line),
+                _ => {
+                    state.old = state.old.saturating_sub(1);
+                    state.new = state.new.saturating_sub(1);
+                    return Cow::Borrowed(line);
+                }
+            }
+        } else if line.starts_with("@@") {
+            *state = DiffState::from_hunk_header(line);
+            ContentStyle::new().cyan()
+        } else if line.starts_with("+++")
+            || line.starts_with("---")
+            || line.starts_with("diff ")
+            || line.starts_with("index ")
+        {
+            ContentStyle::new().bold()
+        } else {
+            return Cow::Borrowed(line);
+        };
+        Cow::Owned(style.apply(line).to_string())
+    }
+}
+
+/// Highlights the levels of log messages, such as `ERROR` or `INFO`
+///
+/// Only the first level found in a line is colored. Indented lines following a message, like the
+/// lines of a stack trace, are colored in the color of the level of that message.
+#[cfg(feature = "syntax")]
+#[cfg_attr(docsrs, doc(cfg(feature = "syntax")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LogHighlighter;
+
+/// [`Highlighter::State`] of [`LogHighlighter`]
+#[cfg(feature = "syntax")]
+#[cfg_attr(docsrs, doc(cfg(feature = "syntax")))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogState {
+    /// Style of the level of the last message
+    level: Option<ContentStyle>,
+}
+
+#[cfg(feature = "syntax")]
+impl LogHighlighter {
+    fn level_style(word: &str) -> Option<ContentStyle> {
+        let style = match word {
+            "FATAL" | "CRITICAL" | "ERROR" | "ERR" => ContentStyle::new().red().bold(),
+            "WARNING" | "WARN" => ContentStyle::new().yellow(),
+            "INFO" => ContentStyle::new().green(),
+            "DEBUG" => ContentStyle::new().blue(),
+            "TRACE" => ContentStyle::new().dark_grey(),
+            _ => return None,
+        };
+        Some(style)
+    }
+}
+
+#[cfg(feature = "syntax")]
+impl Highlighter for LogHighlighter {
+    type State = LogState;
+
+    fn highlight_line<'a>(&self, line: &'a str, state: &mut LogState) -> Cow<'a, str> {
+        let mut start = 0;
+        while start < line.len() {
+            let end = line[start..]
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .map_or(line.len(), |len| start + len);
+            if let Some(style) = Self::level_style(&line[start..end]) {
+                state.level = Some(style);
+                let mut out = String::with_capacity(line.len() + 16);
+                out.push_str(&line[..start]);
+                push_styled(&mut out, &line[start..end], style);
+                out.push_str(&line[end..]);
+                return Cow::Owned(out);
+            }
+            // Skip the character that ended the word
+            start = end + line[end..].chars().next().map_or(0, char::len_utf8);
+        }
+        match state.level {
+            Some(style) if line.starts_with(char::is_whitespace) => {
+                Cow::Owned(style.apply(line).to_string())
+            }
+            _ => {
+                state.level = None;
+                Cow::Borrowed(line)
+            }
+        }
+    }
+}
@@ -1,17 +1,124 @@
+use crate::{
+    minus_core::utils::syntax::SyntaxHighlighting,
+    screen::{gutter::Gutter, syntax::Highlighter},
+    LineNumbers, PagerState,
+};
+use std::{fmt::Write, ops::Range};
+
+/// Builds the [`PagerState`] that the tests start from
+#[derive(Default)]
+struct StateBuilder {
+    max_formatted_rows: Option<usize>,
+    max_lines: Option<usize>,
+    line_numbers: bool,
+    gutter: Option<Gutter>,
+    #[cfg(feature = "search")]
+    filter: Option<crate::search::Filter>,
+    highlighter: Option<SyntaxHighlighting>,
+    lines: usize,
+}
+
+impl StateBuilder {
+    fn max_formatted_rows(mut self, rows: usize) -> Self {
+        self.max_formatted_rows = Some(rows);
+        self
+    }
+
+    fn max_lines(mut self, lines: usize) -> Self {
+        self.max_lines = Some(lines);
+        self
+    }
+
+    fn line_numbers(mut self) -> Self {
+        self.line_numbers = true;
+        self
+    }
+
+    fn gutter(mut self, gutter: Gutter) -> Self {
+        self.gutter = Some(gutter);
+        self
+    }
+
+    /// Display only the lines matching `pattern`
+    #[cfg(feature = "search")]
+    fn filter(mut self, pattern: &str) -> Self {
+        let pattern = regex::Regex::new(pattern).unwrap();
+        self.filter = Some(crate::search::Filter::new(pattern));
+        self
+    }
+
+    fn highlighter(mut self, highlighter: impl Highlighter) -> Self {
+        self.highlighter = Some(SyntaxHighlighting::new(highlighter));
+        self
+    }
+
+    /// Start with the [`text`] of `lines` lines
+    fn lines(mut self, lines: usize) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    fn build(self) -> PagerState {
+        let mut ps = PagerState::new().unwrap();
+        ps.screen.max_formatted_rows = self.max_formatted_rows;
+        ps.screen.max_lines = self.max_lines;
+        if self.line_numbers {
+            ps.line_numbers = LineNumbers::Enabled;
+        }
+        #[cfg(feature = "search")]
+        {
+            ps.screen.filter = self.filter;
+        }
+        if let Some(gutter) = self.gutter {
+            ps.set_gutter(gutter);
+        }
+        if self.highlighter.is_some() {
+            ps.set_highlighter(self.highlighter);
+        }
+        if self.lines > 0 {
+            ps.set_text(&text(self.lines));
+        }
+        ps
+    }
+}
+
+/// Text made of the lines `line 0` up to `line {lines - 1}`
+fn text(lines: usize) -> String {
+    let mut text = String::new();
+    for i in 0..lines {
+        writeln!(text, "line {i}").unwrap();
+    }
+    text
+}
+
+/// Append the lines `line {i}` for each `i` in `lines`, one at a time
+fn push_lines(ps: &mut PagerState, lines: Range<usize>) {
+    for i in lines {
+        ps.append_str(&format!("line {i}\n"));
+    }
+}
+
 mod unterminated {
     use crate::screen::{format_text_block, FormatOpts, Rows};
 
-    const fn get_append_opts_template(text: &str) -> FormatOpts<Rows> {
+    fn get_append_opts_template(text: &str) -> FormatOpts<Rows> {
         FormatOpts {
             buffer: Vec::new(),
             text,
             attachment: None,
             #[cfg(feature = "search")]
             search_term: &None,
+            #[cfg(feature = "search")]
+            filter: None,
+            #[cfg(feature = "search")]
+            highlights: &[],
+            syntax: None,
             lines_count: 0,
             formatted_lines_count: 0,
             cols: 80,
             line_numbers: crate::LineNumbers::Disabled,
+            gutter: Box::leak(Box::default()),
+            line_offset: 0,
             prev_unterminated: 0,
             line_wrapping: true,
         }
@@ -190,3 +297,986 @@ third line\n",
         assert_eq!(3, append_style.num_unterminated);
     }
 }
+
+mod storage {
+    use crate::screen::storage::{FileStore, MemoryStore, TextStore};
+    use std::{convert::TryFrom, io::Cursor};
+
+    fn lines(store: &dyn TextStore, start: usize) -> Vec<String> {
+        store
+            .lines_from(start)
+            .map(|l| l.unwrap().into_owned())
+            .collect()
+    }
+
+    fn check_store(store: &mut dyn TextStore) {
+        assert_eq!(store.line_count(), 0);
+        assert!(store.is_terminated());
+
+        store.push_str("first\nsec").unwrap();
+        assert_eq!(store.line_count(), 2);
+        assert!(!store.is_terminated());
+
+        store.push_str("ond\r\n\nfourth\n").unwrap();
+        assert_eq!(store.line_count(), 4);
+        assert!(store.is_terminated());
+        assert_eq!(lines(store, 0), vec!["first", "second", "", "fourth"]);
+        assert_eq!(lines(store, 3), vec!["fourth"]);
+        assert!(lines(store, 4).is_empty());
+        let line_at_byte = |store: &dyn TextStore, offset| store.line_at_byte(offset).unwrap();
+        assert_eq!(line_at_byte(store, 5), 0);
+        assert_eq!(line_at_byte(store, 6), 1);
+        assert_eq!(line_at_byte(store, 14), 2);
+        assert_eq!(line_at_byte(store, 21), 3);
+        assert_eq!(line_at_byte(store, 22), 4);
+
+        store.drop_lines(1).unwrap();
+        assert_eq!(store.line_count(), 3);
+        assert_eq!(lines(store, 0), vec!["second", "", "fourth"]);
+        assert_eq!(line_at_byte(store, 0), 0);
+        assert_eq!(line_at_byte(store, 8), 1);
+        store.push_str("fifth").unwrap();
+        store.drop_lines(2).unwrap();
+        assert_eq!(lines(store, 0), vec!["fourth", "fifth"]);
+        assert!(!store.is_terminated());
+        store.drop_lines(5).unwrap();
+        assert_eq!(store.line_count(), 0);
+        assert!(store.is_terminated());
+
+        store.push_str("first\nsecond\n").unwrap();
+        store.clear().unwrap();
+        assert_eq!(store.line_count(), 0);
+        assert!(lines(store, 0).is_empty());
+
+        store.push_str("again").unwrap();
+        assert_eq!(lines(store, 0), vec!["again"]);
+    }
+
+    #[test]
+    fn memory_store() {
+        check_store(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn file_store() {
+        check_store(&mut FileStore::new(Cursor::new(Vec::new())).unwrap());
+    }
+
+    /// Store relying on the default implementations of [`TextStore`]
+    struct PlainStore(MemoryStore);
+
+    impl TextStore for PlainStore {
+        fn push_str(&mut self, text: &str) -> std::io::Result<()> {
+            self.0.push_str(text)
+        }
+        fn clear(&mut self) -> std::io::Result<()> {
+            self.0.clear()
+        }
+        fn drop_lines(&mut self, count: usize) -> std::io::Result<()> {
+            self.0.drop_lines(count)
+        }
+        fn line_count(&self) -> usize {
+            self.0.line_count()
+        }
+        fn is_terminated(&self) -> bool {
+            self.0.is_terminated()
+        }
+        fn lines_from(&self, start: usize) -> crate::screen::storage::StoredLines<'_> {
+            self.0.lines_from(start)
+        }
+    }
+
+    #[test]
+    fn default_line_at_byte() {
+        let store = PlainStore(MemoryStore::from("first\nsecond\n\nfourth"));
+        for (offset, line) in [(0, 0), (5, 0), (6, 1), (13, 2), (14, 3), (19, 3), (20, 4)] {
+            assert_eq!(store.line_at_byte(offset).unwrap(), line, "offset {offset}");
+            assert_eq!(
+                store.0.line_at_byte(offset).unwrap(),
+                line,
+                "offset {offset}"
+            );
+        }
+    }
+
+    #[test]
+    fn file_store_existing_content() {
+        let mut content = b"valid\ninvalid \xff\n".to_vec();
+        // Make sure that the lines are read in multiple chunks
+        let long = "x".repeat(100_000);
+        content.extend_from_slice(long.as_bytes());
+
+        let mut store = FileStore::new(Cursor::new(content)).unwrap();
+        assert_eq!(store.line_count(), 3);
+        assert_eq!(lines(&store, 0), vec!["valid", "invalid \u{fffd}", &long]);
+
+        store.push_str("\nappended\n").unwrap();
+        assert_eq!(lines(&store, 2), vec![long.as_str(), "appended"]);
+        assert!(store.into_inner().into_inner().ends_with(b"x\nappended\n"));
+    }
+
+    #[test]
+    fn file_store_reuses_cleared_stream() {
+        let mut store = FileStore::new(Cursor::new(Vec::new())).unwrap();
+        for i in 0..10 {
+            store.clear().unwrap();
+            store.push_str(&format!("text {i}\nmore\n")).unwrap();
+        }
+        assert_eq!(lines(&store, 0), vec!["text 9", "more"]);
+        assert_eq!(store.into_inner().into_inner(), b"text 9\nmore\n");
+    }
+
+    #[test]
+    fn file_store_truncates_dropped_lines() {
+        let truncated_store = || {
+            FileStore::new(Cursor::new(Vec::new()))
+                .unwrap()
+                .truncate_with(|c: &mut Cursor<Vec<u8>>, len| {
+                    c.get_mut().truncate(usize::try_from(len).unwrap());
+                    Ok(())
+                })
+        };
+        let mut store = truncated_store();
+        // Keep the last 1000 lines of an endless stream
+        for i in 0..100_000 {
+            store.push_str(&format!("line {i}\n")).unwrap();
+            if store.line_count() > 1000 {
+                store.drop_lines(store.line_count() - 1000).unwrap();
+            }
+        }
+        assert_eq!(lines(&store, 0)[0], "line 99000");
+        assert_eq!(lines(&store, 999), vec!["line 99999"]);
+        assert_eq!(store.line_at_byte(0).unwrap(), 0);
+        // Nearly 1MB has been written but only about the size of a chunk is left
+        assert!(store.into_inner().into_inner().len() < 128 * 1024);
+
+        let mut store = truncated_store();
+        store.push_str("old\ntext\n").unwrap();
+        store.clear().unwrap();
+        store.push_str("new\n").unwrap();
+        assert_eq!(store.into_inner().into_inner(), b"new\n");
+    }
+
+    #[test]
+    fn file_store_keeps_existing_content() {
+        let mut store = FileStore::new(Cursor::new(b"old\ntext\n".to_vec())).unwrap();
+        assert!(store.clear().is_err());
+        store.drop_lines(1).unwrap();
+        store.push_str("new\n").unwrap();
+        assert_eq!(lines(&store, 0), vec!["text", "new"]);
+        assert_eq!(store.into_inner().into_inner(), b"old\ntext\nnew\n");
+    }
+
+    #[test]
+    #[cfg(feature = "static_output")]
+    fn write_text_keeps_the_end() {
+        for text in ["", "first\nsecond", "first\nsecond\n", "\n"] {
+            let mut screen = crate::screen::Screen::default();
+            screen.set_text(text).unwrap();
+            let mut out = Vec::new();
+            screen.write_text(&mut out).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), text);
+        }
+    }
+}
+
+mod bounded_rows {
+    use super::{text, StateBuilder};
+    use crate::{
+        minus_core::utils::display::write_from_pagerstate, screen::storage::FileStore, PagerState,
+    };
+    use std::{fmt::Write, io::Cursor};
+
+    fn displayed(ps: &mut PagerState) -> String {
+        let mut out = Vec::new();
+        write_from_pagerstate(&mut out, ps).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn keeps_only_window() {
+        let mut ps = StateBuilder::default()
+            .max_formatted_rows(50)
+            .lines(1000)
+            .build();
+        assert_eq!(ps.screen.formatted_lines_count(), 1000);
+        assert!(ps.screen.formatted_lines.len() <= 50);
+        assert_eq!(ps.lines_to_row_map.get(999), Some(&999));
+
+        ps.upper_mark = 700;
+        assert!(displayed(&mut ps).starts_with("\rline 700\n\rline 701\n"));
+        assert!(ps.screen.formatted_lines.len() <= 50);
+        assert_eq!(ps.screen.window_start, 680);
+
+        ps.upper_mark = 5;
+        assert!(displayed(&mut ps).starts_with("\rline 5\n"));
+        assert_eq!(ps.screen.window_start, 0);
+    }
+
+    #[test]
+    fn append_outside_window() {
+        let mut ps = StateBuilder::default()
+            .max_formatted_rows(50)
+            .lines(1000)
+            .build();
+        ps.append_str("line 1000\nline ");
+        ps.append_str("1001\n");
+        assert_eq!(ps.screen.formatted_lines_count(), 1002);
+        assert!(ps.screen.formatted_lines.len() <= 50);
+
+        ps.upper_mark = 1000;
+        assert!(displayed(&mut ps).starts_with("\rline 993\n"));
+        assert!(displayed(&mut ps).ends_with("\rline 1001\n"));
+    }
+
+    #[test]
+    fn wrapped_rows() {
+        let mut ps = StateBuilder::default()
+            .max_formatted_rows(20)
+            .lines(1000)
+            .build();
+        ps.cols = 4;
+        ps.format_lines();
+        // Each line is wrapped into two rows
+        assert_eq!(ps.screen.formatted_lines_count(), 2000);
+        assert_eq!(ps.lines_to_row_map.line_of_row(1001), 500);
+
+        ps.upper_mark = 1001;
+        assert!(displayed(&mut ps).starts_with("\r500\n\rline\n\r501\n"));
+        assert!(ps.screen.formatted_lines.len() <= 21);
+    }
+
+    #[test]
+    fn file_store() {
+        let mut ps = PagerState::new().unwrap();
+        ps.screen.max_formatted_rows = Some(30);
+        ps.screen.store = Box::new(FileStore::new(Cursor::new(text(500).into_bytes())).unwrap());
+        ps.format_lines();
+        assert_eq!(ps.screen.formatted_lines_count(), 500);
+
+        ps.upper_mark = 250;
+        assert!(displayed(&mut ps).starts_with("\rline 250\n"));
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn search_outside_window() {
+        let mut ps = StateBuilder::default()
+            .max_formatted_rows(30)
+            .lines(1000)
+            .build();
+        ps.search_state.search_term = Some(regex::Regex::new("line 9[0-9]{2}").unwrap());
+        ps.format_lines();
+        assert_eq!(ps.search_state.search_idx.len(), 100);
+        assert_eq!(ps.search_state.search_idx.iter().next(), Some(&900));
+
+        ps.upper_mark = 900;
+        let page = displayed(&mut ps);
+        assert!(page.contains(
+            &crate::search::highlight_line_matches(
+                "line 900",
+                ps.search_state.search_term.as_ref().unwrap(),
+                false
+            )
+            .0
+        ));
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn window_matches_all_rows() {
+        use crate::{
+            screen::{make_format_lines, storage::MemoryStore},
+            LineNumbers,
+        };
+
+        let mut text = String::new();
+        for i in 0..300 {
+            writeln!(text, "line {i} {}", "word ".repeat(i % 7)).unwrap();
+        }
+        let store = MemoryStore::from(text.as_str());
+        let term = Some(regex::Regex::new("word|line 1").unwrap());
+        let filter = crate::search::Filter::inverted(regex::Regex::new("5").unwrap());
+        let format = |filter, keep| {
+            make_format_lines(
+                &store,
+                LineNumbers::Enabled,
+                &crate::screen::gutter::Gutter::default(),
+                0,
+                14,
+                true,
+                &term,
+                filter,
+                &[],
+                None,
+                keep,
+            )
+            .unwrap()
+        };
+
+        for filter in [None, Some(&filter)] {
+            let (all, _, full) = format(filter, 0..usize::MAX);
+            let (window, start, part) = format(filter, 150..180);
+            // Whole lines are kept
+            assert!(start <= 150 && start + window.len() >= 180);
+            assert_eq!(window, all[start..start + window.len()]);
+            assert_eq!(part.rows_formatted, full.rows_formatted);
+            assert_eq!(part.append_search_idx, full.append_search_idx);
+            for line in 0..300 {
+                assert_eq!(
+                    part.lines_to_row_map.get(line),
+                    full.lines_to_row_map.get(line)
+                );
+            }
+        }
+    }
+}
+
+mod max_lines {
+    use super::{push_lines, StateBuilder};
+    use crate::PagerState;
+
+    #[test]
+    fn marks_follow_lines() {
+        let mut ps = StateBuilder::default().max_lines(20).build();
+        push_lines(&mut ps, 0..20);
+        ps.marks.lock().extend([('a', 2), ('b', 10)]);
+
+        push_lines(&mut ps, 20..25);
+        assert_eq!(ps.mark('a'), None);
+        assert_eq!(ps.mark('b'), Some(5));
+        assert_eq!(ps.screen.formatted_lines[ps.row_of_line(5)], "line 10");
+    }
+
+    #[test]
+    fn drops_oldest_lines() {
+        let mut ps = StateBuilder::default().max_lines(20).build();
+        push_lines(&mut ps, 0..50);
+        assert_eq!(ps.screen.line_count(), 20);
+        assert_eq!(ps.screen.formatted_lines_count(), 20);
+        assert_eq!(ps.screen.formatted_lines[0], "line 30");
+        assert_eq!(ps.screen.orig_text().lines().next(), Some("line 30"));
+        assert_eq!(ps.lines_to_row_map.get(0), Some(&0));
+        assert_eq!(ps.lines_to_row_map.get(19), Some(&19));
+    }
+
+    #[test]
+    fn unterminated_line_is_kept() {
+        let mut ps = StateBuilder::default().max_lines(2).build();
+        ps.append_str("one\ntwo\nthr");
+        ps.append_str("ee\nfo");
+        ps.append_str("ur");
+        assert_eq!(ps.screen.formatted_lines, vec!["three", "four"]);
+        assert_eq!(ps.screen.unterminated, 1);
+    }
+
+    #[test]
+    fn wrapped_lines() {
+        let mut ps = StateBuilder::default().max_lines(3).build();
+        ps.cols = 4;
+        push_lines(&mut ps, 0..10);
+        // Each line is wrapped into two rows
+        assert_eq!(ps.screen.formatted_lines_count(), 6);
+        assert_eq!(ps.screen.formatted_lines[..2], ["line", "7"]);
+        assert_eq!(ps.lines_to_row_map.get(2), Some(&4));
+    }
+
+    #[cfg(feature = "dynamic_output")]
+    #[test]
+    fn view_stays_on_same_text() {
+        use crate::minus_core::utils::display::AppendStyle;
+
+        let mut ps = StateBuilder::default().max_lines(100).build();
+        *ps.running.lock() = crate::RunMode::Dynamic;
+        push_lines(&mut ps, 0..100);
+        ps.upper_mark = 50;
+
+        assert_eq!(ps.append_str("line 100\n"), AppendStyle::PartialUpdate(&[]));
+        assert_eq!(ps.upper_mark, 49);
+        assert_eq!(ps.screen.formatted_lines[ps.upper_mark], "line 50");
+
+        // The view has to be redrawn once the displayed rows start getting dropped
+        push_lines(&mut ps, 101..150);
+        assert_eq!(ps.upper_mark, 0);
+        assert_eq!(ps.append_str("line 150\n"), AppendStyle::FullRedraw);
+    }
+
+    #[test]
+    fn line_numbers_are_kept() {
+        let mut ps = StateBuilder::default().max_lines(5).line_numbers().build();
+        push_lines(&mut ps, 0..12);
+        assert_eq!(ps.screen.formatted_lines.len(), 5);
+        assert_eq!(ps.screen.formatted_lines[0], "      8. line 7");
+        assert_eq!(ps.screen.formatted_lines[4], "     12. line 11");
+
+        // Formatting the text again gives the same numbers
+        ps.format_lines();
+        assert_eq!(ps.screen.formatted_lines[0], "      8. line 7");
+
+        // The numbers start from 1 again once the text is replaced
+        ps.set_text("new\n");
+        assert_eq!(ps.screen.formatted_lines, ["     1. new"]);
+    }
+
+    #[test]
+    fn split_and_selection_follow_rows() {
+        use crate::minus_core::utils::selection::{Position, Selection};
+
+        let mut ps = StateBuilder::default().max_lines(10).build();
+        push_lines(&mut ps, 0..10);
+        ps.set_split(true);
+        ps.split.as_mut().unwrap().other_upper_mark = 6;
+        let position = |row, column| Position { row, column };
+        ps.selection = Some(Selection {
+            anchor: position(1, 2),
+            head: Some(position(7, 3)),
+        });
+
+        push_lines(&mut ps, 10..13);
+        assert_eq!(ps.split.as_ref().unwrap().other_upper_mark, 3);
+        assert_eq!(
+            ps.selection,
+            Some(Selection {
+                anchor: position(0, 0),
+                head: Some(position(4, 3)),
+            })
+        );
+
+        // The selection is gone once all of its rows are dropped
+        push_lines(&mut ps, 13..18);
+        assert_eq!(ps.selection, None);
+    }
+
+    #[test]
+    fn limit_applies_to_existing_text() {
+        let mut ps = PagerState::new().unwrap();
+        push_lines(&mut ps, 0..10);
+        ps.screen.max_lines = Some(4);
+        ps.trim_lines();
+        assert_eq!(
+            ps.screen.formatted_lines,
+            ["line 6", "line 7", "line 8", "line 9"]
+        );
+    }
+
+    #[test]
+    fn bounded_window() {
+        let mut ps = StateBuilder::default()
+            .max_lines(100)
+            .max_formatted_rows(20)
+            .build();
+        push_lines(&mut ps, 0..300);
+        assert_eq!(ps.screen.formatted_lines_count(), 100);
+        assert!(ps.screen.formatted_lines.len() <= 20);
+        assert!(ps.screen.window_start + ps.screen.formatted_lines.len() <= 100);
+
+        ps.load_rows(0, 9);
+        assert_eq!(
+            ps.screen.get_formatted_lines_with_bounds(0, 1),
+            ["line 200"]
+        );
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn search_marks_move() {
+        let mut ps = StateBuilder::default().max_lines(10).build();
+        ps.search_state.search_term = Some(regex::Regex::new("line [13]$").unwrap());
+        push_lines(&mut ps, 0..10);
+        assert_eq!(
+            ps.search_state
+                .search_idx
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            [1, 3]
+        );
+        ps.search_state.search_mark = 1;
+
+        push_lines(&mut ps, 10..12);
+        assert_eq!(
+            ps.search_state
+                .search_idx
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            [1]
+        );
+        assert_eq!(ps.search_state.search_mark, 0);
+
+        push_lines(&mut ps, 12..14);
+        assert!(ps.search_state.search_idx.is_empty());
+        assert_eq!(ps.search_state.search_mark, 0);
+    }
+}
+
+#[cfg(feature = "search")]
+mod filter {
+    use super::{push_lines, StateBuilder};
+    use crate::{search::Filter, PagerState};
+    use regex::Regex;
+
+    #[test]
+    fn hides_lines() {
+        let mut ps = StateBuilder::default().filter("[05]$").build();
+        push_lines(&mut ps, 0..20);
+        assert_eq!(ps.screen.line_count(), 20);
+        assert_eq!(
+            ps.screen.formatted_lines,
+            ["line 0", "line 5", "line 10", "line 15"]
+        );
+        // Hidden lines start at the row of the next displayed line
+        assert_eq!(ps.lines_to_row_map.get(3), Some(&1));
+        assert_eq!(ps.lines_to_row_map.line_of_row(2), 10);
+
+        ps.screen.filter = Some(Filter::inverted(Regex::new("[1-9]$").unwrap()));
+        ps.format_lines();
+        assert_eq!(ps.screen.formatted_lines, ["line 0", "line 10"]);
+    }
+
+    #[test]
+    fn original_line_numbers() {
+        let mut ps = StateBuilder::default()
+            .filter("line 1[12]")
+            .line_numbers()
+            .build();
+        push_lines(&mut ps, 0..20);
+        assert_eq!(ps.screen.formatted_lines.len(), 2);
+        assert!(ps.screen.formatted_lines[0]
+            .trim_start()
+            .starts_with("12. line 11"));
+        assert!(ps.screen.formatted_lines[1]
+            .trim_start()
+            .starts_with("13. line 12"));
+    }
+
+    #[test]
+    fn unterminated_line() {
+        let mut ps = StateBuilder::default().filter("done").build();
+        ps.append_str("first done\nsec");
+        assert_eq!(ps.screen.formatted_lines, ["first done"]);
+        ps.append_str("ond do");
+        assert_eq!(ps.screen.formatted_lines, ["first done"]);
+        ps.append_str("ne\nthird\n");
+        assert_eq!(ps.screen.formatted_lines, ["first done", "second done"]);
+        assert_eq!(ps.screen.formatted_lines_count(), 2);
+    }
+
+    #[test]
+    fn search_in_filtered_view() {
+        let mut ps = StateBuilder::default().filter("[02468]$").build();
+        ps.search_state.search_term = Some(Regex::new("line 1").unwrap());
+        push_lines(&mut ps, 0..20);
+        assert_eq!(
+            ps.search_state
+                .search_idx
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            [5, 6, 7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn view_stays_on_same_line() {
+        let mut ps = PagerState::new().unwrap();
+        push_lines(&mut ps, 0..100);
+        ps.upper_mark = 42;
+
+        ps.set_filter(Some(Filter::new(Regex::new("0$").unwrap())));
+        assert_eq!(ps.screen.formatted_lines[ps.upper_mark], "line 50");
+
+        ps.set_filter(None);
+        assert_eq!(ps.upper_mark, 50);
+        assert_eq!(ps.screen.formatted_lines_count(), 100);
+    }
+
+    #[test]
+    fn bounded_window() {
+        let mut ps = StateBuilder::default()
+            .filter("7$")
+            .max_formatted_rows(10)
+            .build();
+        push_lines(&mut ps, 0..1000);
+        assert_eq!(ps.screen.formatted_lines_count(), 100);
+
+        ps.load_rows(50, 55);
+        assert_eq!(
+            ps.screen.get_formatted_lines_with_bounds(50, 52),
+            ["line 507", "line 517"]
+        );
+    }
+}
+
+#[cfg(feature = "search")]
+mod highlights {
+    use crate::{search::Highlight, PagerState};
+    use crossterm::style::{ContentStyle, Stylize};
+    use regex::Regex;
+
+    fn highlight(pattern: &str) -> Highlight {
+        Highlight::new(Regex::new(pattern).unwrap(), ContentStyle::new().yellow())
+    }
+
+    #[test]
+    fn applied_alongside_search() {
+        let mut ps = PagerState::new().unwrap();
+        ps.search_state.highlights = vec![highlight("id=[0-9]+")];
+        ps.search_state.search_term = Some(Regex::new("ERROR").unwrap());
+        ps.append_str("ERROR id=42\nINFO id=7\nERROR done\n");
+
+        let yellow = "\x1b[38;5;11m";
+        assert_eq!(
+            ps.screen.formatted_lines[1],
+            format!("INFO {yellow}id=7\x1b[39m")
+        );
+        assert!(ps.screen.formatted_lines[0].contains(&format!("{yellow}id=42")));
+        assert!(ps.screen.formatted_lines[0].contains("\x1b[7mERROR\x1b[27m"));
+        // Only the search matches can be jumped to
+        assert_eq!(
+            ps.search_state
+                .search_idx
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            [0, 2]
+        );
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let mut ps = PagerState::new().unwrap();
+        ps.append_str("one\ntwo\n");
+
+        ps.add_highlight(highlight("o"));
+        ps.add_highlight(highlight("t"));
+        assert_eq!(
+            ps.screen.formatted_lines[1],
+            "\x1b[38;5;11mt\x1b[39mw\x1b[38;5;11mo\x1b[39m"
+        );
+        // The same pattern replaces the existing highlight
+        ps.add_highlight(Highlight::new(
+            Regex::new("t").unwrap(),
+            ContentStyle::new().red(),
+        ));
+        assert_eq!(ps.search_state.highlights.len(), 2);
+
+        assert!(ps.remove_highlights(Some("o")));
+        assert!(!ps.remove_highlights(Some("o")));
+        assert_eq!(ps.screen.formatted_lines[0], "one");
+        assert!(ps.remove_highlights(None));
+        assert!(ps.search_state.highlights.is_empty());
+        assert_eq!(ps.screen.formatted_lines[1], "two");
+    }
+}
+
+mod syntax {
+    use super::StateBuilder;
+    use crate::screen::syntax::Highlighter;
+    use std::{
+        borrow::Cow,
+        fmt::Write,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    /// Puts the lines between a `{` and a `}` line in brackets
+    #[derive(Default)]
+    struct Blocks {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Highlighter for Blocks {
+        type State = bool;
+
+        fn highlight_line<'a>(&self, line: &'a str, in_block: &mut bool) -> Cow<'a, str> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            match line {
+                "{" => *in_block = true,
+                "}" => *in_block = false,
+                _ if *in_block => return format!("[{line}]").into(),
+                _ => {}
+            }
+            line.into()
+        }
+    }
+
+    #[test]
+    fn state_carried_across_lines() {
+        let mut ps = StateBuilder::default()
+            .highlighter(Blocks::default())
+            .build();
+        ps.append_str("a\n{\nb\nc\n}\nd\n");
+        assert_eq!(
+            ps.screen.formatted_lines,
+            ["a", "{", "[b]", "[c]", "}", "d"]
+        );
+
+        ps.format_lines();
+        assert_eq!(
+            ps.screen.formatted_lines,
+            ["a", "{", "[b]", "[c]", "}", "d"]
+        );
+    }
+
+    #[test]
+    fn appends_continue_from_last_line() {
+        let blocks = Blocks::default();
+        let calls = blocks.calls.clone();
+        let mut ps = StateBuilder::default().highlighter(blocks).build();
+        ps.append_str("a\n{\nb");
+        ps.append_str("c\n");
+        ps.append_str("d\n}\ne\n");
+        assert_eq!(
+            ps.screen.formatted_lines,
+            ["a", "{", "[bc]", "[d]", "}", "e"]
+        );
+        // Only the unterminated line and the one before each append are highlighted again
+        assert!(calls.load(Ordering::Relaxed) <= 10);
+    }
+
+    #[test]
+    fn dropped_rows_formatted_again() {
+        let mut ps = StateBuilder::default()
+            .highlighter(Blocks::default())
+            .max_formatted_rows(10)
+            .build();
+        let mut text = String::from("{\n");
+        for i in 0..1000 {
+            writeln!(text, "{i}").unwrap();
+        }
+        ps.append_str(&text);
+        ps.format_lines();
+        ps.load_rows(700, 710);
+        assert_eq!(
+            ps.screen.get_formatted_lines_with_bounds(700, 701),
+            ["[699]"]
+        );
+    }
+
+    #[test]
+    fn state_kept_when_lines_dropped() {
+        let mut ps = StateBuilder::default()
+            .highlighter(Blocks::default())
+            .max_lines(5)
+            .build();
+        ps.append_str("{\n");
+        for i in 0..10 {
+            ps.append_str(&format!("{i}\n"));
+        }
+        ps.append_str("}\nx\n");
+        assert_eq!(ps.screen.line_count(), 5);
+        ps.format_lines();
+        assert_eq!(ps.screen.formatted_lines, ["[7]", "[8]", "[9]", "}", "x"]);
+    }
+
+    #[cfg(feature = "syntax")]
+    fn highlight_lines<H: Highlighter>(highlighter: &H, text: &str) -> Vec<String> {
+        let mut state = H::State::default();
+        text.lines()
+            .map(|line| highlighter.highlight_line(line, &mut state).into_owned())
+            .collect()
+    }
+
+    #[cfg(feature = "syntax")]
+    #[test]
+    fn json() {
+        use crate::screen::syntax::JsonHighlighter;
+        use crossterm::style::Stylize;
+
+        let lines = highlight_lines(&JsonHighlighter, "{\n  \"a\": [1, \"x\\\"\", null]\n}");
+        assert_eq!(lines[0], "{".yellow().to_string());
+        assert_eq!(
+            lines[1],
+            format!(
+                "  {}: {}{}, {}, {}{}",
+                "\"a\"".blue().bold(),
+                "[".magenta(),
+                "1".cyan(),
+                "\"x\\\"\"".green(),
+                "null".magenta(),
+                "]".magenta(),
+            )
+        );
+        assert_eq!(lines[2], "}".yellow().to_string());
+    }
+
+    #[cfg(feature = "syntax")]
+    #[test]
+    fn diff() {
+        use crate::screen::syntax::DiffHighlighter;
+        use crossterm::style::Stylize;
+
+        let diff = "--- a/file\n+++ b/file\n@@ -1,2 +1 @@\n--- removed\n context\n+++ b/next";
+        let lines = highlight_lines(&DiffHighlighter, diff);
+        assert_eq!(lines[0], "--- a/file".bold().to_string());
+        assert_eq!(lines[1], "+++ b/file".bold().to_string());
+        assert_eq!(lines[2], "@@ -1,2 +1 @@".cyan().to_string());
+        // Inside a hunk, the line is a removed line and not a file header
+        assert_eq!(lines[3], "--- removed".red().to_string());
+        assert_eq!(lines[4], " context");
+        assert_eq!(lines[5], "+++ b/next".bold().to_string());
+    }
+
+    #[cfg(feature = "syntax")]
+    #[test]
+    fn log_levels() {
+        use crate::screen::syntax::LogHighlighter;
+        use crossterm::style::Stylize;
+
+        let log = "[12:00] ERROR failed\n    at main\n[12:01] INFO: done\n    more\nplain";
+        let lines = highlight_lines(&LogHighlighter, log);
+        assert_eq!(lines[0], format!("[12:00] {} failed", "ERROR".red().bold()));
+        assert_eq!(lines[1], "    at main".red().bold().to_string());
+        assert_eq!(lines[2], format!("[12:01] {}: done", "INFO".green()));
+        assert_eq!(lines[3], "    more".green().to_string());
+        assert_eq!(lines[4], "plain");
+    }
+
+    #[test]
+    fn replaced_text_starts_again() {
+        let mut ps = StateBuilder::default()
+            .highlighter(Blocks::default())
+            .build();
+        ps.append_str("{\na\n");
+        ps.set_text("b\n");
+        assert_eq!(ps.screen.formatted_lines, ["b"]);
+    }
+}
+
+mod gutter {
+    use super::StateBuilder;
+    use crate::{
+        minus_core::utils::display::write_from_pagerstate,
+        screen::gutter::{split_gutter, Gutter, GutterColumn, NumberWidth},
+        LineNumbers,
+    };
+
+    #[test]
+    fn default_layout() {
+        let mut ps = StateBuilder::default().line_numbers().build();
+        ps.append_str("a\nb\n");
+        assert_eq!(ps.screen.formatted_lines, ["     1. a", "     2. b"]);
+    }
+
+    #[test]
+    fn width_and_separator() {
+        let mut ps = StateBuilder::default()
+            .line_numbers()
+            .gutter(Gutter {
+                width: NumberWidth::Fixed(2),
+                separator: "│".to_string(),
+                ..Gutter::default()
+            })
+            .build();
+        ps.cols = 8;
+        ps.append_str("abcd efgh\n");
+        // The rows after the first are indented by the width of the gutter
+        assert_eq!(ps.screen.formatted_lines, [" 1│abcd", "   efgh"]);
+
+        // Fitting numbers grow with the line count
+        ps.cols = 80;
+        ps.set_gutter(Gutter {
+            width: NumberWidth::Fit { padding: 0 },
+            separator: " ".to_string(),
+            ..Gutter::default()
+        });
+        assert_eq!(ps.screen.formatted_lines[0], "1 abcd efgh");
+        ps.append_str(&"x\n".repeat(9));
+        ps.format_lines();
+        assert_eq!(ps.screen.formatted_lines[0], " 1 abcd efgh");
+        assert_eq!(ps.screen.formatted_lines[9], "10 x");
+    }
+
+    #[test]
+    fn extra_column() {
+        let mut ps = StateBuilder::default()
+            .gutter(Gutter {
+                column: Some(GutterColumn::new(3, |idx, line| {
+                    format!("{}{idx}", &line[..1])
+                })),
+                ..Gutter::default()
+            })
+            .build();
+        // The column is displayed without line numbers too
+        ps.append_str("a\nbc\n");
+        assert_eq!(ps.screen.formatted_lines, ["a0 a", "b1 bc"]);
+
+        ps.line_numbers = LineNumbers::Enabled;
+        ps.format_lines();
+        assert_eq!(ps.screen.formatted_lines[1], "     2. b1 bc");
+    }
+
+    #[test]
+    fn relative_numbers() {
+        let mut ps = StateBuilder::default()
+            .line_numbers()
+            .gutter(Gutter {
+                width: NumberWidth::Fixed(2),
+                separator: " ".to_string(),
+                relative: true,
+                ..Gutter::default()
+            })
+            .build();
+        ps.rows = 4;
+        ps.cols = 6;
+        ps.append_str("a\nbbbbbbb\nc\nd\n");
+        // The numbers are only filled in when displaying the rows
+        assert_eq!(ps.screen.formatted_lines[0], "   a");
+
+        ps.upper_mark = 1;
+        let mut out = Vec::new();
+        write_from_pagerstate(&mut out, &mut ps).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\r 2 bbb\n\r   bbb\n\r   b\n"
+        );
+        ps.upper_mark = 3;
+        let mut out = Vec::new();
+        write_from_pagerstate(&mut out, &mut ps).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\r   b\n\r 1 c\n\r 2 d\n");
+    }
+
+    #[test]
+    fn split_after_gutter() {
+        assert_eq!(split_gutter("  1. text", 5), ("  1. ", "text"));
+        assert_eq!(
+            split_gutter("\x1b[1m  1.\x1b[0m text", 4),
+            ("\x1b[1m  1.\x1b[0m", " text")
+        );
+        assert_eq!(split_gutter("│ text", 2), ("│ ", "text"));
+        assert_eq!(split_gutter("text", 0), ("", "text"));
+    }
+}
+
+mod append {
+    use crate::PagerState;
+
+    #[test]
+    fn rows_of_continued_line() {
+        let mut ps = PagerState::new().unwrap();
+        ps.append_str("one\ntw");
+        ps.append_str("o\nthree\n");
+        assert_eq!(ps.screen.formatted_lines, ["one", "two", "three"]);
+        assert_eq!(ps.lines_to_row_map.get(2), Some(&2));
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn search_matches_in_later_appends() {
+        let mut ps = PagerState::new().unwrap();
+        ps.search_state.search_term = Some(regex::Regex::new("match").unwrap());
+        ps.append_str("a\nmatch\n");
+        ps.append_str("b\nmatch\n");
+        assert_eq!(
+            ps.search_state
+                .search_idx
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            [1, 3]
+        );
+    }
+}
@@ -51,22 +51,28 @@
 //! ```
 
 #![allow(unused_imports)]
-use crate::minus_core::utils::{display, term};
+use crate::backend::EventSource;
+use crate::minus_core::utils::{display, syntax::SyntaxHighlighting, term, LinesRowMap};
 use crate::screen::Screen;
 use crate::{error::MinusError, input::HashedEventRegister, screen};
 use crate::{LineNumbers, PagerState};
 use crossterm::{
     cursor::{self, MoveTo},
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
-    style::Attribute,
+    style::{Attribute, Color, Colored, ContentStyle, Stylize},
     terminal::{Clear, ClearType},
 };
 use once_cell::sync::Lazy;
-use regex::Regex;
+use parking_lot::Mutex;
+use regex::{Regex, RegexBuilder};
 use std::collections::BTreeSet;
 use std::{
+    borrow::Cow,
     convert::{TryFrom, TryInto},
-    io::Write,
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
     time::Duration,
 };
 
@@ -107,6 +113,314 @@ impl PartialEq for SearchMode {
     }
 }
 
+/// Defines how the letter case is treated when matching a search query
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaseSensitivity {
+    /// Match the case exactly
+    #[default]
+    Sensitive,
+    /// Ignore the case unless the query contains an uppercase letter
+    Smart,
+    /// Ignore the case
+    Insensitive,
+}
+
+impl CaseSensitivity {
+    /// Get the mode that follows this one when it is toggled at the prompt
+    const fn next(self) -> Self {
+        match self {
+            Self::Sensitive => Self::Smart,
+            Self::Smart => Self::Insensitive,
+            Self::Insensitive => Self::Sensitive,
+        }
+    }
+}
+
+/// Compile a search query into a [Regex]
+///
+/// In literal mode, the query is matched as plain text rather than as a regular expression.
+pub(crate) fn compile_query(
+    query: &str,
+    case_sensitivity: CaseSensitivity,
+    literal: bool,
+) -> Result<Regex, regex::Error> {
+    let ignore_case = match case_sensitivity {
+        CaseSensitivity::Sensitive => false,
+        CaseSensitivity::Smart => !query.chars().any(char::is_uppercase),
+        CaseSensitivity::Insensitive => true,
+    };
+    let pattern = if literal {
+        Cow::Owned(regex::escape(query))
+    } else {
+        Cow::Borrowed(query)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(ignore_case)
+        .build()
+}
+
+/// Maximum number of queries kept in the [SearchHistory]
+const MAX_HISTORY_ENTRIES: usize = 100;
+
+/// Queries that have been searched for, oldest first
+///
+/// If a file is set, the history is loaded from it and saved to it after each search.
+#[derive(Default)]
+pub(crate) struct SearchHistory {
+    entries: Vec<String>,
+    file: Option<PathBuf>,
+}
+
+impl SearchHistory {
+    /// Get all the queries in the history
+    pub(crate) fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Add `queries` as the most recent entries without saving them
+    ///
+    /// Queries that are already present are moved to the end.
+    pub(crate) fn extend<I>(&mut self, queries: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        for query in queries {
+            // Each query is stored on its own line of the file
+            if query.is_empty() || query.contains('\n') {
+                continue;
+            }
+            self.entries.retain(|q| *q != query);
+            self.entries.push(query);
+        }
+        let excess = self.entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        self.entries.drain(..excess);
+    }
+
+    /// Add `query` as the most recent entry and save the history
+    pub(crate) fn push(&mut self, query: String) -> io::Result<()> {
+        self.extend([query]);
+        self.save()
+    }
+
+    /// Load the history stored in `path` and save the history there from now on
+    ///
+    /// The entries that are already present are kept as the most recent ones. A missing file
+    /// is treated as an empty history.
+    pub(crate) fn set_file(&mut self, path: PathBuf) -> io::Result<()> {
+        let stored = match fs::read_to_string(&path) {
+            Ok(stored) => stored,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let entries = std::mem::take(&mut self.entries);
+        self.extend(stored.lines().map(String::from));
+        self.extend(entries);
+        self.file = Some(path);
+        Ok(())
+    }
+
+    /// Write the history to the file if one is set
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        let mut text = self.entries.join("\n");
+        text.push('\n');
+        fs::write(path, text)
+    }
+}
+
+/// A pattern deciding which lines are displayed
+///
+/// Lines are matched without their ANSI escape sequences. See
+/// [`Pager::set_filter`](crate::Pager::set_filter) for more info.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    pattern: Regex,
+    inverted: bool,
+}
+
+impl Filter {
+    /// Display only the lines matching `pattern`
+    #[must_use]
+    pub const fn new(pattern: Regex) -> Self {
+        Self {
+            pattern,
+            inverted: false,
+        }
+    }
+
+    /// Display only the lines that don't match `pattern`
+    #[must_use]
+    pub const fn inverted(pattern: Regex) -> Self {
+        Self {
+            pattern,
+            inverted: true,
+        }
+    }
+
+    /// Get the pattern of the filter
+    #[must_use]
+    pub const fn pattern(&self) -> &Regex {
+        &self.pattern
+    }
+
+    /// Returns `true` if the lines matching the pattern are hidden
+    #[must_use]
+    pub const fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Returns `true` if `line` should be displayed
+    pub(crate) fn shows(&self, line: &str) -> bool {
+        let stripped_str = ANSI_REGEX.replace_all(line, "");
+        self.pattern.is_match(&stripped_str) != self.inverted
+    }
+}
+
+impl From<Regex> for Filter {
+    fn from(pattern: Regex) -> Self {
+        Self::new(pattern)
+    }
+}
+
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.inverted == other.inverted
+    }
+}
+
+/// A pattern whose matches are displayed in a style of its own
+///
+/// Unlike the search term, highlights can't be jumped between and any number of them can be
+/// active at once. See [`Pager::add_highlight`](crate::Pager::add_highlight) for more info.
+#[derive(Clone, Debug)]
+pub struct Highlight {
+    pattern: Regex,
+    style: ContentStyle,
+    /// Escape sequences that apply the style
+    start: String,
+    /// Escape sequences that undo the style without touching the rest of the line
+    end: String,
+}
+
+impl Highlight {
+    /// Display the matches of `pattern` with the given `style`
+    #[must_use]
+    pub fn new(pattern: Regex, style: ContentStyle) -> Self {
+        let mut start = String::new();
+        let mut end = String::new();
+        let colors = [
+            (
+                style.foreground_color.map(Colored::ForegroundColor),
+                Colored::ForegroundColor(Color::Reset),
+            ),
+            (
+                style.background_color.map(Colored::BackgroundColor),
+                Colored::BackgroundColor(Color::Reset),
+            ),
+            (
+                style.underline_color.map(Colored::UnderlineColor),
+                Colored::UnderlineColor(Color::Reset),
+            ),
+        ];
+        for (color, reset) in colors {
+            if let Some(color) = color {
+                // Writing to a String never fails
+                let _ = write!(start, "\x1b[{color}m");
+                let _ = write!(end, "\x1b[{reset}m");
+            }
+        }
+        for attribute in Attribute::iterator().filter(|a| style.attributes.has(*a)) {
+            if let Some(undo) = undo_attribute(attribute) {
+                start.push_str(&attribute.to_string());
+                end.push_str(&undo.to_string());
+            }
+        }
+        Self {
+            pattern,
+            style,
+            start,
+            end,
+        }
+    }
+
+    /// Get the pattern of the highlight
+    #[must_use]
+    pub const fn pattern(&self) -> &Regex {
+        &self.pattern
+    }
+
+    /// Get the style in which the matches are displayed
+    #[must_use]
+    pub const fn style(&self) -> ContentStyle {
+        self.style
+    }
+
+    /// Apply the style to all the matches in `line`
+    pub(crate) fn apply(&self, line: &str) -> Option<String> {
+        let (highlighted, is_match) =
+            mark_line_matches(line, &self.pattern, false, &self.start, &self.end);
+        is_match.then_some(highlighted)
+    }
+}
+
+impl PartialEq for Highlight {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.style == other.style
+    }
+}
+
+/// Get the attribute that turns off `attribute`
+///
+/// Returns `None` for attributes that can't be turned off on their own, like
+/// [`Attribute::Reset`].
+const fn undo_attribute(attribute: Attribute) -> Option<Attribute> {
+    match attribute {
+        Attribute::Bold | Attribute::Dim => Some(Attribute::NormalIntensity),
+        Attribute::Italic | Attribute::Fraktur => Some(Attribute::NoItalic),
+        Attribute::Underlined
+        | Attribute::DoubleUnderlined
+        | Attribute::Undercurled
+        | Attribute::Underdotted
+        | Attribute::Underdashed => Some(Attribute::NoUnderline),
+        Attribute::SlowBlink | Attribute::RapidBlink => Some(Attribute::NoBlink),
+        Attribute::Reverse => Some(Attribute::NoReverse),
+        Attribute::Hidden => Some(Attribute::NoHidden),
+        Attribute::CrossedOut => Some(Attribute::NotCrossedOut),
+        Attribute::Framed | Attribute::Encircled => Some(Attribute::NotFramedOrEncircled),
+        Attribute::OverLined => Some(Attribute::NotOverLined),
+        _ => None,
+    }
+}
+
+/// Background colors given to the highlights added from the prompt
+const HIGHLIGHT_COLORS: [Color; 6] = [
+    Color::Yellow,
+    Color::Red,
+    Color::Green,
+    Color::Cyan,
+    Color::Magenta,
+    Color::Blue,
+];
+
+/// Get the style for a highlight added from the prompt
+///
+/// This picks the first color that is not used by any of the `highlights` yet. Once all of them
+/// are in use, the colors are repeated.
+pub(crate) fn next_highlight_style(highlights: &[Highlight]) -> ContentStyle {
+    let color = HIGHLIGHT_COLORS
+        .iter()
+        .copied()
+        .find(|c| {
+            !highlights
+                .iter()
+                .any(|h| h.style().background_color == Some(*c))
+        })
+        .unwrap_or(HIGHLIGHT_COLORS[highlights.len() % HIGHLIGHT_COLORS.len()]);
+    ContentStyle::new().black().on(color)
+}
+
 /// Options controlling the behaviour of search overall
 ///
 /// Although it isn't much important for most use cases but it alongside [IncrementalSearchOpts] are the key components
@@ -131,14 +445,29 @@ pub struct SearchOpts<'a> {
     pub word_index: Vec<u16>,
     /// Search character, either `/` or `?` depending on [SearchMode]
     pub search_char: char,
+    /// How the letter case is treated when matching the query
+    pub case_sensitivity: CaseSensitivity,
+    /// Whether the query is matched as plain text rather than as a regular expression
+    pub literal: bool,
     /// Number of rows available in the terminal
     pub rows: u16,
     /// Number of cols available in the terminal
     pub cols: u16,
+    /// Row of the terminal where the pager starts
+    top: u16,
     /// Options specifically controlling incremental search
     pub incremental_search_options: Option<IncrementalSearchOpts<'a>>,
     incremental_search_cache: Option<IncrementalSearchCache>,
     compiled_regex: Option<Regex>,
+    /// Previous queries that can be recalled with `Up` and `Down`
+    history: &'a [String],
+    /// Index of the history entry being shown, `None` while editing a new query
+    history_position: Option<usize>,
+    /// Query that was being typed before moving into the history
+    draft: String,
+    /// Whether the query is a pattern, in which case the search options are displayed and can be
+    /// changed
+    pattern_query: bool,
 }
 
 /// Options to control incremental search
@@ -154,6 +483,10 @@ pub struct IncrementalSearchOpts<'a> {
     pub screen: &'a Screen,
     /// Value of [PagerState::upper_mark] before starting of search prompt
     pub initial_left_mark: usize,
+    /// Highlights applied to the matching text
+    pub highlights: &'a [Highlight],
+    /// Reference to [PagerState::lines_to_row_map]
+    pub lines_to_row_map: &'a LinesRowMap,
 }
 
 impl<'a> From<&'a PagerState> for IncrementalSearchOpts<'a> {
@@ -163,6 +496,8 @@ impl<'a> From<&'a PagerState> for IncrementalSearchOpts<'a> {
             initial_upper_mark: ps.upper_mark,
             screen: &ps.screen,
             initial_left_mark: ps.left_mark,
+            highlights: &ps.search_state.highlights,
+            lines_to_row_map: &ps.lines_to_row_map,
         }
     }
 }
@@ -177,7 +512,18 @@ impl<'a> From<&'a PagerState> for SearchOpts<'a> {
         } else {
             unreachable!();
         };
+        Self::with_char(ps, search_char)
+    }
+}
+
+impl<'a> SearchOpts<'a> {
+    /// Row of the terminal where the prompt is displayed
+    const fn prompt_row(&self) -> u16 {
+        self.top + self.rows.saturating_sub(1)
+    }
 
+    /// Create the options for a prompt starting with `search_char`
+    fn with_char(ps: &'a PagerState, search_char: char) -> Self {
         let incremental_search_options = IncrementalSearchOpts::from(ps);
 
         Self {
@@ -187,11 +533,18 @@ impl<'a> From<&'a PagerState> for SearchOpts<'a> {
             cursor_position: 1,
             word_index: Vec::with_capacity(200),
             search_char,
+            case_sensitivity: ps.search_state.case_sensitivity,
+            literal: ps.search_state.literal,
             rows: ps.rows.try_into().unwrap(),
             cols: ps.cols.try_into().unwrap(),
+            top: ps.top.try_into().unwrap(),
             incremental_search_options: Some(incremental_search_options),
             incremental_search_cache: None,
             compiled_regex: None,
+            history: ps.search_state.history.entries(),
+            history_position: None,
+            draft: String::new(),
+            pattern_query: true,
             search_mode: ps.search_state.search_mode,
         }
     }
@@ -225,16 +578,22 @@ pub(crate) struct FetchInputResult {
     pub(crate) incremental_search_result: Option<IncrementalSearchCache>,
     /// Cached pre-compiled [Regex] if available
     pub(crate) compiled_regex: Option<Regex>,
+    /// Case sensitivity that was active when the prompt closed
+    pub(crate) case_sensitivity: CaseSensitivity,
+    /// Whether literal mode was active when the prompt closed
+    pub(crate) literal: bool,
 }
 
 impl FetchInputResult {
     /// Create an empty `FetchInputResult` with string set to empty string and
     /// incremental_search_cache and compiled_regex set to `None`.
-    const fn new_empty() -> Self {
+    const fn new_empty(case_sensitivity: CaseSensitivity, literal: bool) -> Self {
         Self {
             string: String::new(),
             incremental_search_result: None,
             compiled_regex: None,
+            case_sensitivity,
+            literal,
         }
     }
 }
@@ -243,6 +602,8 @@ impl FetchInputResult {
 pub(crate) struct IncrementalSearchCache {
     /// Lines to be displayed with highlighted search matches
     pub(crate) formatted_lines: Vec<String>,
+    /// Index of the row at which `formatted_lines` starts
+    pub(crate) window_start: usize,
     /// Index from `search_idx` where a search match after current upper mark may be found
     /// NOTE: There is no guarantee that this will stay within the bounds of `search_idx`
     pub(crate) search_mark: usize,
@@ -286,14 +647,20 @@ where
     let reset_screen = |out: &mut O, so: &SearchOpts<'_>| -> crate::Result {
         display::write_text_checked(
             out,
+            so.top,
             &iso.screen.formatted_lines,
+            iso.screen.window_start,
+            iso.screen.formatted_lines_count(),
             iso.initial_upper_mark,
             so.rows.into(),
             so.cols.into(),
             iso.screen.line_wrapping,
             iso.initial_left_mark,
             iso.line_numbers,
+            &iso.screen.gutter,
+            iso.lines_to_row_map,
             iso.screen.line_count(),
+            iso.screen.dropped_lines,
         )?;
         Ok(())
     };
@@ -318,30 +685,66 @@ where
     // format_result.append_search_idx which is after the current upper mark
     //
     // PERF: Check if this can be futhur optimized
-    let (buffer, format_result) = screen::make_format_lines(
-        &iso.screen.orig_text,
-        iso.line_numbers,
-        so.cols.into(),
-        iso.screen.line_wrapping,
-        &so.compiled_regex,
-    );
+    let format = |keep| {
+        let mut syntax = iso.screen.syntax.as_ref().map(SyntaxHighlighting::fork);
+        screen::make_format_lines(
+            &*iso.screen.store,
+            iso.line_numbers,
+            &iso.screen.gutter,
+            iso.screen.dropped_lines,
+            so.cols.into(),
+            iso.screen.line_wrapping,
+            &so.compiled_regex,
+            iso.screen.filter.as_ref(),
+            iso.highlights,
+            syntax.as_mut(),
+            keep,
+        )
+    };
+    // If the text can't be read, don't show any incremental results. The error is
+    // reported once the search is confirmed and the text is formatted again.
+    let Ok((mut buffer, mut window_start, format_result)) = format(
+        iso.screen
+            .window_around(iso.initial_upper_mark, so.rows.into()),
+    ) else {
+        reset_screen(out, so)?;
+        return Ok(None);
+    };
     let position_of_next_match =
         next_nth_match(&format_result.append_search_idx, iso.initial_upper_mark, 0);
     // Get the upper mark. If we can't find one, reset the display
     let upper_mark;
     if let Some(pnm) = position_of_next_match {
         upper_mark = *format_result.append_search_idx.iter().nth(pnm).unwrap();
+        // If only some of the rows are kept in memory, the match may lie outside them
+        let keep = iso.screen.window_around(upper_mark, so.rows.into());
+        if window_start > keep.start
+            || window_start + buffer.len() < keep.end.min(format_result.rows_formatted)
+        {
+            let Ok((b, ws, _)) = format(keep) else {
+                reset_screen(out, so)?;
+                return Ok(None);
+            };
+            buffer = b;
+            window_start = ws;
+        }
         // Draw the incrementally searched lines from upper mark
         display::write_text_checked(
             out,
+            so.top,
             &buffer,
+            window_start,
+            iso.screen.formatted_lines_count(),
             upper_mark,
             so.rows.into(),
             so.cols.into(),
             iso.screen.line_wrapping,
             iso.initial_left_mark,
             iso.line_numbers,
+            &iso.screen.gutter,
+            &format_result.lines_to_row_map,
             iso.screen.line_count(),
+            iso.screen.dropped_lines,
         )?;
     } else {
         reset_screen(out, so)?;
@@ -351,12 +754,47 @@ where
     // cache.
     Ok(Some(IncrementalSearchCache {
         formatted_lines: buffer,
+        window_start,
         search_mark: position_of_next_match.unwrap(),
         upper_mark,
         search_idx: format_result.append_search_idx,
     }))
 }
 
+/// Write the prompt line with the query and the search options that are turned on
+///
+/// The options are shown at the right end as long as they don't overlap the query.
+fn write_query(out: &mut impl Write, so: &SearchOpts<'_>) -> crate::Result {
+    term::move_cursor(out, 0, so.prompt_row(), false)?;
+    write!(
+        out,
+        "\r{}{}{}",
+        Clear(ClearType::CurrentLine),
+        so.search_char,
+        so.string,
+    )?;
+
+    let mut options = String::new();
+    if so.pattern_query {
+        match so.case_sensitivity {
+            CaseSensitivity::Sensitive => {}
+            CaseSensitivity::Smart => options.push_str("[smart-case]"),
+            CaseSensitivity::Insensitive => options.push_str("[ignore-case]"),
+        }
+        if so.literal {
+            options.push_str("[literal]");
+        }
+    }
+    let query_len = so.string.chars().count().saturating_add(1);
+    if let Some(column) = usize::from(so.cols).checked_sub(options.len()) {
+        if !options.is_empty() && column > query_len {
+            term::move_cursor(out, column.try_into().unwrap(), so.prompt_row(), false)?;
+            write!(out, "{options}")?;
+        }
+    }
+    Ok(())
+}
+
 /// Respond to keyboard events
 ///
 /// This souuld be called exactly once for each event by [fetch_input]
@@ -388,7 +826,7 @@ where
 
     let refresh_display = |out: &mut O, so: &mut SearchOpts<'_>| -> Result<(), MinusError> {
         // Cache the compiled regex if the regex is valid
-        so.compiled_regex = Regex::new(&so.string).ok();
+        so.compiled_regex = compile_query(&so.string, so.case_sensitivity, so.literal).ok();
 
         // Run incremental search and update the upper mark if incremental search had a successful
         // run otherwise set it to the initial upper mark
@@ -396,15 +834,7 @@ where
             run_incremental_search(out, so, incremental_search_condition)?;
 
         // Update prompt
-        term::move_cursor(out, 0, so.rows, false)?;
-        write!(
-            out,
-            "\r{}{}{}",
-            Clear(ClearType::CurrentLine),
-            so.search_char,
-            so.string,
-        )?;
-        Ok(())
+        write_query(out, so)
     };
 
     match so.ev.as_ref().unwrap() {
@@ -434,7 +864,7 @@ where
             populate_word_index(so);
             // Update the line
             refresh_display(out, so)?;
-            term::move_cursor(out, so.cursor_position, so.rows, false)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), false)?;
             out.flush()?;
         }
         Event::Key(KeyEvent {
@@ -454,7 +884,7 @@ where
             so.cursor_position = so.cursor_position.saturating_add(1);
             // Update the line
             refresh_display(out, so)?;
-            term::move_cursor(out, so.cursor_position, so.rows, false)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), false)?;
             out.flush()?;
         }
         Event::Key(KeyEvent {
@@ -464,6 +894,54 @@ where
         }) => {
             so.input_status = InputStatus::Confirmed;
         }
+        Event::Key(KeyEvent {
+            code: code @ (KeyCode::Up | KeyCode::Down),
+            modifiers: KeyModifiers::NONE,
+            ..
+        }) => {
+            // Move through the history, going past the most recent entry restores the query
+            // that was being typed
+            let position = match (*code, so.history_position) {
+                (KeyCode::Up, None) if !so.history.is_empty() => {
+                    so.draft = so.string.clone();
+                    Some(so.history.len() - 1)
+                }
+                (KeyCode::Up, Some(position)) => Some(position.saturating_sub(1)),
+                (KeyCode::Down, Some(position)) if position + 1 < so.history.len() => {
+                    Some(position + 1)
+                }
+                (KeyCode::Down, Some(_)) => None,
+                _ => return Ok(()),
+            };
+            so.string = match position {
+                Some(position) => so.history[position].clone(),
+                None => std::mem::take(&mut so.draft),
+            };
+            so.history_position = position;
+            so.cursor_position = so.string.len().saturating_add(1).try_into().unwrap();
+            populate_word_index(so);
+            refresh_display(out, so)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), false)?;
+            out.flush()?;
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) if so.pattern_query => {
+            so.literal = !so.literal;
+            refresh_display(out, so)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), true)?;
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::ALT,
+            ..
+        }) if so.pattern_query => {
+            so.case_sensitivity = so.case_sensitivity.next();
+            refresh_display(out, so)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), true)?;
+        }
         Event::Key(KeyEvent {
             code: KeyCode::Left,
             modifiers: KeyModifiers::NONE,
@@ -473,7 +951,7 @@ where
                 return Ok(());
             }
             so.cursor_position = so.cursor_position.saturating_sub(1);
-            term::move_cursor(out, so.cursor_position, so.rows, true)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), true)?;
         }
         Event::Key(KeyEvent {
             code: KeyCode::Left,
@@ -488,7 +966,7 @@ where
                 .iter()
                 .rfind(|c| c < &&so.cursor_position)
                 .unwrap_or(&FIRST_AVAILABLE_COLUMN);
-            term::move_cursor(out, so.cursor_position, so.rows, true)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), true)?;
         }
         Event::Key(KeyEvent {
             code: KeyCode::Right,
@@ -499,7 +977,7 @@ where
                 return Ok(());
             }
             so.cursor_position = so.cursor_position.saturating_add(1);
-            term::move_cursor(out, so.cursor_position, so.rows, true)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), true)?;
         }
         Event::Key(KeyEvent {
             code: KeyCode::Right,
@@ -514,7 +992,7 @@ where
                 .iter()
                 .find(|c| c > &&so.cursor_position)
                 .unwrap_or(&last_available_column);
-            term::move_cursor(out, so.cursor_position, so.rows, true)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), true)?;
         }
         Event::Key(KeyEvent {
             code: KeyCode::Home,
@@ -522,7 +1000,7 @@ where
             ..
         }) => {
             so.cursor_position = 1;
-            term::move_cursor(out, 1, so.rows, true)?;
+            term::move_cursor(out, 1, so.prompt_row(), true)?;
         }
         Event::Key(KeyEvent {
             code: KeyCode::End,
@@ -530,7 +1008,7 @@ where
             ..
         }) => {
             so.cursor_position = so.string.len().saturating_add(1).try_into().unwrap();
-            term::move_cursor(out, so.cursor_position, so.rows, true)?;
+            term::move_cursor(out, so.cursor_position, so.prompt_row(), true)?;
         }
 
         Event::Key(event) => {
@@ -543,7 +1021,7 @@ where
                 populate_word_index(so);
                 refresh_display(out, so)?;
                 so.cursor_position = so.cursor_position.saturating_add(1);
-                term::move_cursor(out, so.cursor_position, so.rows, false)?;
+                term::move_cursor(out, so.cursor_position, so.prompt_row(), false)?;
                 out.flush()?;
             }
         }
@@ -563,64 +1041,118 @@ where
 pub(crate) fn fetch_input(
     out: &mut impl std::io::Write,
     ps: &PagerState,
+    events: &Mutex<dyn EventSource + '_>,
 ) -> Result<FetchInputResult, MinusError> {
-    // Set the search character to show at column 0
-    let search_char = if ps.search_state.search_mode == SearchMode::Forward {
-        '/'
-    } else {
-        '?'
+    let search_opts = read_query(
+        out,
+        ps,
+        events,
+        SearchOpts::from(ps),
+        &ps.search_state.incremental_search_condition,
+    )?;
+
+    let fetch_input_result = match search_opts.input_status {
+        InputStatus::Active => unreachable!(),
+        InputStatus::Cancelled => {
+            FetchInputResult::new_empty(search_opts.case_sensitivity, search_opts.literal)
+        }
+        // When the query is confirmed, return the actual query along with everything that is valid
+        // in the cache
+        InputStatus::Confirmed => FetchInputResult {
+            string: search_opts.string,
+            incremental_search_result: search_opts.incremental_search_cache,
+            compiled_regex: search_opts.compiled_regex,
+            case_sensitivity: search_opts.case_sensitivity,
+            literal: search_opts.literal,
+        },
     };
+    Ok(fetch_input_result)
+}
 
+/// Fetch a pattern for a filter or a highlight
+///
+/// This works like [fetch_input] except that the prompt starts with `prompt_char` and
+/// incremental search does not run. Returns `None` if the prompt has been cancelled.
+#[cfg(feature = "search")]
+pub(crate) fn fetch_pattern(
+    out: &mut impl std::io::Write,
+    ps: &PagerState,
+    events: &Mutex<dyn EventSource + '_>,
+    prompt_char: char,
+) -> Result<Option<FetchInputResult>, MinusError> {
+    let mut search_opts = SearchOpts::with_char(ps, prompt_char);
+    search_opts.incremental_search_options = None;
+    let search_opts = read_query(out, ps, events, search_opts, |_: &SearchOpts<'_>| false)?;
+
+    if search_opts.input_status == InputStatus::Cancelled {
+        return Ok(None);
+    }
+    Ok(Some(FetchInputResult {
+        string: search_opts.string,
+        incremental_search_result: None,
+        compiled_regex: None,
+        case_sensitivity: search_opts.case_sensitivity,
+        literal: search_opts.literal,
+    }))
+}
+
+/// Read a query at the prompt until it is either confirmed or cancelled
+fn read_query<'a, F>(
+    out: &mut impl std::io::Write,
+    ps: &PagerState,
+    events: &Mutex<dyn EventSource + '_>,
+    mut search_opts: SearchOpts<'a>,
+    incremental_search_condition: F,
+) -> Result<SearchOpts<'a>, MinusError>
+where
+    F: Fn(&SearchOpts<'_>) -> bool,
+{
     // Initial setup
     // - Place the cursor at the beginning of prompt line
     // - Clear the prompt
-    // - Write the search character and
-    // - Show the cursor
-    term::move_cursor(out, 0, ps.rows.try_into().unwrap(), false)?;
-    write!(
+    // - Write the search character along with the active search options and
+    // - Show the cursor after the search character
+    write_query(out, &search_opts)?;
+    term::move_cursor(
         out,
-        "{}{}{}",
-        Clear(ClearType::CurrentLine),
-        search_char,
-        cursor::Show
+        search_opts.cursor_position,
+        search_opts.prompt_row(),
+        false,
     )?;
+    write!(out, "{}", cursor::Show)?;
     out.flush()?;
 
-    let mut search_opts = SearchOpts::from(ps);
+    let mut events = events.lock();
 
     // Fetch events from the terminal and handle them
     loop {
-        if event::poll(Duration::from_millis(100)).map_err(|e| MinusError::HandleEvent(e.into()))? {
-            let ev = event::read().map_err(|e| MinusError::HandleEvent(e.into()))?;
+        if events
+            .poll(Duration::from_millis(100))
+            .map_err(|e| MinusError::HandleEvent(e.into()))?
+        {
+            let ev = events
+                .read()
+                .map_err(|e| MinusError::HandleEvent(e.into()))?;
             search_opts.ev = Some(ev);
-            handle_key_press(
-                out,
-                &mut search_opts,
-                &ps.search_state.incremental_search_condition,
-            )?;
+            handle_key_press(out, &mut search_opts, &incremental_search_condition)?;
             search_opts.ev = None;
         }
         if search_opts.input_status.done() {
             break;
         }
     }
+    drop(events);
     // Teardown: almost opposite of setup
-    term::move_cursor(out, 0, ps.rows.try_into().unwrap(), false)?;
+    term::move_cursor(out, 0, ps.prompt_row(), false)?;
     write!(out, "{}{}", Clear(ClearType::CurrentLine), cursor::Hide)?;
     out.flush()?;
 
-    let fetch_input_result = match search_opts.input_status {
-        InputStatus::Active => unreachable!(),
-        InputStatus::Cancelled => FetchInputResult::new_empty(),
-        // When the query is confirmed, return the actual query along with everything that is valid
-        // in the cache
-        InputStatus::Confirmed => FetchInputResult {
-            string: search_opts.string,
-            incremental_search_result: search_opts.incremental_search_cache,
-            compiled_regex: search_opts.compiled_regex,
-        },
-    };
-    Ok(fetch_input_result)
+    Ok(search_opts)
+}
+
+/// Returns `true` if `query` matches `line` when ignoring its escape sequences
+pub(crate) fn line_matches(line: &str, query: &regex::Regex) -> bool {
+    query.is_match(&ANSI_REGEX.replace_all(line, ""))
 }
 
 /// Highlights the search match
@@ -631,6 +1163,19 @@ pub(crate) fn highlight_line_matches(
     line: &str,
     query: &regex::Regex,
     accurate: bool,
+) -> (String, bool) {
+    mark_line_matches(line, query, accurate, &INVERT, &NORMAL)
+}
+
+/// Surrounds the matches of `query` in `line` with the `start` and `end` escape sequences
+///
+/// The return values are the same as that of [highlight_line_matches].
+fn mark_line_matches(
+    line: &str,
+    query: &regex::Regex,
+    accurate: bool,
+    start: &str,
+    end: &str,
 ) -> (String, bool) {
     // Remove all ansi escapes so we can look through it as if it had none
     let stripped_str = ANSI_REGEX.replace_all(line, "");
@@ -670,7 +1215,7 @@ pub(crate) fn highlight_line_matches(
     // by inverting their background/foreground colors
     let mut inverted = query
         .replace_all(&stripped_str, |caps: &regex::Captures| {
-            format!("{}{}{}", *INVERT, &caps[0], *NORMAL)
+            format!("{}{}{}", start, &caps[0], end)
         })
         .to_string();
 
@@ -693,12 +1238,12 @@ pub(crate) fn highlight_line_matches(
         let mut pos = if !accurate && match_count % 2 == 1 {
             // INFO: Its safe to unwrap here
             matches.get(match_count).unwrap()
-                + NORMAL.len()
+                + end.len()
                 + inserted_escs_len
-                + (num_invert * INVERT.len())
-                + (num_normal * NORMAL.len())
+                + (num_invert * start.len())
+                + (num_normal * end.len())
         } else {
-            esc.0 + inserted_escs_len + (num_invert * INVERT.len()) + (num_normal * NORMAL.len())
+            esc.0 + inserted_escs_len + (num_invert * start.len()) + (num_normal * end.len())
         };
 
         if match_count % 2 == 1 {
@@ -777,7 +1322,7 @@ pub(crate) fn next_nth_match(
 mod tests {
     mod input_handling {
         use crate::{
-            search::{handle_key_press, InputStatus, SearchOpts},
+            search::{handle_key_press, CaseSensitivity, InputStatus, SearchOpts},
             SearchMode,
         };
         use crossterm::{
@@ -801,11 +1346,18 @@ mod tests {
                 cursor_position: 1,
                 word_index: Vec::with_capacity(200),
                 search_char,
+                case_sensitivity: CaseSensitivity::Sensitive,
+                literal: false,
                 rows: 25,
                 cols: 100,
+                top: 0,
                 incremental_search_options: None,
                 incremental_search_cache: None,
                 compiled_regex: None,
+                history: &[],
+                history_position: None,
+                draft: String::new(),
+                pattern_query: true,
                 search_mode: sm,
             }
         }
@@ -1016,9 +1568,9 @@ mod tests {
                 write!(
                     result_out,
                     "{move_to_prompt}\r{clear_line}/{string}{move_to_position}",
-                    move_to_prompt = MoveTo(0, search_opts.rows),
+                    move_to_prompt = MoveTo(0, search_opts.prompt_row()),
                     clear_line = Clear(ClearType::CurrentLine),
-                    move_to_position = MoveTo(cursor_position, search_opts.rows),
+                    move_to_position = MoveTo(cursor_position, search_opts.prompt_row()),
                 )
                 .unwrap();
             }
@@ -1051,14 +1603,153 @@ mod tests {
                 write!(
                     result_out,
                     "{move_to_prompt}\r{clear_line}?{string}{move_to_position}",
-                    move_to_prompt = MoveTo(0, search_opts.rows),
+                    move_to_prompt = MoveTo(0, search_opts.prompt_row()),
                     clear_line = Clear(ClearType::CurrentLine),
-                    move_to_position = MoveTo(cursor_position, search_opts.rows),
+                    move_to_position = MoveTo(cursor_position, search_opts.prompt_row()),
                 )
                 .unwrap();
             }
             assert_eq!(out, result_out);
         }
+
+        #[test]
+        fn recall_history() {
+            let history = ["first".to_string(), "second".to_string()];
+            let mut search_opts = new_search_opts(SearchMode::Forward);
+            search_opts.history = &history;
+            let mut out = Vec::new();
+            let mut press = |so: &mut SearchOpts<'_>, kc| {
+                so.ev = Some(make_event_from_keycode(kc));
+                handle_key_press(&mut out, so, |_| false).unwrap();
+            };
+
+            press(&mut search_opts, KeyCode::Char('x'));
+            press(&mut search_opts, KeyCode::Up);
+            assert_eq!(search_opts.string, "second");
+            assert_eq!(search_opts.cursor_position, 7);
+            press(&mut search_opts, KeyCode::Up);
+            press(&mut search_opts, KeyCode::Up);
+            assert_eq!(search_opts.string, "first");
+
+            press(&mut search_opts, KeyCode::Down);
+            assert_eq!(search_opts.string, "second");
+            // Moving past the most recent entry restores the typed query
+            press(&mut search_opts, KeyCode::Down);
+            assert_eq!(search_opts.string, "x");
+            press(&mut search_opts, KeyCode::Down);
+            assert_eq!(search_opts.string, "x");
+        }
+
+        #[test]
+        fn toggle_search_options() {
+            let (mut search_opts, mut out, last_movable_column, _) = pretest_setup_forward_search();
+
+            search_opts.ev = Some(Event::Key(KeyEvent::new(
+                KeyCode::Char('r'),
+                KeyModifiers::CONTROL,
+            )));
+            handle_key_press(&mut out, &mut search_opts, |_| false).unwrap();
+            assert!(search_opts.literal);
+
+            let alt_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::ALT));
+            for case_sensitivity in [
+                CaseSensitivity::Smart,
+                CaseSensitivity::Insensitive,
+                CaseSensitivity::Sensitive,
+            ] {
+                search_opts.ev = Some(alt_c.clone());
+                handle_key_press(&mut out, &mut search_opts, |_| false).unwrap();
+                assert_eq!(search_opts.case_sensitivity, case_sensitivity);
+            }
+
+            // The query is left untouched
+            assert_eq!(search_opts.string, "this is@complex-text_search?query");
+            assert_eq!(search_opts.cursor_position, last_movable_column);
+        }
+    }
+
+    mod history {
+        use super::super::{SearchHistory, MAX_HISTORY_ENTRIES};
+        use std::fs;
+
+        #[test]
+        fn recent_entries_last() {
+            let mut history = SearchHistory::default();
+            history.extend(["one", "two", "", "three\nfour", "one"].map(String::from));
+            assert_eq!(history.entries(), ["two", "one"]);
+
+            history.extend((0..MAX_HISTORY_ENTRIES).map(|i| i.to_string()));
+            assert_eq!(history.entries().len(), MAX_HISTORY_ENTRIES);
+            assert_eq!(history.entries()[0], "0");
+        }
+
+        #[test]
+        fn file() {
+            let path = std::env::temp_dir().join(format!("minus-history-{}", std::process::id()));
+            fs::write(&path, "stored\nseeded\n").unwrap();
+
+            let mut history = SearchHistory::default();
+            history.extend([String::from("seeded")]);
+            history.set_file(path.clone()).unwrap();
+            assert_eq!(history.entries(), ["stored", "seeded"]);
+
+            history.push(String::from("new")).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "stored\nseeded\nnew\n");
+            fs::remove_file(&path).unwrap();
+
+            // A missing file is an empty history
+            let mut history = SearchHistory::default();
+            history.set_file(path.clone()).unwrap();
+            assert!(history.entries().is_empty());
+        }
+    }
+
+    #[test]
+    fn compile_query() {
+        use super::{compile_query, CaseSensitivity};
+
+        let sensitive = compile_query("Foo", CaseSensitivity::Sensitive, false).unwrap();
+        assert!(!sensitive.is_match("foo"));
+
+        let smart = compile_query("foo", CaseSensitivity::Smart, false).unwrap();
+        assert!(smart.is_match("FOO"));
+        let smart = compile_query("Foo", CaseSensitivity::Smart, false).unwrap();
+        assert!(!smart.is_match("foo"));
+
+        let insensitive = compile_query("Foo", CaseSensitivity::Insensitive, false).unwrap();
+        assert!(insensitive.is_match("fOO"));
+
+        assert!(compile_query("foo(bar", CaseSensitivity::Sensitive, false).is_err());
+        let literal = compile_query("foo(bar)", CaseSensitivity::Sensitive, true).unwrap();
+        assert!(literal.is_match("call foo(bar)"));
+        assert!(!literal.is_match("call foobar"));
+    }
+
+    #[test]
+    fn highlight() {
+        use super::{next_highlight_style, Highlight};
+        use crossterm::style::{Color, ContentStyle, Stylize};
+        use regex::Regex;
+
+        let style = ContentStyle::new().red().on_blue().bold();
+        let highlight = Highlight::new(Regex::new("b+").unwrap(), style);
+        assert_eq!(
+            highlight.apply("abba cab").unwrap(),
+            "a\x1b[38;5;9m\x1b[48;5;12m\x1b[1mbb\x1b[39m\x1b[49m\x1b[22ma ca\
+             \x1b[38;5;9m\x1b[48;5;12m\x1b[1mb\x1b[39m\x1b[49m\x1b[22m"
+        );
+        assert_eq!(highlight.apply("none"), None);
+
+        // Colors that are in use are skipped
+        let yellow = Highlight::new(Regex::new("a").unwrap(), ContentStyle::new().on_yellow());
+        assert_eq!(
+            next_highlight_style(&[yellow]).background_color,
+            Some(Color::Red)
+        );
+        assert_eq!(
+            next_highlight_style(&[highlight]).background_color,
+            Some(Color::Yellow)
+        );
     }
 
     #[test]
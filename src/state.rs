@@ -1,35 +1,53 @@
 //! Contains types that hold run-time information of the pager.
 
 #[cfg(feature = "search")]
-use crate::search::{SearchMode, SearchOpts};
+use crate::search::{
+    self, CaseSensitivity, Filter, Highlight, SearchHistory, SearchMode, SearchOpts,
+};
 
 use crate::{
-    error::{MinusError, TermError},
-    input::{self, HashedEventRegister},
+    backend::{CrosstermEvents, Terminal},
+    error::{CleanupError, MinusError, SetupError},
+    hints::{self, Hint, HintAction, Hints},
+    input::{self, HashedEventRegister, MarkAction},
     minus_core::{
-        self,
-        utils::{display::AppendStyle, LinesRowMap},
+        utils::{
+            display::{self, AppendStyle},
+            selection::{self, Position, Selection},
+            syntax::SyntaxHighlighting,
+            LinesRowMap,
+        },
         CommandQueue,
     },
-    screen::{self, Screen},
+    prompt::{self, PromptTheme, PromptValues},
+    save::{self, SaveOptions, SaveRegion},
+    screen::{
+        self,
+        gutter::{split_gutter, Gutter, NumberWidth},
+        Screen,
+    },
     ExitStrategy, LineNumbers,
 };
-use crossterm::{terminal, tty::IsTty};
-#[cfg(feature = "search")]
-use parking_lot::Condvar;
-use parking_lot::Mutex;
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    terminal::{Clear, ClearType},
+};
+use parking_lot::{Condvar, Mutex};
 #[cfg(feature = "search")]
 use std::collections::BTreeSet;
 use std::{
-    collections::hash_map::RandomState,
+    borrow::Cow,
+    collections::{hash_map::RandomState, BTreeMap},
     convert::TryInto,
-    io::stdout,
-    io::Stdout,
+    io,
+    ops::RangeInclusive,
+    path::Path,
     sync::{atomic::AtomicBool, Arc},
 };
 
 use crate::minus_core::{commands::Command, ev_handler::handle_event};
-use crossbeam_channel::Receiver;
+use flume::Receiver;
 
 #[cfg(feature = "search")]
 #[cfg_attr(docsrs, doc(cfg(feature = "search")))]
@@ -42,6 +60,16 @@ pub struct SearchState {
     pub search_mode: SearchMode,
     /// Stores the most recent search term
     pub(crate) search_term: Option<regex::Regex>,
+    /// Query from which [`SearchState::search_term`] has been compiled
+    pub(crate) search_query: String,
+    /// How the letter case is treated when searching
+    pub(crate) case_sensitivity: CaseSensitivity,
+    /// Whether search queries are matched as plain text rather than as regular expressions
+    pub(crate) literal: bool,
+    /// Queries that have been searched for
+    pub(crate) history: SearchHistory,
+    /// Patterns highlighted in their own styles in all buffers
+    pub(crate) highlights: Vec<Highlight>,
     /// Lines where searches have a match
     /// In order to avoid duplicate entries of lines, we keep it in a [`BTreeSet`]
     pub(crate) search_idx: BTreeSet<usize>,
@@ -71,6 +99,11 @@ impl Default for SearchState {
         Self {
             search_mode: SearchMode::Unknown,
             search_term: None,
+            search_query: String::new(),
+            case_sensitivity: CaseSensitivity::Sensitive,
+            literal: false,
+            history: SearchHistory::default(),
+            highlights: Vec::new(),
             search_idx: BTreeSet::new(),
             search_mark: 0,
             incremental_search_condition,
@@ -78,6 +111,91 @@ impl Default for SearchState {
     }
 }
 
+/// A position entered at the goto prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GotoTarget {
+    /// One based line number
+    Line(usize),
+    /// Percentage of the lines
+    Percent(usize),
+    /// Byte offset from the start of the text
+    Byte(u64),
+}
+
+impl GotoTarget {
+    /// Parse a line number `N`, a percentage `N%` or a byte offset `Nb`
+    pub(crate) fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(percent) = input.strip_suffix('%') {
+            return percent.parse().ok().map(Self::Percent);
+        }
+        if let Some(offset) = input.strip_suffix('b') {
+            return offset.parse().ok().map(Self::Byte);
+        }
+        input.parse().ok().map(Self::Line)
+    }
+}
+
+/// A document held by the pager
+///
+/// Only the buffer that is being displayed lives in the fields of [`PagerState`]. The rest of
+/// the buffers are parked here until they are switched to.
+pub(crate) struct Buffer {
+    pub(crate) name: String,
+    pub(crate) screen: Screen,
+    pub(crate) upper_mark: usize,
+    pub(crate) left_mark: usize,
+    pub(crate) line_numbers: LineNumbers,
+    pub(crate) lines_to_row_map: LinesRowMap,
+    pub(crate) marks: BTreeMap<char, usize>,
+    #[cfg(feature = "search")]
+    pub(crate) search_mode: SearchMode,
+    #[cfg(feature = "search")]
+    pub(crate) search_term: Option<regex::Regex>,
+    #[cfg(feature = "search")]
+    pub(crate) search_query: String,
+    #[cfg(feature = "search")]
+    pub(crate) search_idx: BTreeSet<usize>,
+    #[cfg(feature = "search")]
+    pub(crate) search_mark: usize,
+}
+
+impl Buffer {
+    const fn new(name: String, screen: Screen, line_numbers: LineNumbers) -> Self {
+        Self {
+            name,
+            screen,
+            upper_mark: 0,
+            left_mark: 0,
+            line_numbers,
+            lines_to_row_map: LinesRowMap::new(),
+            marks: BTreeMap::new(),
+            #[cfg(feature = "search")]
+            search_mode: SearchMode::Unknown,
+            #[cfg(feature = "search")]
+            search_term: None,
+            #[cfg(feature = "search")]
+            search_query: String::new(),
+            #[cfg(feature = "search")]
+            search_idx: BTreeSet::new(),
+            #[cfg(feature = "search")]
+            search_mark: 0,
+        }
+    }
+}
+
+/// Layout of the view when it is split into two panes
+///
+/// The focused pane scrolls with [`PagerState::upper_mark`] while the other one keeps its own
+/// upper mark here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Split {
+    /// Upper mark of the pane that is not focused
+    pub(crate) other_upper_mark: usize,
+    /// Whether the bottom pane is focused
+    pub(crate) bottom_focused: bool,
+}
+
 /// Holds all information and configuration about the pager during
 /// its run time.
 ///
@@ -87,7 +205,7 @@ impl Default for SearchState {
 ///
 /// Various fields are made public so that their values can be accessed while implementing the
 /// trait.
-#[allow(clippy::module_name_repetitions)]
+#[allow(clippy::module_name_repetitions, clippy::struct_excessive_bools)]
 pub struct PagerState {
     /// Configuration for line numbers. See [`LineNumbers`]
     pub line_numbers: LineNumbers,
@@ -127,7 +245,7 @@ pub struct PagerState {
     /// until any of `j`, `k`, `G`, `Up` or `Down` is pressed
     pub prefix_num: String,
     /// Describes whether minus is running and in which mode
-    pub running: &'static Mutex<crate::RunMode>,
+    pub running: Arc<Mutex<crate::RunMode>>,
     #[cfg(feature = "search")]
     #[cfg_attr(docsrs, cfg(feature = "search"))]
     pub search_state: SearchState,
@@ -147,34 +265,70 @@ pub struct PagerState {
     pub(crate) displayed_prompt: String,
     /// Whether to show the prompt on the screen
     pub(crate) show_prompt: bool,
+    /// Layout of the prompt, see [`Pager::set_prompt_format`](crate::Pager::set_prompt_format)
+    pub(crate) prompt_format: Option<String>,
+    /// Styles of the parts of the prompt
+    pub(crate) prompt_theme: PromptTheme,
     /// Do we want to page if there is no overflow
-    #[cfg(feature = "static_output")]
     pub(crate) run_no_overflow: bool,
     pub(crate) lines_to_row_map: LinesRowMap,
     /// Value for follow mode.
     /// See [follow_output](crate::pager::Pager::follow_output) for more info on follow mode.
     pub(crate) follow_output: bool,
+    /// Number of readers given to [push_reader](crate::pager::Pager::push_reader) that are
+    /// still being read
+    pub(crate) loading: usize,
+    /// Whether the application has signalled that all of the text has been given, see
+    /// [`Pager::end_of_input`](crate::Pager::end_of_input)
+    pub(crate) input_complete: bool,
+    /// Whether the text is written to the main screen once the pager quits, because all of it
+    /// fit on the screen when the input was complete
+    pub(crate) print_on_exit: bool,
+    /// Frame of the spinner shown while waiting for data
+    pub(crate) spinner: usize,
+    /// All the buffers held by the pager
+    ///
+    /// The entry of the current buffer only holds its name, the rest of it is stored in the
+    /// fields of this type.
+    pub(crate) buffers: Vec<Buffer>,
+    /// Index of the buffer that is being displayed
+    pub(crate) current_buffer: usize,
+    /// Layout of the panes if the view is split
+    pub(crate) split: Option<Split>,
+    /// Zero based line numbers of the marks of the current buffer, shared with the
+    /// [`Pager`](crate::Pager)
+    pub(crate) marks: Arc<Mutex<BTreeMap<char, usize>>>,
+    /// Whether the next key names a mark to set or jump to
+    pub(crate) pending_mark: Option<MarkAction>,
+    /// Text selected with the mouse
+    pub(crate) selection: Option<Selection>,
+    /// What to do with the link or file location selected in hints mode
+    pub(crate) hint_action: Option<HintAction>,
+    /// Hints labelled on the screen while in hints mode
+    pub(crate) hints: Option<Hints>,
+    /// Options for saving the text with the `s` command
+    pub(crate) save_options: SaveOptions,
+    /// Whether users can save the text with the `s` command
+    pub(crate) saving_allowed: bool,
+    /// Whether users can run an editor with `v` or a shell command with `!`
+    pub(crate) external_commands_allowed: bool,
+    /// Number of rows taken up by the pager when it is drawn inline below the cursor instead of
+    /// on the alternate screen
+    pub(crate) inline_rows: Option<usize>,
+    /// Row of the terminal where the pager starts, which is only non zero when drawn inline
+    pub(crate) top: usize,
 }
 
 impl PagerState {
-    pub(crate) fn new() -> Result<Self, TermError> {
-        let (rows, cols);
-
-        if cfg!(test) {
-            // In tests, set  number of columns to 80 and rows to 10
-            cols = 80;
-            rows = 10;
-        } else if stdout().is_tty() {
-            // If a proper terminal is present, get size and set it
-            let size = terminal::size()?;
-            cols = size.0 as usize;
-            rows = size.1 as usize;
-        } else {
-            // For other cases beyond control
-            cols = 1;
-            rows = 1;
-        };
+    /// Create a [`PagerState`] with 80 columns and 10 rows for use in tests
+    #[cfg(test)]
+    #[allow(clippy::unnecessary_wraps)]
+    pub(crate) fn new() -> Result<Self, crate::error::TermError> {
+        Ok(Self::with_size(80, 10))
+    }
 
+    /// Create a [`PagerState`] for a terminal having `cols` columns and `rows` rows
+    pub(crate) fn with_size(cols: usize, rows: usize) -> Self {
         let prompt = std::env::current_exe()
             .unwrap_or_else(|_| std::path::PathBuf::from("minus"))
             .file_name()
@@ -189,7 +343,7 @@ impl PagerState {
             line_numbers: LineNumbers::Disabled,
             upper_mark: 0,
             prompt,
-            running: &minus_core::RUNMODE,
+            running: Arc::new(Mutex::new(crate::RunMode::Uninitialized)),
             left_mark: 0,
             exit_strategy: ExitStrategy::ProcessQuit,
             input_classifier: Box::<HashedEventRegister<RandomState>>::default(),
@@ -198,49 +352,71 @@ impl PagerState {
             screen: Screen::default(),
             displayed_prompt: String::new(),
             show_prompt: true,
-            #[cfg(feature = "static_output")]
+            prompt_format: None,
+            prompt_theme: PromptTheme::default(),
             run_no_overflow: false,
             #[cfg(feature = "search")]
             search_mode: SearchMode::default(),
             #[cfg(feature = "search")]
             search_state: SearchState::default(),
-            // Just to be safe in tests, keep at 1x1 size
             cols,
             rows,
             prefix_num: String::new(),
             lines_to_row_map: LinesRowMap::new(),
             follow_output: false,
+            loading: 0,
+            input_complete: false,
+            print_on_exit: false,
+            spinner: 0,
+            buffers: vec![Buffer::new(
+                String::new(),
+                Screen::default(),
+                LineNumbers::Disabled,
+            )],
+            current_buffer: 0,
+            split: None,
+            marks: Arc::default(),
+            pending_mark: None,
+            selection: None,
+            hint_action: None,
+            hints: None,
+            save_options: SaveOptions::default(),
+            saving_allowed: true,
+            external_commands_allowed: false,
+            inline_rows: None,
+            top: 0,
         };
 
         state.format_prompt();
-        Ok(state)
+        state
     }
 
     /// Generate the initial [`PagerState`]
     ///
     /// [`init_core`](crate::minus_core::init::init_core) calls this functions for creating the PagerState.
     ///
-    /// This function creates a default [`PagerState`] and fetches all events present in the receiver
-    /// to create the initial state. This is done before starting the pager so that
-    /// the optimizationss can be applied.
+    /// This function creates a default [`PagerState`] sized to fit `out` and fetches all events
+    /// present in the receiver to create the initial state. This is done before starting the
+    /// pager so that the optimizationss can be applied.
     ///
     /// # Errors
-    /// This function will return an error if it could not create the default [`PagerState`] or fails
+    /// This function will return an error if it could not get the size of `out` or fails
     /// to process the events
     pub(crate) fn generate_initial_state(
         rx: &Receiver<Command>,
-        mut out: &mut Stdout,
+        out: &mut impl Terminal,
     ) -> Result<Self, MinusError> {
-        let mut ps = Self::new()?;
-        let mut command_queue = CommandQueue::new_zero();
+        let (cols, rows) = out.size().map_err(|e| SetupError::TerminalSize(e.into()))?;
+        let mut ps = Self::with_size(cols.into(), rows.into());
+        let mut command_queue = CommandQueue::new_zero(ps.running.clone());
         rx.try_iter().try_for_each(|ev| -> Result<(), MinusError> {
             handle_event(
                 ev,
-                &mut out,
+                out,
                 &mut ps,
                 &mut command_queue,
                 &Arc::new(AtomicBool::new(false)),
-                #[cfg(feature = "search")]
+                &Mutex::new(CrosstermEvents),
                 &Arc::new((Mutex::new(true), Condvar::new())),
             )
         })?;
@@ -248,113 +424,1046 @@ impl PagerState {
     }
 
     pub(crate) fn format_lines(&mut self) {
-        let (buffer, format_result) = screen::make_format_lines(
-            &self.screen.orig_text,
+        // The selected and labelled rows may no longer hold the same text
+        self.selection = None;
+        self.hints = None;
+        let keep = self.screen.window_around(self.upper_mark, self.rows);
+        let res = screen::make_format_lines(
+            &*self.screen.store,
             self.line_numbers,
+            &self.screen.gutter,
+            self.screen.dropped_lines,
             self.cols,
             self.screen.line_wrapping,
             #[cfg(feature = "search")]
             &self.search_state.search_term,
+            #[cfg(feature = "search")]
+            self.screen.filter.as_ref(),
+            #[cfg(feature = "search")]
+            &self.search_state.highlights,
+            self.screen.syntax.as_mut(),
+            keep,
         );
 
+        match res {
+            Ok((buffer, buffer_start, format_result)) => {
+                #[cfg(feature = "search")]
+                {
+                    self.search_state.search_idx = format_result.append_search_idx;
+                }
+                self.screen.formatted_lines = buffer;
+                self.screen.window_start = buffer_start;
+                self.screen.rows_count = format_result.rows_formatted;
+                self.lines_to_row_map = format_result.lines_to_row_map;
+                self.screen.max_line_length = format_result.max_line_length;
+
+                self.screen.unterminated = format_result.num_unterminated;
+            }
+            Err(e) => self.storage_error(&e),
+        }
+        self.format_prompt();
+    }
+
+    /// Make sure that the formatted rows from `start` up to `end` are kept in memory
+    ///
+    /// This formats the rows again if they were dropped due to
+    /// [`Pager::set_max_formatted_rows`](crate::Pager::set_max_formatted_rows).
+    pub(crate) fn load_rows(&mut self, start: usize, end: usize) {
+        let end = end.min(self.screen.formatted_lines_count());
+        if self.screen.has_rows(start, end) {
+            return;
+        }
+        let keep = self.screen.window_around(start, end - start);
+        let keep_end = keep.end.min(self.screen.formatted_lines_count());
+
+        let first_line = self.lines_to_row_map.line_of_row(keep.start);
+        let mut row = self.lines_to_row_map.get(first_line).copied().unwrap_or(0);
+        let window_start = row;
+        let line_number_digits = self.screen.line_number_digits();
+        let mut buffer = Vec::with_capacity(keep_end - window_start);
         #[cfg(feature = "search")]
-        {
-            self.search_state.search_idx = format_result.append_search_idx;
+        let mut search_idx = BTreeSet::new();
+
+        let store = &*self.screen.store;
+        let mut error = self
+            .screen
+            .syntax
+            .as_mut()
+            .and_then(|syntax| syntax.seek(store, first_line).err());
+        let line_count = self.screen.store.line_count();
+        for (idx, line) in self.screen.store.lines_from(first_line).enumerate() {
+            if row >= keep_end || error.is_some() {
+                break;
+            }
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            };
+            let highlighted = self
+                .screen
+                .syntax
+                .as_mut()
+                .map_or(Cow::Borrowed(&*line), |s| {
+                    s.highlight_line(first_line + idx, &line, first_line + idx + 1 == line_count)
+                });
+            let rows = screen::formatted_line(
+                &highlighted,
+                line_number_digits,
+                self.screen.dropped_lines + first_line + idx,
+                self.line_numbers,
+                &self.screen.gutter,
+                self.cols,
+                self.screen.line_wrapping,
+                #[cfg(feature = "search")]
+                row,
+                #[cfg(feature = "search")]
+                &mut search_idx,
+                #[cfg(feature = "search")]
+                &self.search_state.search_term,
+                #[cfg(feature = "search")]
+                self.screen.filter.as_ref(),
+                #[cfg(feature = "search")]
+                &self.search_state.highlights,
+            );
+            row += rows.len();
+            buffer.extend(rows);
+        }
+        if let Some(e) = error {
+            self.storage_error(&e);
+            self.format_prompt();
+            return;
         }
         self.screen.formatted_lines = buffer;
-        self.lines_to_row_map = format_result.lines_to_row_map;
-        self.screen.max_line_length = format_result.max_line_length;
+        self.screen.window_start = window_start;
+    }
 
-        self.screen.unterminated = format_result.num_unterminated;
+    /// Drop the oldest lines that exceed [`Pager::set_max_lines`](crate::Pager::set_max_lines)
+    ///
+    /// The upper marks, the selection and the search matches are moved along with the remaining
+    /// rows so that they keep pointing to the same text. Returns the number of rows that have
+    /// been dropped.
+    pub(crate) fn trim_lines(&mut self) -> usize {
+        let Some(max_lines) = self.screen.max_lines else {
+            return 0;
+        };
+        // Always keep the last line, it may be continued by the next append
+        let lines = self.screen.line_count().saturating_sub(max_lines.max(1));
+        if lines == 0 {
+            return 0;
+        }
+        let rows = self.lines_to_row_map.get(lines).copied().unwrap_or(0);
+        if let Err(e) = self.screen.drop_lines(lines, rows) {
+            self.storage_error(&e);
+            self.format_prompt();
+            return 0;
+        }
+        self.lines_to_row_map.drop_lines(lines, rows);
+        self.upper_mark = self.upper_mark.saturating_sub(rows);
+        if let Some(split) = &mut self.split {
+            split.other_upper_mark = split.other_upper_mark.saturating_sub(rows);
+        }
+        if let Some(selection) = &mut self.selection {
+            if !selection.drop_rows(rows) {
+                self.selection = None;
+            }
+        }
+        // Marks on the dropped lines are removed while the rest move along with their lines
+        self.marks
+            .lock()
+            .retain(|_, line| line.checked_sub(lines).map(|kept| *line = kept).is_some());
+        #[cfg(feature = "search")]
+        {
+            let search_state = &mut self.search_state;
+            let kept = search_state.search_idx.split_off(&rows);
+            search_state.search_mark = search_state
+                .search_mark
+                .saturating_sub(search_state.search_idx.len());
+            search_state.search_idx = kept.into_iter().map(|i| i - rows).collect();
+        }
+        // The kept lines keep their numbers so none of the rows need to be formatted again
         self.format_prompt();
+        rows
     }
 
-    /// Reformat the inputted prompt to how it should be displayed
-    pub(crate) fn format_prompt(&mut self) {
-        const PROMPT_SPEC: &str = "\x1b[2;40;37m";
-        const SEARCH_SPEC: &str = "\x1b[30;44m";
-        const INPUT_SPEC: &str = "\x1b[30;43m";
-        const MSG_SPEC: &str = "\x1b[30;1;41m";
-        const RESET: &str = "\x1b[0m";
-        const FOLLOW_MODE_SPEC: &str = "\x1b[1m";
+    /// Replace the text of the current buffer with `text`
+    pub(crate) fn set_text(&mut self, text: &str) {
+        if let Err(e) = self.screen.set_text(text) {
+            self.storage_error(&e);
+        }
+        self.format_lines();
+        self.screen.line_count = self.screen.store.line_count();
+        self.trim_lines();
+    }
 
-        // Allocate the string. Add extra space in case for the
-        // ANSI escape things if we do have characters typed and search showing
-        let mut format_string = String::with_capacity(self.cols + (SEARCH_SPEC.len() * 5) + 4);
+    /// Set the highlighter of the current buffer and format the text again
+    pub(crate) fn set_highlighter(&mut self, syntax: Option<SyntaxHighlighting>) {
+        self.screen.syntax = syntax;
+        self.format_lines();
+    }
 
-        // Get the string that will contain the search index/match indicator
+    /// Set the gutter of the current buffer and format the text again
+    pub(crate) fn set_gutter(&mut self, gutter: Gutter) {
+        self.screen.gutter = gutter;
+        self.format_lines();
+    }
+
+    /// Set the filter of the current buffer and format the text again
+    ///
+    /// The view stays at the line that was at the top, or at the first line displayed after it
+    /// if that line is hidden now.
+    #[cfg(feature = "search")]
+    pub(crate) fn set_filter(&mut self, filter: Option<Filter>) {
+        let top_line = self.lines_to_row_map.line_of_row(self.upper_mark);
+        let other_top_line = self
+            .split
+            .map(|split| self.lines_to_row_map.line_of_row(split.other_upper_mark));
+        self.screen.filter = filter;
+        self.format_lines();
+
+        let rows_count = self.screen.formatted_lines_count();
+        let lines_to_row_map = &self.lines_to_row_map;
+        let row_of_line = |line| lines_to_row_map.get(line).map_or(rows_count, |row| *row);
+        self.upper_mark = row_of_line(top_line);
+        if let (Some(split), Some(line)) = (&mut self.split, other_top_line) {
+            split.other_upper_mark = row_of_line(line);
+        }
+        self.search_state.search_mark = 0;
+    }
+
+    /// Add a highlight and format the text again
+    ///
+    /// A highlight with the same pattern is replaced.
+    #[cfg(feature = "search")]
+    pub(crate) fn add_highlight(&mut self, highlight: Highlight) {
+        let highlights = &mut self.search_state.highlights;
+        if let Some(existing) = highlights
+            .iter_mut()
+            .find(|h| h.pattern().as_str() == highlight.pattern().as_str())
+        {
+            *existing = highlight;
+        } else {
+            highlights.push(highlight);
+        }
+        self.format_lines();
+    }
+
+    /// Remove the highlight whose pattern is `pattern`, or all of them if it is `None`
+    ///
+    /// Returns `true` if the text has been formatted again without the removed highlights.
+    #[cfg(feature = "search")]
+    pub(crate) fn remove_highlights(&mut self, pattern: Option<&str>) -> bool {
+        let highlights = &mut self.search_state.highlights;
+        let old_len = highlights.len();
+        highlights.retain(|h| matches!(pattern, Some(p) if h.pattern().as_str() != p));
+        if highlights.len() == old_len {
+            return false;
+        }
+        self.format_lines();
+        true
+    }
+
+    /// Compile the search query of the current buffer again with the current search options
+    ///
+    /// Returns `false` if the query is not valid anymore, in which case the search is cleared.
+    #[cfg(feature = "search")]
+    pub(crate) fn compile_search_term(&mut self) -> bool {
+        let search_state = &mut self.search_state;
+        if search_state.search_query.is_empty() {
+            return true;
+        }
+        search_state.search_term = search::compile_query(
+            &search_state.search_query,
+            search_state.case_sensitivity,
+            search_state.literal,
+        )
+        .ok();
+        search_state.search_mark = 0;
+        if search_state.search_term.is_none() {
+            search_state.search_query.clear();
+            search_state.search_idx.clear();
+            return false;
+        }
+        true
+    }
+
+    /// Change the search options and apply them to the search of the current buffer
+    ///
+    /// Returns `true` if the text has been formatted again with the new options.
+    #[cfg(feature = "search")]
+    pub(crate) fn set_search_options(
+        &mut self,
+        case_sensitivity: CaseSensitivity,
+        literal: bool,
+    ) -> bool {
+        let search_state = &mut self.search_state;
+        if search_state.case_sensitivity == case_sensitivity && search_state.literal == literal {
+            return false;
+        }
+        search_state.case_sensitivity = case_sensitivity;
+        search_state.literal = literal;
+        if search_state.search_query.is_empty() {
+            return false;
+        }
+        self.compile_search_term();
+        self.format_lines();
+        true
+    }
+
+    /// Returns the name of the buffer that is being displayed
+    #[must_use]
+    pub fn buffer_name(&self) -> &str {
+        &self.buffers[self.current_buffer].name
+    }
+
+    /// Returns the index of the buffer that is being displayed
+    #[must_use]
+    pub const fn buffer_index(&self) -> usize {
+        self.current_buffer
+    }
+
+    /// Returns the number of buffers held by the pager
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // Vec::len is not const on older Rust versions
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Returns the index of the buffer named `name`
+    pub(crate) fn find_buffer(&self, name: &str) -> Option<usize> {
+        self.buffers.iter().position(|b| b.name == name)
+    }
+
+    /// Swap the buffer at `idx` with the one held in the fields of this type
+    fn exchange_buffer(&mut self, idx: usize) {
+        let buffer = &mut self.buffers[idx];
+        std::mem::swap(&mut self.screen, &mut buffer.screen);
+        std::mem::swap(&mut self.upper_mark, &mut buffer.upper_mark);
+        std::mem::swap(&mut self.left_mark, &mut buffer.left_mark);
+        std::mem::swap(&mut self.line_numbers, &mut buffer.line_numbers);
+        std::mem::swap(&mut self.lines_to_row_map, &mut buffer.lines_to_row_map);
+        // The map stays shared with the `Pager`, which always refers to the displayed buffer
+        std::mem::swap(&mut *self.marks.lock(), &mut buffer.marks);
         #[cfg(feature = "search")]
-        let mut search_str = String::new();
+        {
+            let search_state = &mut self.search_state;
+            std::mem::swap(&mut search_state.search_mode, &mut buffer.search_mode);
+            std::mem::swap(&mut search_state.search_term, &mut buffer.search_term);
+            std::mem::swap(&mut search_state.search_query, &mut buffer.search_query);
+            std::mem::swap(&mut search_state.search_idx, &mut buffer.search_idx);
+            std::mem::swap(&mut search_state.search_mark, &mut buffer.search_mark);
+            self.search_mode = search_state.search_mode;
+        }
+    }
+
+    /// Run `f` with the buffer at `idx` temporarily made the current buffer
+    ///
+    /// Nothing should be drawn while the buffer is swapped in.
+    pub(crate) fn with_buffer<F>(&mut self, idx: usize, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        if idx == self.current_buffer {
+            f(self);
+            return;
+        }
+        self.exchange_buffer(self.current_buffer);
+        self.exchange_buffer(idx);
+        // The panes, the selection and the hints belong to the displayed buffer, so they are kept
+        // away from changes to the other buffer
+        let split = self.split.take();
+        let selection = self.selection.take();
+        let hints = self.hints.take();
+        f(self);
+        self.split = split;
+        self.selection = selection;
+        self.hints = hints;
+        self.exchange_buffer(idx);
+        self.exchange_buffer(self.current_buffer);
+        self.format_prompt();
+    }
+
+    /// Display the buffer at `idx`
+    ///
+    /// Returns `false` if there is no such buffer or it is already being displayed.
+    pub(crate) fn switch_buffer(&mut self, idx: usize) -> bool {
+        if idx == self.current_buffer || idx >= self.buffers.len() {
+            return false;
+        }
+        self.exchange_buffer(self.current_buffer);
+        self.exchange_buffer(idx);
+        self.current_buffer = idx;
+        // The search options may have changed since the buffer was last displayed
         #[cfg(feature = "search")]
-        if !self.search_state.search_idx.is_empty() {
-            search_str.push(' ');
-            search_str.push_str(&(self.search_state.search_mark + 1).to_string());
-            search_str.push('/');
-            search_str.push_str(&self.search_state.search_idx.len().to_string());
-            search_str.push(' ');
+        self.compile_search_term();
+        // Both panes start at the position of the buffer that is switched to
+        if let Some(split) = &mut self.split {
+            split.other_upper_mark = self.upper_mark;
         }
+        // The terminal may have been resized since the buffer was last displayed
+        self.format_lines();
+        true
+    }
 
-        // And get the string that will contain the prefix_num
-        let mut prefix_str = String::new();
-        if !self.prefix_num.is_empty() {
-            prefix_str.push(' ');
-            prefix_str.push_str(&self.prefix_num);
-            prefix_str.push(' ');
+    /// Returns the number of rows available for displaying the text in the focused pane
+    ///
+    /// This is one less than [`PagerState::rows`] to leave space for the prompt, unless the
+    /// view is split in which case it is the height of the focused pane.
+    #[must_use]
+    pub fn pane_rows(&self) -> usize {
+        let writable_rows = self.rows.saturating_sub(1);
+        self.split.map_or(writable_rows, |split| {
+            let (top, bottom) = Self::pane_heights(writable_rows);
+            if split.bottom_focused {
+                bottom
+            } else {
+                top
+            }
+        })
+    }
+
+    /// Returns the row of the terminal where the prompt is displayed
+    pub(crate) fn prompt_row(&self) -> u16 {
+        (self.top + self.rows.saturating_sub(1))
+            .try_into()
+            .unwrap_or(u16::MAX)
+    }
+
+    /// Number of rows taken up by the pager on a terminal having `term_rows` rows
+    ///
+    /// When drawn inline, at least the prompt and one row of text are displayed.
+    pub(crate) fn fit_rows(&self, term_rows: usize) -> usize {
+        self.inline_rows
+            .map_or(term_rows, |rows| rows.max(2).min(term_rows))
+    }
+
+    /// Prepare `out` for paging, either on the alternate screen or inline below the cursor
+    pub(crate) fn setup_terminal(&mut self, out: &mut impl Terminal) -> Result<(), SetupError> {
+        if self.inline_rows.is_none() {
+            return out.setup();
+        }
+        self.top = out
+            .setup_inline(self.rows.try_into().unwrap_or(u16::MAX))?
+            .into();
+        Ok(())
+    }
+
+    /// Restore `out` after paging
+    ///
+    /// When drawn inline, the last page is left on the screen while the prompt is cleared so
+    /// that whatever comes next starts on its row.
+    pub(crate) fn cleanup_terminal(&self, out: &mut impl Terminal) -> Result<(), CleanupError> {
+        if self.inline_rows.is_none() {
+            return out.cleanup();
+        }
+        execute!(
+            out,
+            MoveTo(0, self.prompt_row()),
+            Clear(ClearType::CurrentLine)
+        )
+        .map_err(|e| CleanupError::ClearPrompt(e.into()))?;
+        out.cleanup_inline()
+    }
+
+    /// Restore `out` once the pager quits
+    ///
+    /// If it quit because all of the text fit on the screen once the input was complete, the text
+    /// is then written to the main screen.
+    pub(crate) fn cleanup_on_exit(&mut self, out: &mut impl Terminal) -> crate::Result {
+        self.cleanup_terminal(out)?;
+        if self.print_on_exit {
+            self.write_all_rows(out)?;
+        }
+        Ok(())
+    }
+
+    /// Whether all of the text fits on the screen, in which case it need not be paged
+    ///
+    /// This is always `false` if [`Pager::set_run_no_overflow`](crate::Pager::set_run_no_overflow)
+    /// is turned on.
+    pub(crate) const fn fits_on_screen(&self) -> bool {
+        !self.run_no_overflow && self.screen.formatted_lines_count() <= self.rows
+    }
+
+    /// Write all the rows of text to `out`, which is assumed to fit on the screen
+    pub(crate) fn write_all_rows(&mut self, out: &mut impl io::Write) -> crate::Result {
+        self.load_rows(0, self.rows);
+        display::write_raw_lines(out, &self.screen.formatted_lines, Some("\r"))?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Returns the first row of the zero based line number `line`
+    ///
+    /// A line hidden by a filter gives the row of the next displayed line. Lines past the end
+    /// give the number of rows.
+    #[must_use]
+    pub fn row_of_line(&self, line: usize) -> usize {
+        self.lines_to_row_map
+            .get(line)
+            .copied()
+            .unwrap_or_else(|| self.screen.formatted_lines_count())
+    }
+
+    /// Returns the zero based line number of the mark named `name`
+    #[must_use]
+    pub fn mark(&self, name: char) -> Option<usize> {
+        self.marks.lock().get(&name).copied()
+    }
+
+    /// Set the mark named `name` on the line at the top of the focused pane
+    pub(crate) fn set_mark(&self, name: char) {
+        let line = self.lines_to_row_map.line_of_row(self.upper_mark);
+        self.marks.lock().insert(name, line);
+    }
+
+    /// Returns the zero based number of the line that is `percent` percent into the text
+    ///
+    /// Percentages above 100 are treated as 100.
+    #[must_use]
+    pub fn line_at_percent(&self, percent: usize) -> usize {
+        self.screen.line_count().saturating_mul(percent.min(100)) / 100
+    }
+
+    /// Returns the first row of the line at `target`
+    ///
+    /// # Errors
+    /// Returns an error if the text could not be read to find the line at a byte offset
+    pub(crate) fn row_of_target(&self, target: GotoTarget) -> std::io::Result<usize> {
+        let line = match target {
+            GotoTarget::Line(line) => line.saturating_sub(1),
+            GotoTarget::Percent(percent) => self.line_at_percent(percent),
+            GotoTarget::Byte(offset) => self.screen.store.line_at_byte(offset)?,
+        };
+        Ok(self.row_of_line(line))
+    }
+
+    /// Heights of the top and bottom panes when `writable_rows` rows are split
+    ///
+    /// One row is left between the panes for a separator.
+    pub(crate) const fn pane_heights(writable_rows: usize) -> (usize, usize) {
+        let rows = writable_rows.saturating_sub(1);
+        let top = rows / 2;
+        (top, rows - top)
+    }
+
+    /// Split the view into two panes or go back to a single one
+    ///
+    /// Both panes start at the current position. When the view is unsplit, the focused pane
+    /// is kept.
+    pub(crate) fn set_split(&mut self, split: bool) {
+        if split == self.split.is_some() {
+            return;
+        }
+        self.split = split.then_some(Split {
+            other_upper_mark: self.upper_mark,
+            bottom_focused: false,
+        });
+    }
+
+    /// Move the focus to the other pane
+    ///
+    /// Returns `false` if the view is not split.
+    #[allow(clippy::missing_const_for_fn)] // Mutable references in const fn need a newer Rust
+    pub(crate) fn switch_pane(&mut self) -> bool {
+        let Some(split) = &mut self.split else {
+            return false;
+        };
+        std::mem::swap(&mut self.upper_mark, &mut split.other_upper_mark);
+        split.bottom_focused = !split.bottom_focused;
+        true
+    }
+
+    /// Add a buffer named `name` holding `text`
+    ///
+    /// If a buffer with the same name already exists, its text is replaced instead. The new
+    /// buffer starts with the line number mode and screen options of the current buffer.
+    pub(crate) fn add_buffer(&mut self, name: String, text: &str) {
+        let idx = self.find_buffer(&name).unwrap_or_else(|| {
+            let mut screen = Screen::default();
+            screen.line_wrapping = self.screen.line_wrapping;
+            screen.max_formatted_rows = self.screen.max_formatted_rows;
+            screen.max_lines = self.screen.max_lines;
+            self.buffers
+                .push(Buffer::new(name, screen, self.line_numbers));
+            self.buffers.len() - 1
+        });
+        self.with_buffer(idx, |ps| ps.set_text(text));
+    }
+
+    /// Remove the buffer named `name`
+    ///
+    /// If it is being displayed, the next buffer is displayed instead. The last remaining buffer
+    /// can't be removed. Returns `true` if the current buffer has changed.
+    pub(crate) fn remove_buffer(&mut self, name: &str) -> bool {
+        let Some(idx) = self.find_buffer(name) else {
+            return false;
+        };
+        if self.buffers.len() == 1 {
+            return false;
+        }
+        let switched = idx == self.current_buffer;
+        if switched {
+            self.switch_buffer(if idx + 1 < self.buffers.len() {
+                idx + 1
+            } else {
+                idx - 1
+            });
+        }
+        self.buffers.remove(idx);
+        if self.current_buffer > idx {
+            self.current_buffer -= 1;
         }
+        self.format_prompt();
+        switched
+    }
 
-        // And lastly, the string that contains the prompt or msg
-        let prompt_str = self.message.as_ref().unwrap_or(&self.prompt);
+    /// Range of the lines of the current buffer that make up `region`
+    fn lines_of_region(&self, region: SaveRegion) -> io::Result<RangeInclusive<usize>> {
+        let last = self.screen.store.line_count().saturating_sub(1);
+        let SaveRegion::Marks(start, end) = region else {
+            return Ok(0..=last);
+        };
+        let mark = |name| {
+            self.mark(name).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("mark '{name}' is not set"),
+                )
+            })
+        };
+        let (start, end) = (mark(start)?, mark(end)?);
+        Ok(start.min(end)..=start.max(end).min(last))
+    }
 
+    /// Write the part of the text of the current buffer given by `options` to `out`
+    ///
+    /// Returns the number of lines written.
+    pub(crate) fn write_saved_text(
+        &self,
+        out: &mut impl io::Write,
+        options: SaveOptions,
+    ) -> io::Result<usize> {
         #[cfg(feature = "search")]
-        let search_len = search_str.len();
+        let filtered = options.region == SaveRegion::Filtered;
         #[cfg(not(feature = "search"))]
-        let search_len = 0;
-
-        let follow_mode_str: &str = if self.follow_output { "[F]" } else { "" };
-
-        // Calculate how much extra padding in the middle we need between
-        // the prompt/message and the indicators on the right
-        let prefix_len = prefix_str.len();
-        let extra_space = self
-            .cols
-            .saturating_sub(search_len + prefix_len + follow_mode_str.len() + prompt_str.len());
-        let dsp_prompt: &str = if extra_space == 0 {
-            &prompt_str[..self.cols - search_len - prefix_len - follow_mode_str.len()]
+        let filtered = false;
+        self.write_lines(
+            out,
+            self.lines_of_region(options.region)?,
+            options.strip_ansi,
+            filtered,
+        )
+    }
+
+    /// Write the `lines` of the current buffer to `out`
+    ///
+    /// If `filtered` is true, lines hidden by the filter are left out. Returns the number of lines
+    /// written.
+    #[cfg_attr(not(feature = "search"), allow(unused_variables))]
+    fn write_lines(
+        &self,
+        out: &mut impl io::Write,
+        lines: RangeInclusive<usize>,
+        strip_ansi: bool,
+        filtered: bool,
+    ) -> io::Result<usize> {
+        let store = &*self.screen.store;
+        let line_count = store.line_count();
+        let mut written = 0;
+        for (idx, line) in store.lines_from(*lines.start()).enumerate() {
+            let idx = lines.start() + idx;
+            if idx > *lines.end() {
+                break;
+            }
+            let line = line?;
+            #[cfg(feature = "search")]
+            if filtered && matches!(&self.screen.filter, Some(filter) if !filter.shows(&line)) {
+                continue;
+            }
+            let line = if strip_ansi {
+                save::strip_ansi(&line)
+            } else {
+                line
+            };
+            out.write_all(line.as_bytes())?;
+            // The last line is written without a newline if it does not have one
+            if idx + 1 < line_count || store.is_terminated() {
+                out.write_all(b"\n")?;
+            }
+            written += 1;
+        }
+        out.flush()?;
+        Ok(written)
+    }
+
+    /// Save the text of the current buffer to the file at `path` and show the outcome at the
+    /// prompt
+    ///
+    /// An existing file is only replaced if `overwrite` is true.
+    pub(crate) fn save_to_file(&mut self, path: &Path, options: SaveOptions, overwrite: bool) {
+        let result = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .create_new(!overwrite)
+            .open(path)
+            .and_then(|file| self.write_saved_text(&mut io::BufWriter::new(file), options));
+        self.message = Some(match result {
+            Ok(lines) => format!("Saved {lines} lines to {}", path.display()),
+            Err(e) => format!("Failed to save to {}: {e}", path.display()),
+        });
+        self.format_prompt();
+    }
+
+    /// Position in the text of the cell at `column` and `row` of the screen
+    ///
+    /// Returns `None` if there is no text on that row.
+    pub(crate) fn position_at(&self, column: usize, row: usize) -> Option<Position> {
+        let row = row.checked_sub(self.top)?;
+        let writable_rows = self.rows.saturating_sub(1);
+        let (upper_mark, row) = match self.split {
+            None if row < writable_rows => (self.upper_mark, row),
+            None => return None,
+            Some(split) => {
+                let (top, bottom) = Self::pane_heights(writable_rows);
+                let (top_mark, bottom_mark) = if split.bottom_focused {
+                    (split.other_upper_mark, self.upper_mark)
+                } else {
+                    (self.upper_mark, split.other_upper_mark)
+                };
+                // One row is taken by the separator between the panes
+                if row < top {
+                    (top_mark, row)
+                } else if row > top && row <= top + bottom {
+                    (bottom_mark, row - top - 1)
+                } else {
+                    return None;
+                }
+            }
+        };
+        let row = upper_mark + row;
+        if row >= self.screen.formatted_lines_count() {
+            return None;
+        }
+        let scrolled = if self.screen.line_wrapping {
+            0
         } else {
-            prompt_str
+            self.left_mark
         };
+        Some(Position {
+            row,
+            column: column.saturating_sub(self.screen.gutter_width(self.line_numbers)) + scrolled,
+        })
+    }
 
-        // push the prompt/msg
-        if self.message.is_some() {
-            format_string.push_str(MSG_SPEC);
-        } else {
-            format_string.push_str(PROMPT_SPEC);
+    /// Line at `position` and the byte index of the character at `position` in that line, once
+    /// the escape sequences are removed from it
+    fn offset_of(&mut self, position: Position) -> io::Result<(usize, usize)> {
+        let line_idx = self.lines_to_row_map.line_of_row(position.row);
+        let first_row = self.row_of_line(line_idx).min(position.row);
+        self.load_rows(first_row, position.row + 1);
+        let line = self
+            .screen
+            .store
+            .lines_from(line_idx)
+            .next()
+            .transpose()?
+            .unwrap_or_default();
+        let line = save::strip_ansi(&line);
+        let gutter_width = self.screen.gutter_width(self.line_numbers);
+        let rows = self
+            .screen
+            .get_formatted_lines_with_bounds(first_row, position.row + 1);
+        let mut offset = 0;
+        for (i, row) in rows.iter().enumerate() {
+            let text = save::strip_ansi(split_gutter(row, gutter_width).1);
+            // The whitespace at which the line is wrapped is not part of any row
+            offset += line[offset..].find(&*text).unwrap_or(0);
+            if i + 1 == rows.len() {
+                return Ok((
+                    line_idx,
+                    offset + selection::byte_of_column(&text, position.column),
+                ));
+            }
+            offset += text.len();
+        }
+        Ok((line_idx, offset))
+    }
+
+    /// Returns the text selected with the mouse without its escape sequences
+    ///
+    /// Returns `None` if nothing has been selected.
+    pub(crate) fn selected_text(&mut self) -> io::Result<Option<String>> {
+        let Some((start, end)) = self.selection.as_ref().and_then(Selection::bounds) else {
+            return Ok(None);
+        };
+        let (start_line, start_byte) = self.offset_of(start)?;
+        let (end_line, end_byte) = self.offset_of(Position {
+            row: end.row,
+            column: end.column + 1,
+        })?;
+        let mut text = String::new();
+        for (idx, line) in self.screen.store.lines_from(start_line).enumerate() {
+            let idx = start_line + idx;
+            if idx > end_line {
+                break;
+            }
+            let line = line?;
+            #[cfg(feature = "search")]
+            if matches!(&self.screen.filter, Some(filter) if !filter.shows(&line)) {
+                continue;
+            }
+            let line = save::strip_ansi(&line);
+            let first = if idx == start_line { start_byte } else { 0 };
+            let last = if idx == end_line {
+                end_byte
+            } else {
+                line.len()
+            };
+            text.push_str(line.get(first..last).unwrap_or_default());
+            if idx < end_line {
+                text.push('\n');
+            }
         }
-        format_string.push_str(dsp_prompt);
-        format_string.push_str(&" ".repeat(extra_space));
+        Ok(Some(text))
+    }
 
-        // add the prefix_num if it exists
-        if prefix_len > 0 {
-            format_string.push_str(INPUT_SPEC);
-            format_string.push_str(&prefix_str);
+    /// Copy the text selected with the mouse to the clipboard and show the outcome at the prompt
+    ///
+    /// The selection is cleared. Returns `false` if nothing was selected.
+    pub(crate) fn copy_selection(&mut self, out: &mut impl io::Write) -> io::Result<bool> {
+        let text = self.selected_text();
+        self.selection = None;
+        match text {
+            Ok(Some(text)) => {
+                out.write_all(selection::osc52(text.as_bytes()).as_bytes())?;
+                self.message = Some(format!(
+                    "Copied {} characters to the clipboard",
+                    text.chars().count()
+                ));
+            }
+            Ok(None) => return Ok(false),
+            Err(e) => self.storage_error(&e),
         }
+        self.format_prompt();
+        Ok(true)
+    }
 
-        // and add the search indicator stuff if it exists
+    /// Copy the lines from the mark named `name` up to the line at the top of the focused pane to
+    /// the clipboard and show the outcome at the prompt
+    ///
+    /// The escape sequences are removed from the copied lines and the lines hidden by a filter
+    /// are left out.
+    pub(crate) fn copy_lines_to_mark(
+        &mut self,
+        out: &mut impl io::Write,
+        name: char,
+    ) -> io::Result<()> {
+        let Some(mark) = self.mark(name) else {
+            self.message = Some(format!("Mark '{name}' is not set. Press Enter"));
+            self.format_prompt();
+            return Ok(());
+        };
+        let top = self.lines_to_row_map.line_of_row(self.upper_mark);
+        let mut text = Vec::new();
+        match self.write_lines(&mut text, mark.min(top)..=mark.max(top), true, true) {
+            Ok(lines) => {
+                out.write_all(selection::osc52(&text).as_bytes())?;
+                self.message = Some(format!("Copied {lines} lines to the clipboard"));
+            }
+            Err(e) => self.storage_error(&e),
+        }
+        self.format_prompt();
+        Ok(())
+    }
+
+    /// Links and file locations on the rows of the focused pane that can be opened with the
+    /// [`HintAction`]
+    ///
+    /// Each of them is returned along with the column and row of the screen where it starts.
+    pub(crate) fn visible_hints(&self) -> Vec<(Hint, usize, usize)> {
+        let Some(action) = &self.hint_action else {
+            return Vec::new();
+        };
+        let top = match self.split {
+            Some(split) if split.bottom_focused => {
+                Self::pane_heights(self.rows.saturating_sub(1)).0 + 1
+            }
+            _ => 0,
+        };
+        let gutter_width = self.screen.gutter_width(self.line_numbers);
+        let (scrolled, text_cols) = if self.screen.line_wrapping {
+            (0, usize::MAX)
+        } else {
+            (self.left_mark, self.cols.saturating_sub(gutter_width))
+        };
+        let rows = self
+            .screen
+            .get_formatted_lines_with_bounds(self.upper_mark, self.upper_mark + self.pane_rows());
+        let mut visible = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            let text = save::strip_ansi(split_gutter(row, gutter_width).1);
+            for (range, hint) in hints::find_hints(&text) {
+                let column = textwrap::core::display_width(&text[..range.start]);
+                // Leave out the hints that start outside the screen
+                if action.opens(&hint) && column >= scrolled && column - scrolled < text_cols {
+                    visible.push((hint, gutter_width + column - scrolled, top + i));
+                }
+            }
+        }
+        visible
+    }
+
+    /// Show an error encountered while accessing the stored text at the prompt
+    pub(crate) fn storage_error(&mut self, e: &std::io::Error) {
+        self.message = Some(format!("Failed to access the stored text: {e}"));
+    }
+
+    /// Show an error encountered while accessing the search history file at the prompt
+    #[cfg(feature = "search")]
+    pub(crate) fn history_error(&mut self, e: &std::io::Error) {
+        self.message = Some(format!("Failed to access the search history: {e}"));
+    }
+
+    /// Reformat the inputted prompt to how it should be displayed
+    pub(crate) fn format_prompt(&mut self) {
+        let theme = &self.prompt_theme;
+
+        // Get the string that will contain the search index/match indicator
         #[cfg(feature = "search")]
-        if search_len > 0 {
-            format_string.push_str(SEARCH_SPEC);
-            format_string.push_str(&search_str);
+        let search_index = if self.search_state.search_idx.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "{}/{}",
+                self.search_state.search_mark + 1,
+                self.search_state.search_idx.len()
+            )
+        };
+        #[cfg(not(feature = "search"))]
+        let search_index = String::new();
+
+        // Whether the end of the text or more data is awaited at the bottom of the text
+        let status = self.input_status();
+
+        // The position of the current buffer when there are several of them
+        let buffer_position = if self.buffers.len() > 1 {
+            format!("{}/{}", self.current_buffer + 1, self.buffers.len())
+        } else {
+            String::new()
+        };
+
+        // The prompt or message on the left
+        let mut left = Vec::with_capacity(2);
+        if let Some(message) = &self.message {
+            left.push((message.clone(), theme.message));
+        } else if let Some(format) = &self.prompt_format {
+            let values = PromptValues {
+                text: &self.prompt,
+                top_line: self
+                    .screen
+                    .line_count()
+                    .min(self.lines_to_row_map.line_of_row(self.upper_mark) + 1),
+                line_count: self.screen.line_count(),
+                percent: self.percent(),
+                buffer_name: self.buffer_name(),
+                buffer_position: buffer_position.clone(),
+                search_index: search_index.clone(),
+                follow_output: self.follow_output,
+                status: status.clone(),
+            };
+            left = prompt::render(format, &values, theme);
+        } else {
+            left.push((self.prompt.clone(), theme.prompt));
+        }
+        // The name and position of the current buffer, unless the format places them
+        if self.prompt_format.is_none() && !buffer_position.is_empty() {
+            let name = self.buffer_name();
+            let buffer_str = if name.is_empty() {
+                format!(" ({buffer_position})")
+            } else {
+                format!(" {name} ({buffer_position})")
+            };
+            left.push((buffer_str, theme.prompt));
         }
 
-        // add follow-mode indicator
-        if !follow_mode_str.is_empty() {
-            format_string.push_str(FOLLOW_MODE_SPEC);
-            format_string.push_str(follow_mode_str);
+        // The indicators on the right
+        let mut right = Vec::with_capacity(4);
+        if !self.prefix_num.is_empty() {
+            right.push((format!(" {} ", self.prefix_num), theme.prefix_num));
+        }
+        if self.prompt_format.is_none() {
+            if !search_index.is_empty() {
+                right.push((format!(" {search_index} "), theme.search_index));
+            }
+            if self.follow_output {
+                right.push(("[F]".to_string(), theme.follow_mode));
+            }
+            if !status.0.is_empty() {
+                right.push((format!(" {}", status.0), status.1));
+            }
+        }
+        if self.loading > 0 {
+            right.push(("[loading]".to_string(), theme.loading));
         }
 
-        format_string.push_str(RESET);
+        self.displayed_prompt = prompt::layout(left, &right, self.cols, theme.prompt);
+    }
+
+    /// Indicator of the state of the input along with its style, the text is empty if there is
+    /// none
+    ///
+    /// Once the bottom of the text is reached, it is `(END)` if all the text has been given and
+    /// a spinner followed by `Waiting for data...` if more is expected in follow mode.
+    fn input_status(&self) -> prompt::Segment {
+        const SPINNER: [char; 4] = ['-', '\\', '|', '/'];
 
-        self.displayed_prompt = format_string;
+        let theme = &self.prompt_theme;
+        if self.input_complete && self.at_end() {
+            ("(END)".to_string(), theme.end)
+        } else if self.is_waiting_for_data() {
+            (
+                format!(
+                    "{} Waiting for data...",
+                    SPINNER[self.spinner % SPINNER.len()]
+                ),
+                theme.loading,
+            )
+        } else {
+            (String::new(), theme.prompt)
+        }
+    }
+
+    /// Whether the prompt shows the spinner of the indicator that more data is awaited
+    pub(crate) fn is_waiting_for_data(&self) -> bool {
+        !self.input_complete && self.follow_output && self.at_end()
+    }
+
+    /// Whether the bottom of the text is displayed in the focused pane
+    fn at_end(&self) -> bool {
+        self.upper_mark.saturating_add(self.pane_rows()) >= self.screen.formatted_lines_count()
+    }
+
+    /// Whether the prompt changes with the position in the text, in which case it is formatted
+    /// again whenever the text is scrolled
+    pub(crate) const fn prompt_tracks_position(&self) -> bool {
+        self.prompt_format.is_some() || self.input_complete || self.follow_output
+    }
+
+    /// Percentage of the text that is above the bottom of the focused pane
+    fn percent(&self) -> usize {
+        let rows_count = self.screen.formatted_lines_count();
+        if rows_count == 0 {
+            return 100;
+        }
+        let bottom = self
+            .upper_mark
+            .saturating_add(self.pane_rows())
+            .min(rows_count);
+        bottom * 100 / rows_count
     }
 
     /// Runs the exit callbacks
@@ -365,17 +1474,29 @@ impl PagerState {
     }
 
     pub(crate) fn append_str(&mut self, text: &str) -> AppendStyle {
-        let old_lc = self.screen.line_count();
-        let old_lc_dgts = minus_core::utils::digits(old_lc);
-        let mut append_result = self.screen.push_screen_buf(
+        let old_lc_dgts = self.screen.line_number_digits();
+        let res = self.screen.push_screen_buf(
             text,
             self.line_numbers,
             self.cols.try_into().unwrap(),
             #[cfg(feature = "search")]
             &self.search_state.search_term,
+            #[cfg(feature = "search")]
+            &self.search_state.highlights,
         );
-        let new_lc = self.screen.line_count();
-        let new_lc_dgts = minus_core::utils::digits(new_lc);
+        let mut append_result = match res {
+            Ok(append_result) => append_result,
+            Err(e) => {
+                self.storage_error(&e);
+                self.format_prompt();
+                return if self.running.lock().is_uninitialized() {
+                    AppendStyle::NoDraw
+                } else {
+                    AppendStyle::FullRedraw
+                };
+            }
+        };
+        let new_lc_dgts = self.screen.line_number_digits();
         #[cfg(feature = "search")]
         {
             let mut append_search_idx = append_result.append_search_idx;
@@ -385,21 +1506,43 @@ impl PagerState {
             &mut append_result.lines_to_row_map,
             append_result.clean_append,
         );
+        let old_upper_mark = self.upper_mark;
+        let dropped_rows = self.trim_lines();
+
+        // The gutter gets wider if it fits the line numbers
+        let widened = self.line_numbers.is_on()
+            && matches!(self.screen.gutter.width, NumberWidth::Fit { .. })
+            && (new_lc_dgts != old_lc_dgts && old_lc_dgts != 0);
+        if widened {
+            self.format_lines();
+        }
+        self.screen.trim_window(self.upper_mark, self.rows);
 
         if self.running.lock().is_uninitialized() {
             return AppendStyle::NoDraw;
         }
+        if widened {
+            return AppendStyle::FullRedraw;
+        }
 
-        if self.line_numbers.is_on() && (new_lc_dgts != old_lc_dgts && old_lc_dgts != 0) {
-            self.format_lines();
+        if dropped_rows > 0 {
+            // The displayed rows only change if some of them have been dropped. Otherwise the
+            // screen is already full so there is nothing to append on it.
+            return if dropped_rows > old_upper_mark {
+                AppendStyle::FullRedraw
+            } else {
+                AppendStyle::PartialUpdate(&[])
+            };
+        }
+        // The rows don't contain the relative line numbers, they are filled in when drawing
+        if self.line_numbers.is_on() && self.screen.gutter.relative {
             return AppendStyle::FullRedraw;
         }
 
-        let total_rows = self.screen.line_count();
-        let fmt_lines = &self.screen.get_formatted_lines_with_bounds(
-            total_rows - append_result.rows_formatted,
-            total_rows - 1,
-        );
+        let total_rows = self.screen.formatted_lines_count();
+        let fmt_lines = &self
+            .screen
+            .get_formatted_lines_with_bounds(total_rows - append_result.rows_formatted, total_rows);
         AppendStyle::PartialUpdate(fmt_lines)
     }
 }
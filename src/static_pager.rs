@@ -19,7 +19,7 @@ use crate::{error::MinusError, Pager};
 /// See [example](../index.html#static-output) on how to use this function.
 ///
 /// # Panics
-/// This function will panic if another instance of minus is already running.
+/// This function will panic if `pager` is already running.
 ///
 /// # Errors
 /// The function will return with an error if it encounters a error during paging.
@@ -0,0 +1,322 @@
+//! Utilities to test applications that use minus without a real terminal
+//!
+//! The [`Harness`] runs the pager synchronously on a [`VirtualTerminal`]. You feed it terminal
+//! events like key presses and it applies them exactly the way a running pager would, including
+//! the bindings of a custom [`InputClassifier`](crate::input::InputClassifier). After each step,
+//! the resulting screen can be inspected as a [`Grid`] of styled [`Cell`]s.
+//!
+//! Data, prompts and other configuration are sent through the [`Pager`] as usual and applied
+//! with [`Harness::process_commands`].
+//!
+//! # Example
+//! ```
+//! use minus::{testing::Harness, Pager};
+//!
+//! let pager = Pager::new();
+//! pager.set_prompt("my prompt").unwrap();
+//! for i in 0..20 {
+//!     pager.push_str(format!("line {i}\n")).unwrap();
+//! }
+//!
+//! let mut harness = Harness::new(&pager, 20, 5).unwrap();
+//! assert_eq!(harness.grid().row_text(0), "line 0");
+//!
+//! harness.send_keys(&["j", "j"]).unwrap();
+//! assert_eq!(harness.grid().row_text(0), "line 2");
+//! assert_eq!(harness.grid().row_text(4), "my prompt");
+//!
+//! harness.send_keys(&["q"]).unwrap();
+//! assert!(harness.is_exited());
+//! ```
+//!
+//! # Prompts
+//! Prompts like the search prompt read their input directly from the events that were passed
+//! along with the event that opened it. Hence all the events needed to complete a prompt must be
+//! given in the same call. If the events run out while a prompt is open, the call returns an
+//! error.
+
+mod terminal;
+#[cfg(test)]
+mod tests;
+
+pub use terminal::{Cell, CellStyle, Grid, VirtualTerminal};
+
+use crate::{
+    backend::EventSource,
+    error::MinusError,
+    input::definitions::{keydefs::parse_key_event, mousedefs::parse_mouse_event},
+    minus_core::{
+        commands::Command,
+        ev_handler::handle_event,
+        init::classify_event,
+        utils::display::{draw_for_change, draw_full},
+        CommandQueue, RunMode,
+    },
+    Pager, PagerState,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use parking_lot::{Condvar, Mutex};
+
+/// Events waiting to be applied by the [`Harness`]
+///
+/// Unlike a real terminal, this does not wait for more events once it is empty. It returns
+/// an error instead so that a prompt expecting more input does not block forever.
+struct ScriptedEvents(VecDeque<Event>);
+
+impl ScriptedEvents {
+    fn exhausted() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "no more scripted events are available",
+        )
+    }
+}
+
+impl EventSource for ScriptedEvents {
+    fn poll(&mut self, _timeout: Duration) -> io::Result<bool> {
+        if self.0.is_empty() {
+            Err(Self::exhausted())
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        self.0.pop_front().ok_or_else(Self::exhausted)
+    }
+}
+
+/// Runs a pager on a [`VirtualTerminal`] without spawning any threads
+///
+/// See the [module level documentation](self) for more.
+pub struct Harness {
+    pager: Pager,
+    ps: PagerState,
+    term: VirtualTerminal,
+    command_queue: CommandQueue,
+    is_exited: Arc<AtomicBool>,
+    events: Mutex<ScriptedEvents>,
+    user_input_active: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Harness {
+    /// Start the pager on a terminal of the given size
+    ///
+    /// All the commands already sent through `pager` are applied before the first screen is
+    /// drawn, just like when the pager starts for real. The pager is considered to be running
+    /// until the user quits or the harness is dropped.
+    ///
+    /// # Errors
+    /// Returns an error if applying any of the commands fails
+    ///
+    /// # Panics
+    /// Panics if `pager` is already running
+    pub fn new(pager: &Pager, cols: u16, rows: u16) -> Result<Self, MinusError> {
+        Self::with_terminal(pager, VirtualTerminal::new(cols, rows))
+    }
+
+    /// Start the pager on `term`, which may already display some text
+    ///
+    /// This is useful to test a pager [drawn inline](Pager::set_inline), which starts below the
+    /// text written to `term` before. See [`Harness::new`] for more.
+    ///
+    /// # Errors
+    /// Returns an error if applying any of the commands or setting up `term` fails
+    ///
+    /// # Panics
+    /// Panics if `pager` is already running
+    pub fn with_terminal(pager: &Pager, mut term: VirtualTerminal) -> Result<Self, MinusError> {
+        let mut ps = PagerState::generate_initial_state(&pager.rx, &mut term)?;
+
+        {
+            let mut runmode = pager.running.lock();
+            assert!(runmode.is_uninitialized(), "The pager is already running");
+            #[cfg(feature = "dynamic_output")]
+            let rm = RunMode::Dynamic;
+            #[cfg(not(feature = "dynamic_output"))]
+            let rm = RunMode::Static;
+            *runmode = rm;
+        }
+        ps.running = pager.running.clone();
+        ps.marks = pager.marks.clone();
+        ps.setup_terminal(&mut term)?;
+
+        let mut harness = Self {
+            pager: pager.clone(),
+            command_queue: CommandQueue::new(ps.running.clone()),
+            ps,
+            term,
+            is_exited: Arc::new(AtomicBool::new(false)),
+            events: Mutex::new(ScriptedEvents(VecDeque::new())),
+            user_input_active: Arc::new((Mutex::new(true), Condvar::new())),
+        };
+
+        draw_full(&mut harness.term, &mut harness.ps)?;
+        if harness.ps.follow_output {
+            draw_for_change(&mut harness.term, &mut harness.ps, &mut (usize::MAX - 1))?;
+        }
+        Ok(harness)
+    }
+
+    /// Apply all the commands that were sent through the [`Pager`] since the last call
+    ///
+    /// # Errors
+    /// Returns an error if applying any of the commands fails
+    pub fn process_commands(&mut self) -> Result<(), MinusError> {
+        while let Ok(command) = self.pager.rx.try_recv() {
+            self.handle(command)?;
+        }
+        Ok(())
+    }
+
+    /// Apply a single terminal event
+    ///
+    /// # Errors
+    /// Returns an error if applying the event fails
+    pub fn send_event(&mut self, event: Event) -> Result<(), MinusError> {
+        self.send_events([event])
+    }
+
+    /// Apply a sequence of terminal events in order
+    ///
+    /// Pending commands from the [`Pager`] are applied first. Events after the one that quits
+    /// the pager are ignored.
+    ///
+    /// # Errors
+    /// Returns an error if applying any of the events fails
+    pub fn send_events(
+        &mut self,
+        events: impl IntoIterator<Item = Event>,
+    ) -> Result<(), MinusError> {
+        self.process_commands()?;
+        self.events.lock().0.extend(events);
+
+        loop {
+            if self.is_exited() {
+                self.events.lock().0.clear();
+                break;
+            }
+            // The lock must be released here since a prompt opened by this event reads the
+            // following events by itself
+            let Some(ev) = self.events.lock().0.pop_front() else {
+                break;
+            };
+            if let Some(iev) = classify_event(ev, &mut self.ps) {
+                self.handle(Command::UserInput(iev))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply key presses given in the same format used by
+    /// [`HashedEventRegister::add_key_events`](crate::input::HashedEventRegister::add_key_events)
+    ///
+    /// # Errors
+    /// Returns an error if applying any of the key presses fails
+    ///
+    /// # Panics
+    /// Panics if any of the descriptions is invalid
+    pub fn send_keys(&mut self, keys: &[&str]) -> Result<(), MinusError> {
+        self.send_events(keys.iter().map(|k| Event::Key(parse_key_event(k))))
+    }
+
+    /// Type each character of `text` as a key press
+    ///
+    /// # Errors
+    /// Returns an error if applying any of the key presses fails
+    pub fn send_text(&mut self, text: &str) -> Result<(), MinusError> {
+        self.send_events(
+            text.chars()
+                .map(|c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))),
+        )
+    }
+
+    /// Apply mouse events given in the same format used by
+    /// [`HashedEventRegister::add_mouse_events`](crate::input::HashedEventRegister::add_mouse_events)
+    ///
+    /// # Errors
+    /// Returns an error if applying any of the mouse events fails
+    ///
+    /// # Panics
+    /// Panics if any of the descriptions is invalid
+    pub fn send_mouse(&mut self, actions: &[&str]) -> Result<(), MinusError> {
+        self.send_events(actions.iter().map(|m| Event::Mouse(parse_mouse_event(m))))
+    }
+
+    /// Resize the terminal and notify the pager about it
+    ///
+    /// # Errors
+    /// Returns an error if redrawing the screen fails
+    pub fn resize(&mut self, cols: u16, rows: u16) -> Result<(), MinusError> {
+        self.term.resize(cols, rows);
+        self.send_event(Event::Resize(cols, rows))
+    }
+
+    /// Returns the screen as it is currently displayed
+    #[must_use]
+    pub const fn grid(&self) -> &Grid {
+        self.term.grid()
+    }
+
+    /// Returns the terminal on which the pager is drawn
+    #[must_use]
+    pub const fn terminal(&self) -> &VirtualTerminal {
+        &self.term
+    }
+
+    /// Returns the current state of the pager
+    #[must_use]
+    pub const fn state(&self) -> &PagerState {
+        &self.ps
+    }
+
+    /// Returns whether the user has quit the pager
+    #[must_use]
+    pub fn is_exited(&self) -> bool {
+        self.is_exited.load(Ordering::SeqCst)
+    }
+
+    fn handle(&mut self, command: Command) -> Result<(), MinusError> {
+        if self.is_exited() {
+            return Ok(());
+        }
+        let mut next = Some(command);
+        while let Some(command) = next {
+            handle_event(
+                command,
+                &mut self.term,
+                &mut self.ps,
+                &mut self.command_queue,
+                &self.is_exited,
+                &self.events,
+                &self.user_input_active,
+            )?;
+            next = if self.is_exited() {
+                None
+            } else {
+                self.command_queue.pop_front()
+            };
+        }
+        if self.is_exited() {
+            *self.ps.running.lock() = RunMode::Uninitialized;
+            self.ps.cleanup_on_exit(&mut self.term)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        *self.ps.running.lock() = RunMode::Uninitialized;
+    }
+}
@@ -0,0 +1,566 @@
+//! An in-memory terminal that interprets the escape sequences written by minus
+
+use crate::{
+    backend::Terminal,
+    error::{CleanupError, SetupError},
+};
+use crossterm::style::Color;
+use std::{convert::TryFrom, fmt, io};
+
+/// Style attributes of a [`Cell`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct CellStyle {
+    /// Foreground color, `None` if it is the terminal's default
+    pub fg: Option<Color>,
+    /// Background color, `None` if it is the terminal's default
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+/// A single character on the screen along with its style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: char,
+    pub style: CellStyle,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: ' ',
+            style: CellStyle::default(),
+        }
+    }
+}
+
+/// Contents of the screen as rows of [`Cell`]s
+///
+/// The [`Display`](fmt::Display) implementation prints the text of each row with the trailing
+/// whitespace removed, which is convenient for snapshot tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    cols: u16,
+    rows: u16,
+    cells: Vec<Vec<Cell>>,
+}
+
+impl Grid {
+    fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            cols,
+            rows,
+            cells: vec![blank_row(cols); rows.into()],
+        }
+    }
+
+    fn resize(&mut self, cols: u16, rows: u16) {
+        self.cells.resize(rows.into(), blank_row(cols));
+        for row in &mut self.cells {
+            row.resize(cols.into(), Cell::default());
+        }
+        self.cols = cols;
+        self.rows = rows;
+    }
+
+    /// Returns the size of the grid as `(columns, rows)`
+    #[must_use]
+    pub const fn size(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    /// Returns the cells of row `y`
+    ///
+    /// # Panics
+    /// Panics if `y` is outside the grid
+    #[must_use]
+    pub fn row(&self, y: u16) -> &[Cell] {
+        &self.cells[usize::from(y)]
+    }
+
+    /// Returns the cell at column `x` of row `y` if it is inside the grid
+    #[must_use]
+    pub fn cell(&self, x: u16, y: u16) -> Option<&Cell> {
+        self.cells.get(usize::from(y))?.get(usize::from(x))
+    }
+
+    /// Returns the text of row `y` without the trailing whitespace
+    ///
+    /// # Panics
+    /// Panics if `y` is outside the grid
+    #[must_use]
+    pub fn row_text(&self, y: u16) -> String {
+        row_to_string(self.row(y))
+    }
+
+    /// Returns the text of all the rows without their trailing whitespace
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        self.cells.iter().map(|r| row_to_string(r)).collect()
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lines().join("\n"))
+    }
+}
+
+fn blank_row(cols: u16) -> Vec<Cell> {
+    vec![Cell::default(); cols.into()]
+}
+
+fn row_to_string(row: &[Cell]) -> String {
+    let text: String = row.iter().map(|c| c.symbol).collect();
+    text.trim_end().to_string()
+}
+
+#[derive(Debug)]
+enum ParseState {
+    Ground,
+    Escape,
+    Csi(String),
+    Osc(String),
+    OscEscape(String),
+}
+
+/// A [`Terminal`] that keeps the screen in memory
+///
+/// It understands the subset of ANSI escape sequences that minus uses: cursor movement, clearing,
+/// scrolling, colors and text attributes, cursor visibility and the alternate screen. Every
+/// character takes exactly one cell, hence wide characters are not laid out like a real terminal
+/// would.
+///
+/// Rows scrolled off the top of the main screen are kept in the [`scrollback`](Self::scrollback).
+#[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct VirtualTerminal {
+    primary: Grid,
+    alternate: Grid,
+    alternate_active: bool,
+    scrollback: Vec<Vec<Cell>>,
+    cursor: (u16, u16),
+    saved_cursor: (u16, u16),
+    cursor_visible: bool,
+    autowrap: bool,
+    pending_wrap: bool,
+    style: CellStyle,
+    state: ParseState,
+    // Bytes of a UTF-8 character split across two writes
+    partial: Vec<u8>,
+    osc: Vec<String>,
+}
+
+impl VirtualTerminal {
+    /// Create a blank terminal of the given size
+    #[must_use]
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            primary: Grid::new(cols, rows),
+            alternate: Grid::new(cols, rows),
+            alternate_active: false,
+            scrollback: Vec::new(),
+            cursor: (0, 0),
+            saved_cursor: (0, 0),
+            cursor_visible: true,
+            autowrap: true,
+            pending_wrap: false,
+            style: CellStyle::default(),
+            state: ParseState::Ground,
+            partial: Vec::new(),
+            osc: Vec::new(),
+        }
+    }
+
+    /// Returns the screen that is currently displayed
+    #[must_use]
+    pub const fn grid(&self) -> &Grid {
+        if self.alternate_active {
+            &self.alternate
+        } else {
+            &self.primary
+        }
+    }
+
+    /// Returns the rows that were scrolled off the top of the main screen, oldest first
+    #[must_use]
+    pub fn scrollback(&self) -> &[Vec<Cell>] {
+        &self.scrollback
+    }
+
+    /// Returns the position of the cursor as `(column, row)`
+    #[must_use]
+    pub const fn cursor_position(&self) -> (u16, u16) {
+        self.cursor
+    }
+
+    /// Returns whether the cursor is visible
+    #[must_use]
+    pub const fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Returns whether the alternate screen is active
+    #[must_use]
+    pub const fn is_alternate_screen(&self) -> bool {
+        self.alternate_active
+    }
+
+    /// Returns the payloads of the operating system commands (`ESC ]`) received so far
+    #[must_use]
+    pub fn osc_sequences(&self) -> &[String] {
+        &self.osc
+    }
+
+    /// Change the size of the terminal
+    ///
+    /// This only changes the screen, the pager must still be notified about it with a
+    /// [`Event::Resize`](crossterm::event::Event::Resize).
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.primary.resize(cols, rows);
+        self.alternate.resize(cols, rows);
+        self.cursor = self.clamp(self.cursor.0, self.cursor.1);
+        self.saved_cursor = self.clamp(self.saved_cursor.0, self.saved_cursor.1);
+        self.pending_wrap = false;
+    }
+
+    const fn grid_mut(&mut self) -> &mut Grid {
+        if self.alternate_active {
+            &mut self.alternate
+        } else {
+            &mut self.primary
+        }
+    }
+
+    fn clamp(&self, x: u16, y: u16) -> (u16, u16) {
+        let (cols, rows) = self.primary.size();
+        (x.min(cols.saturating_sub(1)), y.min(rows.saturating_sub(1)))
+    }
+
+    fn move_to(&mut self, x: u16, y: u16) {
+        self.cursor = self.clamp(x, y);
+        self.pending_wrap = false;
+    }
+
+    fn process(&mut self, text: &str) {
+        for c in text.chars() {
+            let state = std::mem::replace(&mut self.state, ParseState::Ground);
+            self.state = match state {
+                ParseState::Ground => {
+                    self.ground(c);
+                    if c == '\x1b' {
+                        ParseState::Escape
+                    } else {
+                        ParseState::Ground
+                    }
+                }
+                ParseState::Escape => self.escape(c),
+                ParseState::Csi(mut params) => {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        self.csi(&params, c);
+                        ParseState::Ground
+                    } else {
+                        params.push(c);
+                        ParseState::Csi(params)
+                    }
+                }
+                ParseState::Osc(mut payload) => match c {
+                    '\x07' => {
+                        self.osc.push(payload);
+                        ParseState::Ground
+                    }
+                    '\x1b' => ParseState::OscEscape(payload),
+                    c => {
+                        payload.push(c);
+                        ParseState::Osc(payload)
+                    }
+                },
+                // Anything after ESC ends the sequence, normally it is the `\` of ST
+                ParseState::OscEscape(payload) => {
+                    self.osc.push(payload);
+                    ParseState::Ground
+                }
+            };
+        }
+    }
+
+    fn ground(&mut self, c: char) {
+        match c {
+            '\r' => self.move_to(0, self.cursor.1),
+            '\n' => self.line_feed(),
+            '\x08' => self.move_to(self.cursor.0.saturating_sub(1), self.cursor.1),
+            '\t' => self.move_to((self.cursor.0 / 8 + 1) * 8, self.cursor.1),
+            c if c.is_control() => {}
+            c => self.put(c),
+        }
+    }
+
+    fn escape(&mut self, c: char) -> ParseState {
+        match c {
+            '[' => return ParseState::Csi(String::new()),
+            ']' => return ParseState::Osc(String::new()),
+            '7' => self.saved_cursor = self.cursor,
+            '8' => self.move_to(self.saved_cursor.0, self.saved_cursor.1),
+            'M' => {
+                if self.cursor.1 == 0 {
+                    self.scroll_down(1);
+                } else {
+                    self.move_to(self.cursor.0, self.cursor.1 - 1);
+                }
+            }
+            _ => {}
+        }
+        ParseState::Ground
+    }
+
+    fn put(&mut self, c: char) {
+        if self.pending_wrap {
+            self.pending_wrap = false;
+            self.cursor.0 = 0;
+            self.line_feed();
+        }
+        let (x, y) = self.cursor;
+        let style = self.style;
+        if let Some(cell) = self.grid_mut().cells[usize::from(y)].get_mut(usize::from(x)) {
+            *cell = Cell { symbol: c, style };
+        }
+        if x + 1 >= self.primary.cols {
+            self.pending_wrap = self.autowrap;
+        } else {
+            self.cursor.0 += 1;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.pending_wrap = false;
+        if self.cursor.1 + 1 >= self.primary.rows {
+            self.scroll_up(1);
+        } else {
+            self.cursor.1 += 1;
+        }
+    }
+
+    fn scroll_up(&mut self, n: u16) {
+        let (grid, mut scrollback) = if self.alternate_active {
+            (&mut self.alternate, None)
+        } else {
+            (&mut self.primary, Some(&mut self.scrollback))
+        };
+        for _ in 0..n.min(grid.rows) {
+            let row = grid.cells.remove(0);
+            grid.cells.push(blank_row(grid.cols));
+            if let Some(scrollback) = scrollback.as_mut() {
+                scrollback.push(row);
+            }
+        }
+    }
+
+    fn scroll_down(&mut self, n: u16) {
+        let grid = self.grid_mut();
+        for _ in 0..n.min(grid.rows) {
+            grid.cells.pop();
+            grid.cells.insert(0, blank_row(grid.cols));
+        }
+    }
+
+    fn erase(&mut self, from: (u16, u16), to: (u16, u16)) {
+        let grid = self.grid_mut();
+        for y in from.1..=to.1.min(grid.rows.saturating_sub(1)) {
+            let start = if y == from.1 { from.0 } else { 0 };
+            let end = if y == to.1 {
+                to.0
+            } else {
+                grid.cols.saturating_sub(1)
+            };
+            for x in start..=end.min(grid.cols.saturating_sub(1)) {
+                grid.cells[usize::from(y)][usize::from(x)] = Cell::default();
+            }
+        }
+    }
+
+    fn csi(&mut self, params: &str, action: char) {
+        let private = params.starts_with('?');
+        let nums: Vec<u16> = params
+            .trim_start_matches('?')
+            .split(';')
+            .map(|p| p.split(':').next().unwrap_or("").parse().unwrap_or(0))
+            .collect();
+        let arg = |i: usize| nums.get(i).copied().unwrap_or(0);
+        // Most sequences treat a missing or zero count as one
+        let count = arg(0).max(1);
+        let (x, y) = self.cursor;
+        let (cols, rows) = self.primary.size();
+        let (right, bottom) = (cols.saturating_sub(1), rows.saturating_sub(1));
+
+        match action {
+            'H' | 'f' => self.move_to(arg(1).max(1) - 1, arg(0).max(1) - 1),
+            'A' => self.move_to(x, y.saturating_sub(count)),
+            'B' => self.move_to(x, y.saturating_add(count)),
+            'C' => self.move_to(x.saturating_add(count), y),
+            'D' => self.move_to(x.saturating_sub(count), y),
+            'G' => self.move_to(count - 1, y),
+            'd' => self.move_to(x, count - 1),
+            'J' => match arg(0) {
+                0 => self.erase((x, y), (right, bottom)),
+                1 => self.erase((0, 0), (x, y)),
+                _ => self.erase((0, 0), (right, bottom)),
+            },
+            'K' => match arg(0) {
+                0 => self.erase((x, y), (right, y)),
+                1 => self.erase((0, y), (x, y)),
+                _ => self.erase((0, y), (right, y)),
+            },
+            'S' => self.scroll_up(count),
+            'T' => self.scroll_down(count),
+            'm' => self.sgr(&nums),
+            's' => self.saved_cursor = self.cursor,
+            'u' => self.move_to(self.saved_cursor.0, self.saved_cursor.1),
+            'h' | 'l' if private => {
+                let set = action == 'h';
+                for mode in &nums {
+                    match mode {
+                        7 => self.autowrap = set,
+                        25 => self.cursor_visible = set,
+                        1049 if set != self.alternate_active => {
+                            if set {
+                                self.saved_cursor = self.cursor;
+                                self.alternate = Grid::new(cols, rows);
+                                self.alternate_active = true;
+                            } else {
+                                self.alternate_active = false;
+                                self.move_to(self.saved_cursor.0, self.saved_cursor.1);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn sgr(&mut self, params: &[u16]) {
+        let mut params = params.iter().copied();
+        while let Some(p) = params.next() {
+            let style = &mut self.style;
+            match p {
+                0 => *style = CellStyle::default(),
+                1 => style.bold = true,
+                2 => style.dim = true,
+                3 => style.italic = true,
+                4 => style.underline = true,
+                7 => style.reverse = true,
+                22 => {
+                    style.bold = false;
+                    style.dim = false;
+                }
+                23 => style.italic = false,
+                24 => style.underline = false,
+                27 => style.reverse = false,
+                30..=37 => style.fg = Some(ansi_color(p - 30)),
+                38 => style.fg = extended_color(&mut params),
+                39 => style.fg = None,
+                40..=47 => style.bg = Some(ansi_color(p - 40)),
+                48 => style.bg = extended_color(&mut params),
+                49 => style.bg = None,
+                90..=97 => style.fg = Some(ansi_color(p - 90 + 8)),
+                100..=107 => style.bg = Some(ansi_color(p - 100 + 8)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Maps the 16 standard colors to their named variants in crossterm
+fn ansi_color(n: u16) -> Color {
+    match n {
+        0 => Color::Black,
+        1 => Color::DarkRed,
+        2 => Color::DarkGreen,
+        3 => Color::DarkYellow,
+        4 => Color::DarkBlue,
+        5 => Color::DarkMagenta,
+        6 => Color::DarkCyan,
+        7 => Color::Grey,
+        8 => Color::DarkGrey,
+        9 => Color::Red,
+        10 => Color::Green,
+        11 => Color::Yellow,
+        12 => Color::Blue,
+        13 => Color::Magenta,
+        14 => Color::Cyan,
+        15 => Color::White,
+        n => Color::AnsiValue(u8::try_from(n).unwrap_or(u8::MAX)),
+    }
+}
+
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    let mut component = || params.next().map(|v| u8::try_from(v).unwrap_or(u8::MAX));
+    match component()? {
+        5 => component().map(|n| ansi_color(n.into())),
+        2 => Some(Color::Rgb {
+            r: component()?,
+            g: component()?,
+            b: component()?,
+        }),
+        _ => None,
+    }
+}
+
+impl io::Write for VirtualTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.partial.extend_from_slice(buf);
+        let bytes = std::mem::take(&mut self.partial);
+        let mut rest = bytes.as_slice();
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    self.process(text);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    self.process(std::str::from_utf8(valid).unwrap_or_default());
+                    if let Some(len) = e.error_len() {
+                        self.process("\u{FFFD}");
+                        rest = &after[len..];
+                    } else {
+                        // Incomplete character, wait for the rest of it
+                        self.partial = after.to_vec();
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Terminal for VirtualTerminal {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok(self.primary.size())
+    }
+
+    fn setup_inline(&mut self, rows: u16) -> Result<u16, SetupError> {
+        if self.cursor.0 > 0 {
+            self.process("\r\n");
+        }
+        self.process(&"\n".repeat(rows.saturating_sub(1).into()));
+        self.move_to(0, self.cursor.1.saturating_sub(rows.saturating_sub(1)));
+        self.cursor_visible = false;
+        Ok(self.cursor.1)
+    }
+
+    fn cleanup_inline(&mut self) -> Result<(), CleanupError> {
+        self.cursor_visible = true;
+        Ok(())
+    }
+}
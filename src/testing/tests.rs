@@ -0,0 +1,1277 @@
+use super::{CellStyle, Harness, VirtualTerminal};
+use crate::{
+    input::{HashedEventRegister, InputEvent},
+    Pager,
+};
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Attribute, Color, SetAttribute, SetForegroundColor},
+    terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, ScrollUp},
+};
+use std::io::Write;
+
+fn pager_with_lines(n: usize) -> Pager {
+    let pager = Pager::new();
+    for i in 0..n {
+        pager.push_str(format!("line {i}\n")).unwrap();
+    }
+    pager
+}
+
+mod virtual_terminal {
+    use super::*;
+
+    #[test]
+    fn move_and_write() {
+        let mut term = VirtualTerminal::new(10, 3);
+        queue!(term, MoveTo(2, 1)).unwrap();
+        write!(term, "abc").unwrap();
+        assert_eq!(term.grid().lines(), vec!["", "  abc", ""]);
+        assert_eq!(term.cursor_position(), (5, 1));
+    }
+
+    #[test]
+    fn move_is_clamped_to_screen() {
+        let mut term = VirtualTerminal::new(10, 3);
+        queue!(term, MoveTo(20, 20)).unwrap();
+        write!(term, "x").unwrap();
+        assert_eq!(term.grid().row_text(2), "         x");
+    }
+
+    #[test]
+    fn wraps_only_when_more_text_follows() {
+        let mut term = VirtualTerminal::new(4, 3);
+        write!(term, "abcd\r\nef").unwrap();
+        assert_eq!(term.grid().lines(), vec!["abcd", "ef", ""]);
+
+        let mut term = VirtualTerminal::new(4, 3);
+        write!(term, "abcdef").unwrap();
+        assert_eq!(term.grid().lines(), vec!["abcd", "ef", ""]);
+    }
+
+    #[test]
+    fn line_feed_at_bottom_scrolls() {
+        let mut term = VirtualTerminal::new(4, 2);
+        write!(term, "a\r\nb\r\nc").unwrap();
+        assert_eq!(term.grid().lines(), vec!["b", "c"]);
+        assert_eq!(term.scrollback().len(), 1);
+        assert_eq!(term.scrollback()[0][0].symbol, 'a');
+    }
+
+    #[test]
+    fn clear_and_scroll() {
+        let mut term = VirtualTerminal::new(4, 3);
+        write!(term, "a\r\nb\r\nc").unwrap();
+        queue!(term, ScrollUp(1)).unwrap();
+        assert_eq!(term.grid().lines(), vec!["b", "c", ""]);
+
+        queue!(term, MoveTo(0, 0), Clear(ClearType::CurrentLine)).unwrap();
+        assert_eq!(term.grid().lines(), vec!["", "c", ""]);
+
+        queue!(term, Clear(ClearType::All)).unwrap();
+        assert_eq!(term.grid().lines(), vec!["", "", ""]);
+    }
+
+    #[test]
+    fn styles() {
+        let mut term = VirtualTerminal::new(10, 1);
+        queue!(
+            term,
+            SetAttribute(Attribute::Reverse),
+            SetForegroundColor(Color::Red)
+        )
+        .unwrap();
+        write!(term, "a").unwrap();
+        queue!(term, SetAttribute(Attribute::Reset)).unwrap();
+        write!(term, "\x1b[1;38;2;1;2;3mb").unwrap();
+
+        let grid = term.grid();
+        assert_eq!(
+            grid.cell(0, 0).unwrap().style,
+            CellStyle {
+                fg: Some(Color::Red),
+                reverse: true,
+                ..CellStyle::default()
+            }
+        );
+        assert_eq!(
+            grid.cell(1, 0).unwrap().style,
+            CellStyle {
+                fg: Some(Color::Rgb { r: 1, g: 2, b: 3 }),
+                bold: true,
+                ..CellStyle::default()
+            }
+        );
+        assert_eq!(grid.cell(2, 0).unwrap().style, CellStyle::default());
+    }
+
+    #[test]
+    fn alternate_screen() {
+        let mut term = VirtualTerminal::new(10, 2);
+        write!(term, "main").unwrap();
+        queue!(term, EnterAlternateScreen).unwrap();
+        assert!(term.is_alternate_screen());
+        assert_eq!(term.grid().lines(), vec!["", ""]);
+        write!(term, "alt").unwrap();
+        queue!(term, LeaveAlternateScreen).unwrap();
+        assert!(!term.is_alternate_screen());
+        assert_eq!(term.grid().lines(), vec!["main", ""]);
+    }
+
+    #[test]
+    fn utf8_split_across_writes() {
+        let mut term = VirtualTerminal::new(10, 1);
+        let bytes = "é".as_bytes();
+        term.write_all(&bytes[..1]).unwrap();
+        term.write_all(&bytes[1..]).unwrap();
+        assert_eq!(term.grid().row_text(0), "é");
+    }
+
+    #[test]
+    fn osc_sequences() {
+        let mut term = VirtualTerminal::new(10, 1);
+        write!(term, "\x1b]52;c;YWJj\x07\x1b]0;title\x1b\\x").unwrap();
+        assert_eq!(term.osc_sequences(), ["52;c;YWJj", "0;title"]);
+        assert_eq!(term.grid().row_text(0), "x");
+    }
+}
+
+mod harness {
+    use super::*;
+
+    #[test]
+    fn initial_screen() {
+        let pager = pager_with_lines(20);
+        pager.set_prompt("prompt").unwrap();
+        let harness = Harness::new(&pager, 20, 4).unwrap();
+        assert_eq!(harness.grid().to_string(), "line 0\nline 1\nline 2\nprompt");
+        assert!(harness.grid().row(3)[0].style.dim);
+    }
+
+    #[test]
+    fn scroll_with_keys_and_mouse() {
+        let pager = pager_with_lines(20);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        harness.send_keys(&["j", "down"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 2");
+        assert_eq!(harness.state().upper_mark, 2);
+
+        harness.send_mouse(&["scroll:down"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 7");
+
+        harness.send_keys(&["g"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 0");
+    }
+
+    #[test]
+    fn prefix_numbers() {
+        let pager = pager_with_lines(20);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        harness.send_text("12j").unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 12");
+        assert!(harness.state().prefix_num.is_empty());
+    }
+
+    #[test]
+    fn commands_from_pager() {
+        let pager = pager_with_lines(2);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        pager.set_text("a\nb\nc").unwrap();
+        pager.set_prompt("new prompt").unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(harness.grid().lines(), vec!["a", "b", "c", "new prompt"]);
+    }
+
+    #[test]
+    fn inline() {
+        let pager = pager_with_lines(20);
+        pager.set_prompt("prompt").unwrap();
+        pager.set_inline(Some(4)).unwrap();
+        let mut term = VirtualTerminal::new(20, 6);
+        term.write_all(b"$ one\r\n$ two\r\n$ app").unwrap();
+        let mut harness = Harness::with_terminal(&pager, term).unwrap();
+
+        // The text above the cursor scrolls up to make room for the pager
+        assert_eq!(harness.state().top, 2);
+        assert_eq!(
+            harness.grid().lines(),
+            ["$ two", "$ app", "line 0", "line 1", "line 2", "prompt"]
+        );
+        assert_eq!(harness.terminal().scrollback().len(), 1);
+
+        harness.send_keys(&["j", "q"]).unwrap();
+        // The last page stays on the screen while the prompt is cleared
+        assert_eq!(
+            harness.grid().lines(),
+            ["$ two", "$ app", "line 1", "line 2", "line 3", ""]
+        );
+        assert!(!harness.terminal().is_alternate_screen());
+        assert_eq!(harness.terminal().cursor_position(), (0, 5));
+    }
+
+    #[test]
+    fn end_of_input() {
+        let pager = pager_with_lines(20);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        pager.end_of_input().unwrap();
+        harness.process_commands().unwrap();
+        assert!(!harness.is_exited());
+
+        // Quit once the text fits on the screen and write it to the main screen
+        pager.set_text("a\nb\n").unwrap();
+        pager.end_of_input().unwrap();
+        harness.process_commands().unwrap();
+        assert!(harness.is_exited());
+        assert!(harness.state().print_on_exit);
+
+        let pager = pager_with_lines(2);
+        pager.set_run_no_overflow(true).unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        pager.end_of_input().unwrap();
+        harness.process_commands().unwrap();
+        assert!(!harness.is_exited());
+    }
+
+    #[test]
+    fn resize() {
+        let pager = pager_with_lines(20);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        harness.resize(20, 6).unwrap();
+        assert_eq!(harness.grid().size(), (20, 6));
+        assert_eq!(harness.grid().row_text(4), "line 4");
+    }
+
+    #[test]
+    fn follow_output_on_full_redraw() {
+        let pager = pager_with_lines(9);
+        pager.set_line_numbers(crate::LineNumbers::Enabled).unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        pager.follow_output(true).unwrap();
+
+        // The line numbers get wider, so the whole screen is drawn again
+        pager.push_str("line 9\n").unwrap();
+        harness.process_commands().unwrap();
+        assert!(harness.grid().row_text(2).ends_with("line 9"));
+    }
+
+    #[test]
+    fn append_to_wrapped_lines() {
+        let pager = Pager::new();
+        pager.push_str("a long line that wraps\n").unwrap();
+        let mut harness = Harness::new(&pager, 10, 7).unwrap();
+        pager.push_str("one\ntwo\n").unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(harness.grid().row_text(3), "one");
+        assert_eq!(harness.grid().row_text(4), "two");
+
+        pager.push_str("thr").unwrap();
+        harness.process_commands().unwrap();
+        pager.push_str("ee\n").unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(harness.grid().row_text(5), "three");
+    }
+
+    #[test]
+    fn custom_bindings() {
+        let pager = pager_with_lines(20);
+        let mut input_register = HashedEventRegister::default();
+        input_register.add_key_events(&["x"], |_, ps| {
+            InputEvent::UpdateUpperMark(ps.upper_mark + 10)
+        });
+        input_register.add_key_events(&["c-q"], |_, _| InputEvent::Exit);
+        pager
+            .set_input_classifier(Box::new(input_register))
+            .unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        harness.send_keys(&["j", "x"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 11");
+
+        // Nothing is applied after quitting
+        harness.send_keys(&["c-q", "x"]).unwrap();
+        assert!(harness.is_exited());
+        assert_eq!(harness.grid().row_text(0), "line 11");
+    }
+
+    #[test]
+    fn buffers() {
+        let pager = pager_with_lines(20);
+        pager.set_prompt("prompt").unwrap();
+        pager.add_buffer("other", "a\nb\n").unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        assert_eq!(harness.grid().row_text(3), "prompt (1/2)");
+
+        harness.send_keys(&["j", "]"]).unwrap();
+        assert_eq!(
+            harness.grid().lines(),
+            vec!["a", "b", "", "prompt other (2/2)"]
+        );
+        assert_eq!(harness.state().buffer_name(), "other");
+        // Only the rows of its text are allocated
+        assert!(harness.state().screen.formatted_lines.capacity() < 1024);
+
+        // Each buffer keeps its own position
+        harness.send_keys(&["["]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 1");
+
+        pager.push_str_to_buffer("other", "c\n").unwrap();
+        pager.switch_buffer("other").unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(
+            harness.grid().lines(),
+            vec!["a", "b", "c", "prompt other (2/2)"]
+        );
+
+        pager.remove_buffer("other").unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 1");
+        assert_eq!(harness.grid().row_text(3), "prompt");
+    }
+
+    #[test]
+    fn split_view() {
+        let pager = pager_with_lines(20);
+        pager.set_prompt("prompt").unwrap();
+        let mut harness = Harness::new(&pager, 10, 8).unwrap();
+
+        harness.send_keys(&["W", "j"]).unwrap();
+        assert_eq!(
+            harness.grid().lines(),
+            vec![
+                "line 1",
+                "line 2",
+                "line 3",
+                "▲─────────",
+                "line 0",
+                "line 1",
+                "line 2",
+                "prompt"
+            ]
+        );
+
+        // Only the focused pane scrolls
+        harness.send_keys(&["c-w", "G"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 1");
+        assert!(harness.grid().row_text(3).starts_with('▼'));
+        assert_eq!(harness.grid().row_text(4), "line 17");
+        assert_eq!(harness.grid().row_text(6), "line 19");
+
+        // The focused pane is kept when going back to a single pane
+        harness.send_keys(&["W"]).unwrap();
+        assert!(harness.state().split.is_none());
+        assert_eq!(harness.grid().row_text(0), "line 13");
+        assert_eq!(harness.grid().row_text(6), "line 19");
+
+        // Incremental search draws a single pane, both panes are drawn again once it is done
+        #[cfg(feature = "search")]
+        {
+            harness
+                .send_keys(&["W", "g", "/", "1", "5", "enter"])
+                .unwrap();
+            let panes = vec![
+                "line 15",
+                "line 16",
+                "line 17",
+                "▲─────────",
+                "line 13",
+                "line 14",
+                "line 15",
+            ];
+            assert_eq!(harness.grid().lines()[..7], panes);
+
+            harness.send_keys(&["/", "1", "7", "esc"]).unwrap();
+            assert_eq!(harness.grid().lines()[..7], panes);
+        }
+    }
+
+    #[test]
+    fn split_view_with_other_buffers() {
+        let pager = pager_with_lines(10);
+        pager.set_prompt("prompt").unwrap();
+        pager.set_max_lines(Some(10)).unwrap();
+        pager.add_buffer("other", "a\nb\n").unwrap();
+        let mut harness = Harness::new(&pager, 10, 8).unwrap();
+
+        // Scroll the top pane, then move the focus to the bottom one
+        harness.send_keys(&["W", "5", "j", "c-w"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 5");
+
+        // Lines dropped from the other buffer don't move the pane that is not focused
+        let text: String = (0..12).map(|i| format!("{i}\n")).collect();
+        pager.push_str_to_buffer("other", text).unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(harness.state().split.unwrap().other_upper_mark, 5);
+        assert_eq!(harness.grid().row_text(0), "line 5");
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn search() {
+        let pager = pager_with_lines(20);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        let mut keys = vec!["/"];
+        keys.extend(["1", "5", "enter"]);
+        harness.send_keys(&keys).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 15");
+        assert!(harness.grid().row(0)[5].style.reverse);
+
+        // The prompt is left without any input to read
+        assert!(harness.send_keys(&["/"]).is_err());
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn empty_search_after_editing() {
+        let pager = pager_with_lines(20);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        harness
+            .send_keys(&["/", "1", "backspace", "enter"])
+            .unwrap();
+        assert!(harness.state().search_state.search_term.is_none());
+        assert!(harness.state().search_state.search_idx.is_empty());
+        assert!(!harness.grid().row(0)[0].style.reverse);
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn search_options() {
+        use crate::search::CaseSensitivity;
+
+        let pager = Pager::new();
+        pager.push_str("Foo(1)\nfoo(2)\nbar\n").unwrap();
+        pager.set_literal_search(true).unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        let matches = |harness: &Harness| {
+            harness
+                .state()
+                .search_state
+                .search_idx
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        harness
+            .send_keys(&["/", "f", "o", "o", "(", "enter"])
+            .unwrap();
+        assert_eq!(matches(&harness), [1]);
+
+        // Toggling an option at the prompt applies it to the previous search
+        harness.send_keys(&["/", "m-c", "enter"]).unwrap();
+        assert_eq!(
+            harness.state().search_state.case_sensitivity,
+            CaseSensitivity::Smart
+        );
+        assert_eq!(matches(&harness), [0, 1]);
+
+        pager.set_search_case(CaseSensitivity::Sensitive).unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(matches(&harness), [1]);
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn search_history() {
+        let pager = pager_with_lines(20);
+        pager.add_search_history(["line 1[45]"]).unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        harness.send_keys(&["/", "1", "2", "enter"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 12");
+
+        harness.send_keys(&["/", "up", "up", "enter"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 14");
+        assert_eq!(harness.state().search_state.search_idx.len(), 2);
+        assert_eq!(
+            harness.state().search_state.history.entries(),
+            ["12", "line 1[45]"]
+        );
+    }
+
+    #[cfg(all(feature = "search", feature = "syntax"))]
+    #[test]
+    fn syntax_highlighting() {
+        use crate::screen::syntax::LogHighlighter;
+
+        let pager = Pager::new();
+        pager.set_highlighter(LogHighlighter).unwrap();
+        pager.set_text("INFO start\nERROR failed\n").unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        let fg = |harness: &Harness, x, y| harness.grid().cell(x, y).unwrap().style.fg;
+        assert_eq!(harness.grid().row_text(1), "ERROR failed");
+        assert_eq!(fg(&harness, 0, 0), Some(Color::Green));
+        assert_eq!(fg(&harness, 0, 1), Some(Color::Red));
+        assert_eq!(fg(&harness, 6, 1), None);
+
+        // Searches work on the text without the colors
+        harness
+            .send_keys(&["/", "R", "space", "f", "enter"])
+            .unwrap();
+        assert!(harness.grid().cell(4, 1).unwrap().style.reverse);
+
+        pager.remove_highlighter().unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(fg(&harness, 0, 1), None);
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn highlights() {
+        use crossterm::style::{Color, ContentStyle, Stylize};
+
+        let pager = pager_with_lines(20);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        let bg = |harness: &Harness, x, y| harness.grid().cell(x, y).unwrap().style.bg;
+
+        harness.send_keys(&["+", "1", "enter"]).unwrap();
+        harness.send_keys(&["+", "l", "i", "enter"]).unwrap();
+        assert_eq!(harness.grid().row_text(1), "line 1");
+        assert_eq!(bg(&harness, 5, 1), Some(Color::Yellow));
+        assert_eq!(bg(&harness, 0, 1), Some(Color::Red));
+        assert_eq!(bg(&harness, 2, 1), None);
+
+        // Search matches are still displayed in reverse video
+        harness.send_keys(&["/", "n", "e", "enter"]).unwrap();
+        assert!(harness.grid().cell(2, 0).unwrap().style.reverse);
+        assert_eq!(bg(&harness, 0, 0), Some(Color::Red));
+
+        harness.send_keys(&["dash", "l", "i", "enter"]).unwrap();
+        assert_eq!(bg(&harness, 0, 0), None);
+        assert_eq!(harness.state().search_state.highlights.len(), 1);
+
+        // The freed color is given to the next highlight
+        harness.send_keys(&["+", "2", "enter"]).unwrap();
+        assert_eq!(bg(&harness, 5, 2), Some(Color::Red));
+
+        // An empty pattern removes all the highlights
+        harness.send_keys(&["dash", "enter"]).unwrap();
+        assert!(harness.state().search_state.highlights.is_empty());
+
+        pager
+            .add_highlight(
+                regex::Regex::new("0").unwrap(),
+                ContentStyle::new().on_green(),
+            )
+            .unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(bg(&harness, 5, 0), Some(Color::Green));
+        pager.clear_highlights().unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(bg(&harness, 5, 0), None);
+    }
+
+    #[test]
+    fn prompt_format() {
+        use crate::prompt::PromptTheme;
+        use crossterm::style::{ContentStyle, Stylize};
+
+        let pager = pager_with_lines(20);
+        pager.set_prompt_format("line %l/%L %p%%?f %f.").unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        assert_eq!(harness.grid().row_text(3), "line 1/20 15%");
+
+        harness.send_text("5j").unwrap();
+        assert_eq!(harness.grid().row_text(3), "line 6/20 40%");
+        pager.follow_output(true).unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(harness.grid().row_text(3), "line 18/20 100% [F]");
+
+        pager
+            .set_prompt_theme(PromptTheme {
+                prompt: ContentStyle::new().on_dark_blue(),
+                follow_mode: ContentStyle::new().red(),
+                ..PromptTheme::default()
+            })
+            .unwrap();
+        harness.process_commands().unwrap();
+        let style = |x| harness.grid().cell(x, 3).unwrap().style;
+        assert_eq!(style(0).bg, Some(Color::DarkBlue));
+        assert_eq!(style(16).fg, Some(Color::Red));
+
+        // An empty format brings back the default layout
+        pager.end_of_input().unwrap();
+        pager.set_prompt("prompt").unwrap();
+        pager.set_prompt_format("").unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(harness.grid().row_text(3), "prompt     [F] (END)");
+    }
+
+    #[cfg(feature = "dynamic_output")]
+    #[test]
+    fn end_of_input_indicator() {
+        let pager = pager_with_lines(20);
+        let mut harness = Harness::new(&pager, 40, 4).unwrap();
+        pager.follow_output(true).unwrap();
+        harness.process_commands().unwrap();
+        assert!(harness
+            .grid()
+            .row_text(3)
+            .ends_with("[F] - Waiting for data..."));
+
+        pager.end_of_input().unwrap();
+        harness.process_commands().unwrap();
+        assert!(harness.grid().row_text(3).ends_with("[F] (END)"));
+
+        // The end is only shown at the bottom of the text
+        harness.send_text("g").unwrap();
+        assert!(!harness.grid().row_text(3).contains("(END)"));
+
+        pager.set_prompt_format("%l?e %e").unwrap();
+        harness.send_text("G").unwrap();
+        assert_eq!(harness.grid().row_text(3), "18 (END)");
+    }
+
+    #[test]
+    fn loading_indicator_counts_readers() {
+        // Reads whatever is sent to it until the sender is dropped
+        struct Pipe(flume::Receiver<Vec<u8>>);
+
+        impl std::io::Read for Pipe {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let Ok(data) = self.0.recv() else {
+                    return Ok(0);
+                };
+                buf[..data.len()].copy_from_slice(&data);
+                Ok(data.len())
+            }
+        }
+
+        let pager = Pager::new();
+        pager.set_prompt("prompt").unwrap();
+        let (tx1, rx1) = flume::unbounded();
+        let (tx2, rx2) = flume::unbounded::<Vec<u8>>();
+        let r1 = pager.push_reader(Pipe(rx1)).unwrap();
+        let r2 = pager.push_reader(Pipe(rx2)).unwrap();
+        tx1.send(b"first\n".to_vec()).unwrap();
+        drop(tx1);
+        r1.join().unwrap().unwrap();
+
+        // The second reader has not finished yet
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        assert_eq!(harness.grid().row_text(0), "first");
+        assert!(harness.grid().row_text(3).ends_with("[loading]"));
+
+        drop(tx2);
+        r2.join().unwrap().unwrap();
+        harness.process_commands().unwrap();
+        assert!(!harness.grid().row_text(3).contains("[loading]"));
+    }
+
+    #[test]
+    fn gutter() {
+        use crate::{
+            screen::gutter::{Gutter, GutterColumn, NumberWidth},
+            LineNumbers,
+        };
+        use crossterm::style::{ContentStyle, Stylize};
+
+        let pager = pager_with_lines(20);
+        pager
+            .push_str("a line that is wider than the terminal\n")
+            .unwrap();
+        pager.set_line_numbers(LineNumbers::Enabled).unwrap();
+        pager
+            .set_gutter(Gutter {
+                width: NumberWidth::Fixed(3),
+                separator: " | ".to_string(),
+                number_style: ContentStyle::new().yellow(),
+                relative: true,
+                column: Some(GutterColumn::new(2, |idx, _| {
+                    if idx % 2 == 0 { "*" } else { "" }.to_string()
+                })),
+                ..Gutter::default()
+            })
+            .unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        assert_eq!(harness.grid().row_text(0), "  1 | * line 0");
+        assert_eq!(harness.grid().row_text(1), "  1 |   line 1");
+        assert_eq!(
+            harness.grid().cell(2, 0).unwrap().style.fg,
+            Some(Color::Yellow)
+        );
+
+        // The numbers are relative to the line at the top
+        harness.send_text("5j").unwrap();
+        assert_eq!(harness.grid().row_text(0), "  6 |   line 5");
+        assert_eq!(harness.grid().row_text(2), "  2 |   line 7");
+
+        // The gutter stays in place when scrolling horizontally
+        pager.horizontal_scroll(true).unwrap();
+        harness.process_commands().unwrap();
+        harness.send_text("ll").unwrap();
+        assert_eq!(harness.grid().row_text(1), "  1 | * ne 6");
+    }
+
+    #[test]
+    fn horizontal_scroll_with_line_numbers() {
+        let pager = Pager::new();
+        pager.push_str("0123456789abcdefghij\n").unwrap();
+        pager.set_line_numbers(crate::LineNumbers::Enabled).unwrap();
+        pager.horizontal_scroll(true).unwrap();
+        let mut harness = Harness::new(&pager, 16, 3).unwrap();
+
+        // The end of the line can be reached even though the gutter takes up some columns
+        harness.send_text(&"l".repeat(20)).unwrap();
+        assert!(harness.grid().row_text(0).ends_with("ghij"));
+    }
+
+    #[test]
+    fn marks() {
+        let pager = pager_with_lines(20);
+        pager.set_mark('e', 15);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        harness.send_text("5jma").unwrap();
+        assert_eq!(pager.marks().get(&'a'), Some(&5));
+        harness.send_text("g'a").unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 5");
+        harness.send_text("'e").unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 15");
+
+        // Marks stay on their lines when the text is wrapped again
+        harness.resize(4, 4).unwrap();
+        harness.send_text("g'a").unwrap();
+        assert_eq!(harness.state().upper_mark, 10);
+        assert_eq!(harness.grid().row_text(1), "5");
+
+        // Any key other than a character cancels reading the name of the mark
+        harness.send_keys(&["m", "esc", "g", "j"]).unwrap();
+        assert_eq!(harness.state().upper_mark, 1);
+        assert_eq!(pager.marks().len(), 2);
+
+        harness.send_text("'z").unwrap();
+        assert!(harness.state().message.is_some());
+    }
+
+    #[test]
+    fn marks_per_buffer() {
+        let pager = pager_with_lines(20);
+        pager.add_buffer("other", "a\nb\nc\nd\ne\nf\n").unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        harness.send_text("5jma]").unwrap();
+        // The mark of the first buffer is not set in this one
+        harness.send_text("'a").unwrap();
+        assert!(harness.state().message.is_some());
+        harness.send_keys(&["enter"]).unwrap();
+        assert!(pager.marks().is_empty());
+
+        harness.send_text("2jmagG'a").unwrap();
+        assert_eq!(harness.grid().row_text(0), "c");
+
+        // Each buffer jumps to its own mark
+        harness.send_text("['a").unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 5");
+        assert_eq!(pager.marks().get(&'a'), Some(&5));
+    }
+
+    #[test]
+    fn copy_to_clipboard() {
+        use crossterm::event::{Event, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+        let mouse = |kind, column, row| {
+            Event::Mouse(MouseEvent {
+                kind,
+                column,
+                row,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        let pager = pager_with_lines(3);
+        pager
+            .push_str("a line that is wider than the terminal\n")
+            .unwrap();
+        let mut harness = Harness::new(&pager, 20, 6).unwrap();
+
+        // The selected text is highlighted while dragging
+        harness
+            .send_events([
+                mouse(MouseEventKind::Down(MouseButton::Left), 5, 1),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 3, 3),
+            ])
+            .unwrap();
+        assert!(!harness.grid().cell(4, 1).unwrap().style.reverse);
+        assert!(harness.grid().cell(5, 1).unwrap().style.reverse);
+        assert!(harness.grid().cell(0, 2).unwrap().style.reverse);
+        assert!(!harness.grid().cell(4, 3).unwrap().style.reverse);
+        assert!(harness.terminal().osc_sequences().is_empty());
+
+        // and copied when the button is released
+        harness
+            .send_event(mouse(MouseEventKind::Up(MouseButton::Left), 3, 3))
+            .unwrap();
+        assert_eq!(
+            harness.terminal().osc_sequences(),
+            ["52;c;MQpsaW5lIDIKYSBsaQ=="]
+        );
+        assert_eq!(
+            harness.state().message.as_deref(),
+            Some("Copied 13 characters to the clipboard")
+        );
+        assert!(!harness.grid().cell(5, 1).unwrap().style.reverse);
+
+        // The space at which a line is wrapped is kept
+        harness
+            .send_events([
+                mouse(MouseEventKind::Down(MouseButton::Left), 15, 3),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 3, 4),
+                mouse(MouseEventKind::Up(MouseButton::Left), 3, 4),
+            ])
+            .unwrap();
+        assert_eq!(
+            harness.terminal().osc_sequences().last().unwrap(),
+            "52;c;d2lkZXIgdGhhbg=="
+        );
+
+        // A click without dragging copies nothing
+        harness
+            .send_events([
+                mouse(MouseEventKind::Down(MouseButton::Left), 0, 0),
+                mouse(MouseEventKind::Up(MouseButton::Left), 0, 0),
+            ])
+            .unwrap();
+        assert_eq!(harness.terminal().osc_sequences().len(), 2);
+
+        // `y` copies the lines from the line at the top down to a mark
+        pager.set_mark('a', 2);
+        harness.send_text("ya").unwrap();
+        assert_eq!(
+            harness.terminal().osc_sequences().last().unwrap(),
+            "52;c;bGluZSAwCmxpbmUgMQpsaW5lIDIK"
+        );
+        assert_eq!(
+            harness.state().message.as_deref(),
+            Some("Copied 3 lines to the clipboard")
+        );
+    }
+
+    #[test]
+    fn hints() {
+        use crate::hints::{Hint, HintAction};
+        use parking_lot::Mutex;
+        use std::sync::Arc;
+
+        let pager = Pager::new();
+        pager
+            .push_str("see https://example.com/x\nat src/lib.rs:10:2 and src/main.rs:3\n")
+            .unwrap();
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let opened2 = opened.clone();
+        pager
+            .set_hint_action(Some(HintAction::callback(move |hint| {
+                opened2.lock().push(hint.clone());
+            })))
+            .unwrap();
+        let mut harness = Harness::new(&pager, 40, 4).unwrap();
+
+        // Each hint is labelled where it starts
+        harness.send_text("o").unwrap();
+        assert_eq!(harness.grid().row_text(0), "see attps://example.com/x");
+        assert_eq!(
+            harness.grid().row_text(1),
+            "at src/lib.rs:10:2 and drc/main.rs:3"
+        );
+        assert_eq!(
+            harness.grid().cell(3, 1).unwrap().style.bg,
+            Some(Color::Yellow)
+        );
+
+        harness.send_text("s").unwrap();
+        assert_eq!(
+            *opened.lock(),
+            [Hint::Location {
+                path: "src/lib.rs".to_string(),
+                line: 10,
+                column: Some(2),
+            }]
+        );
+        assert_eq!(
+            harness.grid().row_text(1),
+            "at src/lib.rs:10:2 and src/main.rs:3"
+        );
+
+        // Any other key removes the labels
+        harness.send_keys(&["o", "esc", "j"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "see https://example.com/x");
+        assert_eq!(harness.state().upper_mark, 0);
+        assert_eq!(opened.lock().len(), 1);
+
+        pager.set_text("nothing to open").unwrap();
+        harness.send_text("o").unwrap();
+        assert_eq!(
+            harness.state().message.as_deref(),
+            Some("No links or file locations on the screen")
+        );
+    }
+
+    #[test]
+    fn suspend_with() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        let pager = pager_with_lines(3);
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+        let ran = Arc::new(AtomicBool::new(false));
+        let ran2 = ran.clone();
+        pager
+            .suspend_with(move || ran2.store(true, Ordering::SeqCst))
+            .unwrap();
+        harness.process_commands().unwrap();
+        assert!(ran.load(Ordering::SeqCst));
+        assert_eq!(harness.grid().row_text(0), "line 0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn edit() {
+        // The editor fails, so the error is shown at the prompt
+        std::env::set_var("VISUAL", "false");
+        let pager = pager_with_lines(3);
+        let mut harness = Harness::new(&pager, 40, 4).unwrap();
+        // Nothing is run until the application allows it
+        harness.send_text("v").unwrap();
+        assert_eq!(harness.state().message, None);
+
+        pager.allow_external_commands(true).unwrap();
+        harness.send_text("v").unwrap();
+        assert_eq!(
+            harness.grid().row_text(3),
+            "false failed with exit status: 1"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell() {
+        use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+        let path = std::env::temp_dir().join(format!("minus-harness-shell-{}", std::process::id()));
+        let pager = pager_with_lines(3);
+        let mut harness = Harness::new(&pager, 80, 4).unwrap();
+        // The prompt is not opened until the application allows running commands
+        harness.send_text("!").unwrap();
+        assert!(!harness.grid().row_text(3).starts_with('!'));
+
+        pager.allow_external_commands(true).unwrap();
+        // Type the command, then press Enter once to run it and once more to return to the pager
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        let typed = format!("!touch {}", path.display());
+        let keys = typed.chars().map(KeyCode::Char).map(key);
+        harness
+            .send_events(keys.chain([key(KeyCode::Enter), key(KeyCode::Enter)]))
+            .unwrap();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hint_commands() {
+        use crate::hints::HintAction;
+
+        let path = std::env::temp_dir().join(format!("minus-hint-{}.log", std::process::id()));
+        let pager = Pager::new();
+        pager
+            .push_str(format!("https://example.com\n{}:1\n", path.display()))
+            .unwrap();
+        pager
+            .set_hint_action(Some(HintAction::Command {
+                url: None,
+                location: Some("touch {path}".to_string()),
+            }))
+            .unwrap();
+        let mut harness = Harness::new(&pager, 80, 4).unwrap();
+
+        // URLs are not labelled since there is no command for them
+        harness.send_text("oa").unwrap();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+
+        pager
+            .set_hint_action(Some(HintAction::Command {
+                url: None,
+                location: Some("minus-no-such-command {path}".to_string()),
+            }))
+            .unwrap();
+        harness.send_text("oa").unwrap();
+        assert!(harness
+            .grid()
+            .row_text(3)
+            .starts_with("Failed to run minus-no-such-command"));
+    }
+
+    #[test]
+    fn save() {
+        use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+        let path = std::env::temp_dir().join(format!("minus-harness-save-{}", std::process::id()));
+        let name = path.display().to_string();
+        let pager = pager_with_lines(3);
+        let mut harness = Harness::new(&pager, 80, 5).unwrap();
+        // Press `s`, type the name of the file and confirm it
+        let save = |harness: &mut Harness| {
+            let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+            let typed = format!("s{name}");
+            let keys = typed.chars().map(KeyCode::Char).map(key);
+            harness
+                .send_events(keys.chain([key(KeyCode::Enter)]))
+                .unwrap();
+        };
+
+        save(&mut harness);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "line 0\nline 1\nline 2\n"
+        );
+        assert_eq!(
+            harness.grid().row_text(4),
+            format!("Saved 3 lines to {}", path.display())
+        );
+
+        // Existing files are not replaced
+        save(&mut harness);
+        assert!(harness.grid().row_text(4).starts_with("Failed to save to"));
+        std::fs::remove_file(&path).unwrap();
+
+        // The command can be turned off
+        pager.allow_saving(false).unwrap();
+        harness.send_text("s").unwrap();
+        assert!(!harness.grid().row_text(4).starts_with('>'));
+    }
+
+    #[test]
+    fn goto() {
+        let pager = Pager::new();
+        for i in 0..20 {
+            // Each line is 13 bytes long and wraps onto two rows
+            pager
+                .push_str(format!("line {i:02} {}\n", "x".repeat(4)))
+                .unwrap();
+        }
+        let mut harness = Harness::new(&pager, 8, 5).unwrap();
+
+        harness.send_keys(&[":", "5", "enter"]).unwrap();
+        assert_eq!(harness.state().upper_mark, 8);
+        assert_eq!(harness.grid().row_text(0), "line 04");
+
+        harness.send_keys(&[":", "2", "5", "%", "enter"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 05");
+
+        harness.send_keys(&[":", "3", "9", "b", "enter"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 03");
+
+        // A cancelled prompt stays at the same position
+        harness.send_keys(&[":", "9", "esc"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 03");
+
+        harness.send_keys(&[":", "x", "enter"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 03");
+        assert!(harness.state().message.is_some());
+        harness.send_keys(&["enter"]).unwrap();
+
+        harness.send_keys(&["5", "0", "%"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 10");
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn filter() {
+        let pager = pager_with_lines(20);
+        pager.set_prompt("prompt").unwrap();
+        let mut harness = Harness::new(&pager, 20, 4).unwrap();
+
+        harness
+            .send_keys(&["&", "1", "[", "4", "5", "]", "enter"])
+            .unwrap();
+        assert_eq!(
+            harness.grid().lines(),
+            vec!["line 14", "line 15", "", "prompt"]
+        );
+
+        harness
+            .send_keys(&["&", "!", "[", "0", "dash", "7", "]", "$", "enter"])
+            .unwrap();
+        assert_eq!(
+            harness.grid().lines(),
+            vec!["line 9", "line 18", "line 19", "prompt"]
+        );
+
+        // Lines pushed later are filtered too
+        pager.push_str("line 28\nline 30\n").unwrap();
+        harness.process_commands().unwrap();
+        assert_eq!(harness.state().screen.formatted_lines_count(), 5);
+
+        // An empty pattern removes the filter
+        harness.send_keys(&["&", "enter", "g"]).unwrap();
+        assert_eq!(harness.grid().row_text(0), "line 0");
+        assert_eq!(harness.state().screen.formatted_lines_count(), 22);
+    }
+}
+
+#[cfg(feature = "dynamic_output")]
+mod run_with {
+    use super::*;
+    use crate::{backend::EventSource, ExitStrategy, RunMode};
+    use crossterm::event::Event;
+    use std::{collections::VecDeque, io, time::Duration};
+
+    // Yields the given events and then waits forever like an idle terminal
+    struct Script(VecDeque<Event>);
+
+    impl Script {
+        fn keys(keys: &[&str]) -> Self {
+            Self(
+                keys.iter()
+                    .map(|k| Event::Key(crate::input::definitions::keydefs::parse_key_event(k)))
+                    .collect(),
+            )
+        }
+    }
+
+    impl EventSource for Script {
+        fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+            if self.0.is_empty() {
+                std::thread::sleep(timeout);
+                Ok(false)
+            } else {
+                Ok(true)
+            }
+        }
+
+        fn read(&mut self) -> io::Result<Event> {
+            Ok(self.0.pop_front().unwrap())
+        }
+    }
+
+    fn pager() -> Pager {
+        let pager = pager_with_lines(20);
+        pager.set_exit_strategy(ExitStrategy::PagerQuit).unwrap();
+        pager
+    }
+
+    #[test]
+    fn end_of_input() {
+        // The pager is not started at all if the text fits on the screen
+        let pager = pager_with_lines(3);
+        pager.end_of_input().unwrap();
+        let mut term = VirtualTerminal::new(20, 4);
+        pager
+            .run_with(RunMode::Dynamic, &mut term, Script::keys(&[]))
+            .unwrap();
+        assert_eq!(term.grid().lines(), ["line 0", "line 1", "line 2", ""]);
+        assert!(pager.running.lock().is_uninitialized());
+    }
+
+    #[test]
+    fn concurrent_and_sequential_pagers() {
+        let (p1, p2) = (pager(), pager());
+        let mut t1 = VirtualTerminal::new(20, 4);
+        let mut t2 = VirtualTerminal::new(20, 4);
+
+        std::thread::scope(|s| {
+            let h1 = s.spawn(|| p1.run_with(RunMode::Dynamic, &mut t1, Script::keys(&["j", "q"])));
+            let h2 = s.spawn(|| p2.run_with(RunMode::Dynamic, &mut t2, Script::keys(&["d", "q"])));
+            h1.join().unwrap().unwrap();
+            h2.join().unwrap().unwrap();
+        });
+        assert_eq!(t1.grid().row_text(0), "line 1");
+        assert_eq!(t2.grid().row_text(0), "line 2");
+
+        // The same pager can be started again once it has quit. Its configuration was consumed
+        // by the previous run hence it needs to be sent again
+        p1.set_exit_strategy(ExitStrategy::PagerQuit).unwrap();
+        p1.set_text("again").unwrap();
+        let mut t1 = VirtualTerminal::new(20, 4);
+        p1.run_with(RunMode::Dynamic, &mut t1, Script::keys(&["q"]))
+            .unwrap();
+        assert_eq!(t1.grid().row_text(0), "again");
+    }
+
+    // Fails to read any input
+    struct Broken;
+
+    impl EventSource for Broken {
+        fn poll(&mut self, _timeout: Duration) -> io::Result<bool> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "terminal closed"))
+        }
+
+        fn read(&mut self) -> io::Result<Event> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn input_error_is_returned() {
+        // The error must be reported instead of quitting the process
+        let pager = pager_with_lines(20);
+        pager.set_exit_strategy(ExitStrategy::ProcessQuit).unwrap();
+        let mut term = VirtualTerminal::new(20, 4);
+        let res = pager.run_with(RunMode::Dynamic, &mut term, Broken);
+        assert!(matches!(res, Err(crate::error::MinusError::HandleEvent(_))));
+        assert!(pager.running.lock().is_uninitialized());
+    }
+}
+
+#[cfg(feature = "async")]
+mod run_async {
+    use super::*;
+    use crate::{input::definitions::keydefs::parse_key_event, ExitStrategy};
+    use crossterm::event::Event;
+
+    fn key(k: &str) -> std::io::Result<Event> {
+        Ok(Event::Key(parse_key_event(k)))
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn push_with_backpressure() {
+        let pager = Pager::with_capacity(1);
+        pager.set_exit_strategy(ExitStrategy::PagerQuit).unwrap();
+        let mut term = VirtualTerminal::new(20, 4);
+        let (events_tx, events_rx) = flume::unbounded();
+
+        let feed = async {
+            for i in 0..20 {
+                pager.push_str_async(format!("line {i}\n")).await.unwrap();
+            }
+            events_tx.send(key("G")).unwrap();
+            events_tx.send(key("q")).unwrap();
+        };
+        let (res, ()) = tokio::join!(
+            pager.run_async_with(&mut term, events_rx.into_stream()),
+            feed
+        );
+        res.unwrap();
+        assert_eq!(term.grid().row_text(2), "line 19");
+    }
+
+    #[cfg(feature = "search")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn search_prompt_reads_from_stream() {
+        let pager = pager_with_lines(20);
+        pager.set_exit_strategy(ExitStrategy::PagerQuit).unwrap();
+        let mut term = VirtualTerminal::new(20, 4);
+        let events = ["/", "1", "5", "enter", "q"].map(key);
+
+        pager
+            .run_async_with(&mut term, futures_util::stream::iter(events))
+            .await
+            .unwrap();
+        assert_eq!(term.grid().row_text(0), "line 15");
+    }
+
+    #[test]
+    fn run_async_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let pager = Pager::new();
+        assert_send(&pager.run_async());
+    }
+}
@@ -37,7 +37,7 @@ mod pager_append_str {
         ps.append_str(TEXT1);
         ps.append_str(TEXT2);
         assert_eq!(ps.screen.formatted_lines, vec![format!("{TEXT1}{TEXT2}")]);
-        assert_eq!(ps.screen.orig_text, TEXT1.to_string() + TEXT2);
+        assert_eq!(ps.screen.orig_text(), TEXT1.to_string() + TEXT2);
     }
 
     #[test]
@@ -148,19 +148,19 @@ mod pager_append_str {
 
         ps.append_str(LINES[0]);
 
-        assert_eq!(ps.screen.orig_text, LINES[0].to_owned());
+        assert_eq!(ps.screen.orig_text(), LINES[0].to_owned());
         assert_eq!(ps.screen.formatted_lines, vec![LINES[0].to_owned()]);
 
         ps.append_str(LINES[1]);
 
         let line = LINES[..2].join("");
-        assert_eq!(ps.screen.orig_text, line);
+        assert_eq!(ps.screen.orig_text(), line);
         assert_eq!(ps.screen.formatted_lines, vec![line]);
 
         ps.append_str(LINES[2]);
 
         let mut line = LINES[..3].join("");
-        assert_eq!(ps.screen.orig_text, line);
+        assert_eq!(ps.screen.orig_text(), line);
 
         line.pop();
         assert_eq!(ps.screen.formatted_lines, vec![line]);
@@ -168,7 +168,7 @@ mod pager_append_str {
         ps.append_str(LINES[3]);
 
         let joined = LINES.join("");
-        assert_eq!(ps.screen.orig_text, joined);
+        assert_eq!(ps.screen.orig_text(), joined);
         assert_eq!(
             ps.screen.formatted_lines,
             joined
@@ -186,7 +186,7 @@ mod pager_append_str {
 
         ps.append_str(TEST);
 
-        assert_eq!(ps.screen.orig_text, TEST.to_owned());
+        assert_eq!(ps.screen.orig_text(), TEST.to_owned());
         assert_eq!(
             ps.screen.formatted_lines,
             TEST.lines()
@@ -194,10 +194,10 @@ mod pager_append_str {
                 .collect::<Vec<String>>()
         );
 
-        ps.screen.orig_text = TEST.to_string();
+        ps.screen.set_text(TEST).unwrap();
         ps.format_lines();
 
-        assert_eq!(ps.screen.orig_text, TEST.to_owned());
+        assert_eq!(ps.screen.orig_text(), TEST.to_owned());
         assert_eq!(
             ps.screen.formatted_lines,
             TEST.lines()
@@ -219,7 +219,7 @@ mod pager_append_str {
                 "but not at the end".to_owned()
             ]
         );
-        assert_eq!(ps.screen.orig_text, TEST.to_string());
+        assert_eq!(ps.screen.orig_text(), TEST.to_string());
     }
 }
 
@@ -244,7 +244,10 @@ fn exit_callback() {
 
 mod emit_events {
     // Check functions emit correct events on function calls
-    use crate::{minus_core::commands::Command, ExitStrategy, LineNumbers, Pager};
+    use crate::{
+        minus_core::commands::Command, screen::storage::MemoryStore, ExitStrategy, LineNumbers,
+        Pager,
+    };
 
     const TEST_STR: &str = "This is sample text";
     #[test]
@@ -288,7 +291,13 @@ mod emit_events {
     }
 
     #[test]
-    #[cfg(feature = "static_output")]
+    fn end_of_input() {
+        let pager = Pager::new();
+        pager.end_of_input().unwrap();
+        assert_eq!(Command::EndOfInput, pager.rx.try_recv().unwrap());
+    }
+
+    #[test]
     fn set_run_no_overflow() {
         let pager = Pager::new();
         pager.set_run_no_overflow(false).unwrap();
@@ -318,6 +327,61 @@ mod emit_events {
         );
     }
 
+    #[test]
+    fn set_storage() {
+        let pager = Pager::new();
+        pager.set_storage(MemoryStore::from(TEST_STR)).unwrap();
+        assert_eq!(
+            Command::SetStorage(Box::new(MemoryStore::new())),
+            pager.rx.try_recv().unwrap()
+        );
+    }
+
+    #[test]
+    fn set_max_formatted_rows() {
+        let pager = Pager::new();
+        pager.set_max_formatted_rows(Some(100)).unwrap();
+        assert_eq!(
+            Command::SetMaxFormattedRows(Some(100)),
+            pager.rx.try_recv().unwrap()
+        );
+    }
+
+    #[test]
+    fn set_max_lines() {
+        let pager = Pager::new();
+        pager.set_max_lines(Some(100)).unwrap();
+        assert_eq!(
+            Command::SetMaxLines(Some(100)),
+            pager.rx.try_recv().unwrap()
+        );
+    }
+
+    #[test]
+    fn split_view() {
+        let pager = Pager::new();
+        pager.split_view(true).unwrap();
+        assert_eq!(Command::SplitView(true), pager.rx.try_recv().unwrap());
+    }
+
+    #[test]
+    fn buffers() {
+        let pager = Pager::new();
+        pager.add_buffer("name", "text").unwrap();
+        pager.push_str_to_buffer("name", "more").unwrap();
+        pager.switch_buffer("name").unwrap();
+        pager.remove_buffer("name").unwrap();
+        assert_eq!(
+            pager.rx.try_iter().collect::<Vec<_>>(),
+            vec![
+                Command::AddBuffer("name".to_string(), "text".to_string()),
+                Command::AppendBufferData("name".to_string(), "more".to_string()),
+                Command::SwitchBuffer("name".to_string()),
+                Command::RemoveBuffer("name".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn add_exit_callback() {
         let func = Box::new(|| println!("Hello"));
@@ -327,3 +391,89 @@ mod emit_events {
         assert_eq!(Command::AddExitCallback(func), pager.rx.try_recv().unwrap());
     }
 }
+
+mod push_reader {
+    use crate::{error::MinusError, minus_core::commands::Command, Pager};
+    use std::io::{self, Read};
+
+    /// Returns at most `step` bytes on each read
+    struct Chunked<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    fn commands(pager: &Pager) -> Vec<Command> {
+        pager.rx.try_iter().collect()
+    }
+
+    fn appended(commands: &[Command]) -> String {
+        commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::AppendData(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn split_utf8_sequences() {
+        const TEXT: &str = "héllo wörld\n日本語\n";
+        let pager = Pager::new();
+        let reader = Chunked {
+            data: TEXT.as_bytes(),
+            step: 1,
+        };
+        pager.push_reader(reader).unwrap().join().unwrap().unwrap();
+
+        let commands = commands(&pager);
+        assert_eq!(commands.first(), Some(&Command::ReaderStarted));
+        assert_eq!(commands.last(), Some(&Command::ReaderFinished));
+        assert_eq!(appended(&commands), TEXT);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let pager = Pager::new();
+        let reader = Chunked {
+            data: b"a\xffb\xe6\x97",
+            step: 2,
+        };
+        pager.push_reader(reader).unwrap().join().unwrap().unwrap();
+
+        assert_eq!(appended(&commands(&pager)), "a\u{fffd}b\u{fffd}");
+    }
+
+    #[test]
+    fn read_error() {
+        let pager = Pager::new();
+        let res = pager.push_reader(Failing).unwrap().join().unwrap();
+
+        assert!(matches!(res, Err(MinusError::ReadInput(_))));
+        assert_eq!(
+            commands(&pager),
+            vec![
+                Command::ReaderStarted,
+                Command::SendMessage("Failed to read input: broken pipe".to_string()),
+                Command::ReaderFinished,
+            ]
+        );
+    }
+}